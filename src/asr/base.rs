@@ -1,3 +1,365 @@
-pub trait AsrProvider {
-    // Base trait for ASR providers
-}
\ No newline at end of file
+use std::path::{Path, PathBuf};
+use async_trait::async_trait;
+use serde::{Serialize, Deserialize};
+use thiserror::Error;
+
+/// ASR服务错误
+#[derive(Error, Debug)]
+pub enum AsrError {
+    #[error("ASR服务不可用: {0}")]
+    Unavailable(String),
+
+    #[error("请求失败: {0}")]
+    Request(String),
+
+    #[error("配额不足或请求过于频繁: {0}")]
+    QuotaExceeded(String),
+
+    #[error("认证失败: {0}")]
+    Unauthorized(String),
+
+    #[error("临时错误: {0}")]
+    Transient(String),
+
+    #[error("响应解析失败: {0}")]
+    InvalidResponse(String),
+
+    #[error("识别任务失败: {0}")]
+    TaskFailed(String),
+
+    #[error("识别超时: {0}")]
+    Timeout(String),
+
+    #[error("IO错误: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+impl AsrError {
+    /// 根据HTTP状态码构造错误
+    pub fn from_status(status: reqwest::StatusCode, body: &str) -> Self {
+        let message = format!("HTTP {}: {}", status.as_u16(), body.chars().take(200).collect::<String>());
+
+        match status.as_u16() {
+            429 => AsrError::QuotaExceeded(message),
+            401 | 403 => AsrError::Unauthorized(message),
+            500..=599 => AsrError::Transient(message),
+            _ => AsrError::Request(message),
+        }
+    }
+
+    /// 是否值得稍后重试
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, AsrError::Transient(_) | AsrError::Timeout(_) | AsrError::QuotaExceeded(_))
+    }
+}
+
+impl From<reqwest::Error> for AsrError {
+    fn from(err: reqwest::Error) -> Self {
+        if err.is_timeout() {
+            AsrError::Timeout(err.to_string())
+        } else if err.is_connect() {
+            AsrError::Transient(err.to_string())
+        } else if err.is_decode() {
+            AsrError::InvalidResponse(err.to_string())
+        } else if let Some(status) = err.status() {
+            AsrError::from_status(status, &err.to_string())
+        } else {
+            AsrError::Request(err.to_string())
+        }
+    }
+}
+
+/// 待识别的音频片段
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioSegment {
+    /// 片段文件路径
+    pub path: PathBuf,
+
+    /// 片段在原始音频中的起始位置（秒）
+    pub offset: f64,
+
+    /// 片段时长（秒），未知时为 None
+    pub duration: Option<f64>,
+
+    /// 片段开头与上一片段重叠的秒数，片段自身的内容从 offset + overlap 开始
+    pub overlap: f64,
+
+    /// 识别语言代码，None 表示由服务决定
+    pub language: Option<String>,
+}
+
+impl AudioSegment {
+    /// 创建新的音频片段
+    pub fn new(path: &Path, offset: f64, duration: Option<f64>) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            offset,
+            duration,
+            overlap: 0.0,
+            language: None,
+        }
+    }
+
+    /// 设置开头与上一片段重叠的秒数
+    pub fn with_overlap(mut self, overlap: f64) -> Self {
+        self.overlap = overlap.max(0.0);
+        self
+    }
+
+    /// 片段自身内容的起点（秒），不含向前重叠的部分
+    pub fn content_start(&self) -> f64 {
+        self.offset + self.overlap
+    }
+
+    /// 指定识别语言
+    pub fn with_language(mut self, language: Option<String>) -> Self {
+        self.language = language;
+        self
+    }
+
+    /// 片段文件名
+    pub fn file_name(&self) -> String {
+        self.path.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "未知片段".to_string())
+    }
+}
+
+/// 一个词的识别结果，时间相对于片段起点（秒）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Word {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    pub confidence: Option<f32>,
+}
+
+/// 一句识别结果，时间相对于片段起点（秒）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Utterance {
+    pub start: f64,
+    pub end: f64,
+    pub text: String,
+    pub confidence: Option<f32>,
+
+    /// 服务返回的说话人标识，只在同一片段内有意义
+    #[serde(default)]
+    pub speaker: Option<String>,
+
+    /// 逐词结果，服务不提供时为空
+    #[serde(default)]
+    pub words: Vec<Word>,
+}
+
+impl Utterance {
+    /// 创建新的识别语句
+    pub fn new(start: f64, end: f64, text: &str) -> Self {
+        Self {
+            start,
+            end,
+            text: text.trim().to_string(),
+            confidence: None,
+            speaker: None,
+            words: Vec::new(),
+        }
+    }
+}
+
+/// 是否为中日韩字符或全角标点
+pub fn is_cjk(c: char) -> bool {
+    matches!(c as u32, 0x3000..=0x303F | 0x4E00..=0x9FFF | 0x3400..=0x4DBF | 0xFF00..=0xFFEF)
+}
+
+/// 拼接各段文本，两侧都不是中文时用空格隔开，避免英文等单词在边界粘连
+pub fn join_segment_texts<S: AsRef<str>>(texts: &[S]) -> String {
+    let mut joined = String::new();
+    for text in texts {
+        let text = text.as_ref();
+        let needs_space = match (joined.chars().last(), text.chars().next()) {
+            (Some(prev), Some(next)) => !is_cjk(prev) && !is_cjk(next) && !prev.is_whitespace() && !next.is_whitespace(),
+            _ => false,
+        };
+        if needs_space {
+            joined.push(' ');
+        }
+        joined.push_str(text);
+    }
+    joined
+}
+
+/// 各词置信度的平均值，没有任何词带置信度时为 None
+pub fn mean_confidence(words: &[Word]) -> Option<f32> {
+    let values: Vec<f32> = words.iter().filter_map(|w| w.confidence).collect();
+    if values.is_empty() {
+        return None;
+    }
+    Some(values.iter().sum::<f32>() / values.len() as f32)
+}
+
+/// 多个服务结果不一致的一段文本，时间相对于片段起点（秒）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Disagreement {
+    pub start: f64,
+    pub end: f64,
+
+    /// 投票选中的文本
+    pub chosen: String,
+
+    /// 其他服务给出的文本，(服务名, 文本)
+    pub alternatives: Vec<(String, String)>,
+}
+
+/// 单个片段的识别结果
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Transcript {
+    /// 产生结果的服务名称
+    pub provider: String,
+
+    /// 识别出的语句
+    pub utterances: Vec<Utterance>,
+
+    /// 投票合并时各服务的分歧，单一服务的结果为空
+    #[serde(default)]
+    pub disagreements: Vec<Disagreement>,
+
+    /// 识别语言，服务返回的或请求时指定的
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+impl Transcript {
+    /// 创建新的识别结果
+    pub fn new(provider: &str, utterances: Vec<Utterance>) -> Self {
+        Self {
+            provider: provider.to_string(),
+            utterances,
+            disagreements: Vec::new(),
+            language: None,
+        }
+    }
+
+    /// 拼接所有语句的文本
+    pub fn text(&self) -> String {
+        let texts: Vec<&str> = self.utterances.iter()
+            .map(|u| u.text.as_str())
+            .filter(|t| !t.is_empty())
+            .collect();
+        join_segment_texts(&texts)
+    }
+
+    /// 是否没有任何有效文本
+    pub fn is_empty(&self) -> bool {
+        self.utterances.iter().all(|u| u.text.trim().is_empty())
+    }
+}
+
+/// ASR服务能力描述
+#[derive(Debug, Clone, Default)]
+pub struct AsrCapabilities {
+    /// 是否需要联网
+    pub requires_network: bool,
+
+    /// 是否返回逐句时间戳
+    pub timestamps: bool,
+
+    /// 是否支持在识别时传入热词
+    pub hotwords: bool,
+
+    /// 支持的语言代码，为空表示不限制
+    pub languages: Vec<String>,
+
+    /// 单个片段允许的最大时长（秒），None 表示不限制
+    pub max_segment_duration: Option<f64>,
+}
+
+/// ASR服务提供者
+#[async_trait]
+pub trait AsrProvider: Send + Sync {
+    /// 服务名称，用于日志和统计
+    fn name(&self) -> &str;
+
+    /// 服务能力
+    fn capabilities(&self) -> AsrCapabilities;
+
+    /// 影响识别结果的选项，作为缓存键的一部分
+    fn cache_fingerprint(&self) -> String {
+        String::new()
+    }
+
+    /// 服务当前是否可用
+    async fn is_available(&self) -> bool;
+
+    /// 识别一个音频片段
+    async fn recognize(&self, segment: &AudioSegment) -> Result<Transcript, AsrError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transcript_text_skips_empty_utterances() {
+        let transcript = Transcript::new("test", vec![
+            Utterance::new(0.0, 1.0, " 第一句 "),
+            Utterance::new(1.0, 2.0, "  "),
+            Utterance::new(2.0, 3.0, "第二句"),
+        ]);
+        assert_eq!(transcript.text(), "第一句第二句");
+        assert!(!transcript.is_empty());
+    }
+
+    #[test]
+    fn transcript_text_spaces_only_latin_words() {
+        let transcript = Transcript::new("test", vec![
+            Utterance::new(0.0, 1.0, "你好。"),
+            Utterance::new(1.0, 2.0, "我是主持人。"),
+            Utterance::new(2.0, 3.0, "Welcome"),
+            Utterance::new(3.0, 4.0, "back"),
+        ]);
+        assert_eq!(transcript.text(), "你好。我是主持人。Welcome back");
+    }
+
+    #[test]
+    fn join_segment_texts_keeps_latin_words_apart() {
+        assert_eq!(join_segment_texts(&["at the end", "of the next"]), "at the end of the next");
+    }
+
+    #[test]
+    fn join_segment_texts_does_not_space_chinese() {
+        assert_eq!(join_segment_texts(&["今天天气", "很好。", "Next one"]), "今天天气很好。Next one");
+    }
+
+    #[test]
+    fn transcript_without_text_is_empty() {
+        assert!(Transcript::new("test", Vec::new()).is_empty());
+        assert!(Transcript::new("test", vec![Utterance::new(0.0, 1.0, " ")]).is_empty());
+    }
+
+    #[test]
+    fn mean_confidence_skips_words_without_confidence() {
+        let word = |confidence| Word { start: 0.0, end: 1.0, text: "词".to_string(), confidence };
+        assert_eq!(mean_confidence(&[word(Some(0.5)), word(None), word(Some(1.0))]), Some(0.75));
+        assert_eq!(mean_confidence(&[word(None)]), None);
+        assert_eq!(mean_confidence(&[]), None);
+    }
+
+    #[test]
+    fn maps_http_status_to_error_kinds() {
+        use reqwest::StatusCode;
+
+        assert!(matches!(AsrError::from_status(StatusCode::TOO_MANY_REQUESTS, ""), AsrError::QuotaExceeded(_)));
+        assert!(matches!(AsrError::from_status(StatusCode::UNAUTHORIZED, ""), AsrError::Unauthorized(_)));
+        assert!(matches!(AsrError::from_status(StatusCode::FORBIDDEN, ""), AsrError::Unauthorized(_)));
+        assert!(matches!(AsrError::from_status(StatusCode::BAD_GATEWAY, ""), AsrError::Transient(_)));
+        assert!(matches!(AsrError::from_status(StatusCode::NOT_FOUND, ""), AsrError::Request(_)));
+
+        assert!(AsrError::from_status(StatusCode::SERVICE_UNAVAILABLE, "").is_retryable());
+        assert!(!AsrError::from_status(StatusCode::BAD_REQUEST, "").is_retryable());
+    }
+
+    #[test]
+    fn segment_file_name_comes_from_the_path() {
+        let segment = AudioSegment::new(Path::new("/tmp/parts/part_0001.mp3"), 30.0, Some(30.0));
+        assert_eq!(segment.file_name(), "part_0001.mp3");
+    }
+}
@@ -1,5 +1,393 @@
-use crate::asr::base::AsrProvider;
+use std::time::Duration;
+use async_trait::async_trait;
+use log::debug;
+use reqwest::RequestBuilder;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
 
+use crate::asr::base::{mean_confidence, AsrProvider, AsrCapabilities, AsrError, AudioSegment, Transcript, Utterance, Word};
+use crate::asr::http::{HttpClient, HttpResponse};
+
+/// 必剪接口默认地址
+pub const DEFAULT_BASE_URL: &str = "https://member.bilibili.com/x/bcut/rubick-interface";
+
+const USER_AGENT: &str = "Bilibili/1.0.0 (https://www.bilibili.com)";
+/// 上传和创建任务使用的模型
+const MODEL_ID: &str = "8";
+/// 查询结果使用的模型。必剪网页端查询任务时固定传 7，与创建任务时的模型无关，这里保持一致
+const RESULT_MODEL_ID: &str = "7";
+
+/// 任务状态: 识别完成
+const STATE_COMPLETE: i64 = 4;
+/// 任务状态: 识别失败
+const STATE_FAILED: i64 = 3;
+
+/// 必剪接口的通用响应
+#[derive(Debug, Deserialize)]
+struct BcutResponse<T> {
+    code: i64,
+    #[serde(default)]
+    message: Option<String>,
+    data: Option<T>,
+}
+
+/// 申请上传返回的数据
+#[derive(Debug, Deserialize)]
+struct UploadSlot {
+    in_boss_key: String,
+    resource_id: String,
+    upload_id: String,
+    upload_urls: Vec<String>,
+    per_size: usize,
+}
+
+/// 提交上传返回的数据
+#[derive(Debug, Deserialize)]
+struct CommitResult {
+    download_url: String,
+}
+
+/// 创建任务返回的数据
+#[derive(Debug, Deserialize)]
+struct TaskCreated {
+    task_id: String,
+}
+
+/// 查询任务返回的数据
+#[derive(Debug, Deserialize)]
+struct TaskResult {
+    state: i64,
+    #[serde(default)]
+    result: Option<String>,
+    #[serde(default)]
+    remark: Option<String>,
+}
+
+/// 识别结果中的一句话，时间单位为毫秒
+#[derive(Debug, Deserialize)]
+struct BcutUtterance {
+    transcript: String,
+    start_time: u64,
+    end_time: u64,
+    #[serde(default)]
+    confidence: Option<f32>,
+    #[serde(default)]
+    words: Vec<BcutWord>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BcutWord {
+    label: String,
+    start_time: u64,
+    end_time: u64,
+    #[serde(default)]
+    confidence: Option<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BcutResult {
+    #[serde(default)]
+    utterances: Vec<BcutUtterance>,
+}
+
+/// 必剪ASR服务
 pub struct BcutProvider {
-    // BCut ASR provider implementation
-}
\ No newline at end of file
+    client: HttpClient,
+    cookie: Option<String>,
+    base_url: String,
+    poll_interval: Duration,
+    max_polls: u32,
+}
+
+impl Default for BcutProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BcutProvider {
+    /// 创建新的必剪ASR服务
+    pub fn new() -> Self {
+        Self::with_base_url(DEFAULT_BASE_URL)
+    }
+
+    /// 使用指定的接口地址创建必剪ASR服务
+    pub fn with_base_url(base_url: &str) -> Self {
+        Self {
+            client: HttpClient::default(),
+            cookie: None,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            poll_interval: Duration::from_secs(1),
+            max_polls: 500,
+        }
+    }
+
+    /// 使用共享的HTTP客户端
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// 设置随接口请求发送的 Cookie
+    pub fn with_cookie(mut self, cookie: Option<String>) -> Self {
+        self.cookie = cookie;
+        self
+    }
+
+    /// 加上登录凭据
+    fn authorize(&self, builder: RequestBuilder) -> RequestBuilder {
+        match &self.cookie {
+            Some(cookie) => builder.header(reqwest::header::COOKIE, cookie),
+            None => builder,
+        }
+    }
+
+    /// 设置查询结果的间隔和最大次数
+    pub fn with_polling(mut self, poll_interval: Duration, max_polls: u32) -> Self {
+        self.poll_interval = poll_interval;
+        self.max_polls = max_polls.max(1);
+        self
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url, path)
+    }
+
+    /// 解析必剪的通用响应，按业务码区分错误类型
+    fn parse_response<T: DeserializeOwned>(response: HttpResponse) -> Result<T, AsrError> {
+        let status = response.status();
+        let body = response.text();
+
+        if !status.is_success() {
+            return Err(AsrError::from_status(status, &body));
+        }
+
+        let parsed: BcutResponse<T> = serde_json::from_str(&body)
+            .map_err(|e| AsrError::InvalidResponse(format!("{}: {}", e, body)))?;
+
+        if parsed.code != 0 {
+            let message = format!("code {}: {}", parsed.code, parsed.message.unwrap_or_default());
+            return Err(code_error(parsed.code, message));
+        }
+
+        parsed.data
+            .ok_or_else(|| AsrError::InvalidResponse("响应缺少data字段".to_string()))
+    }
+
+    async fn post_json<T: DeserializeOwned>(&self, path: &str, body: &Value) -> Result<T, AsrError> {
+        let response = self.client.execute(
+            self.authorize(self.client.post(self.url(path)))
+                .header(reqwest::header::USER_AGENT, self.client.user_agent().unwrap_or(USER_AGENT))
+                .json(body)
+        ).await?;
+
+        Self::parse_response(response)
+    }
+
+    /// 申请上传、分块上传并提交，返回音频的下载地址
+    async fn upload(&self, segment: &AudioSegment, data: &[u8]) -> Result<String, AsrError> {
+        let file_name = segment.file_name();
+        let file_type = segment.path.extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("mp3")
+            .to_lowercase();
+
+        let slot: UploadSlot = self.post_json("/resource/create", &json!({
+            "type": 2,
+            "name": file_name,
+            "size": data.len(),
+            "ResourceFileType": file_type,
+            "model_id": MODEL_ID,
+        })).await?;
+
+        if slot.upload_urls.is_empty() || slot.per_size == 0 {
+            return Err(AsrError::InvalidResponse("上传地址为空".to_string()));
+        }
+        // 分块地址不够时剩下的音频不会上传，识别结果会被截断
+        if slot.upload_urls.len().saturating_mul(slot.per_size) < data.len() {
+            return Err(AsrError::InvalidResponse(format!(
+                "上传地址不足: {} 块 × {} 字节 < {} 字节",
+                slot.upload_urls.len(), slot.per_size, data.len()
+            )));
+        }
+
+        debug!("必剪申请上传成功: {} ({} 块)", slot.resource_id, slot.upload_urls.len());
+
+        let mut etags = Vec::with_capacity(slot.upload_urls.len());
+        for (i, upload_url) in slot.upload_urls.iter().enumerate() {
+            let start = (i * slot.per_size).min(data.len());
+            let end = ((i + 1) * slot.per_size).min(data.len());
+
+            let response = self.client.execute(
+                self.client.put(upload_url)
+                    .header(reqwest::header::USER_AGENT, self.client.user_agent().unwrap_or(USER_AGENT))
+                    .body(data[start..end].to_vec())
+            ).await?;
+
+            let status = response.status();
+            if !status.is_success() {
+                return Err(AsrError::from_status(status, &response.text()));
+            }
+
+            let etag = response.headers()
+                .get(reqwest::header::ETAG)
+                .and_then(|v| v.to_str().ok())
+                .unwrap_or_default()
+                .to_string();
+            etags.push(etag);
+        }
+
+        let commit: CommitResult = self.post_json("/resource/create/complete", &json!({
+            "InBossKey": slot.in_boss_key,
+            "ResourceId": slot.resource_id,
+            "Etags": etags.join(","),
+            "UploadId": slot.upload_id,
+            "model_id": MODEL_ID,
+        })).await?;
+
+        Ok(commit.download_url)
+    }
+
+    /// 创建识别任务
+    async fn create_task(&self, download_url: &str) -> Result<String, AsrError> {
+        let task: TaskCreated = self.post_json("/task", &json!({
+            "resource": download_url,
+            "model_id": MODEL_ID,
+        })).await?;
+
+        Ok(task.task_id)
+    }
+
+    /// 轮询任务结果直到完成
+    async fn wait_result(&self, task_id: &str) -> Result<BcutResult, AsrError> {
+        for _ in 0..self.max_polls {
+            let response = self.client.execute(
+                self.authorize(self.client.get(self.url("/task/result")))
+                    .header(reqwest::header::USER_AGENT, self.client.user_agent().unwrap_or(USER_AGENT))
+                    .query(&[("model_id", RESULT_MODEL_ID), ("task_id", task_id)])
+            ).await?;
+
+            let task: TaskResult = Self::parse_response(response)?;
+
+            match task.state {
+                STATE_COMPLETE => {
+                    let result = task.result.unwrap_or_default();
+                    return serde_json::from_str(&result)
+                        .map_err(|e| AsrError::InvalidResponse(format!("识别结果解析失败: {}", e)));
+                }
+                STATE_FAILED => {
+                    return Err(AsrError::TaskFailed(task.remark.unwrap_or_else(|| task_id.to_string())));
+                }
+                _ => tokio::time::sleep(self.poll_interval).await,
+            }
+        }
+
+        Err(AsrError::Timeout(format!("任务 {} 在 {} 次查询后仍未完成", task_id, self.max_polls)))
+    }
+}
+
+#[async_trait]
+impl AsrProvider for BcutProvider {
+    fn name(&self) -> &str {
+        "bcut"
+    }
+
+    fn capabilities(&self) -> AsrCapabilities {
+        AsrCapabilities {
+            requires_network: true,
+            timestamps: true,
+            hotwords: false,
+            languages: vec!["zh".to_string(), "en".to_string()],
+            max_segment_duration: None,
+        }
+    }
+
+    async fn is_available(&self) -> bool {
+        true
+    }
+
+    async fn recognize(&self, segment: &AudioSegment) -> Result<Transcript, AsrError> {
+        let data = tokio::fs::read(&segment.path).await?;
+
+        let download_url = self.upload(segment, &data).await?;
+        let task_id = self.create_task(&download_url).await?;
+        debug!("必剪任务已创建: {} ({})", task_id, segment.file_name());
+
+        let result = self.wait_result(&task_id).await?;
+
+        let utterances = result.utterances.iter()
+            .map(|u| {
+                let mut utterance = Utterance::new(u.start_time as f64 / 1000.0, u.end_time as f64 / 1000.0, &u.transcript);
+                utterance.words = u.words.iter()
+                    .map(|w| Word {
+                        start: w.start_time as f64 / 1000.0,
+                        end: w.end_time as f64 / 1000.0,
+                        text: w.label.trim().to_string(),
+                        confidence: w.confidence,
+                    })
+                    .collect();
+                // 没有整句置信度时取各词的平均值
+                utterance.confidence = u.confidence.or_else(|| mean_confidence(&utterance.words));
+                utterance
+            })
+            .collect();
+
+        Ok(Transcript::new(self.name(), utterances))
+    }
+}
+
+/// 按必剪的业务码区分错误类型
+fn code_error(code: i64, message: String) -> AsrError {
+    match code {
+        -101 | -111 => AsrError::Unauthorized(message),
+        -412 | -429 | -509 => AsrError::QuotaExceeded(message),
+        -500 | -503 | -504 => AsrError::Transient(message),
+        // -400 是请求参数错误，不是凭据问题
+        _ => AsrError::Request(message),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_business_codes_to_error_kinds() {
+        let error = |code| code_error(code, String::new());
+        assert!(matches!(error(-101), AsrError::Unauthorized(_)));
+        assert!(matches!(error(-111), AsrError::Unauthorized(_)));
+        assert!(matches!(error(-400), AsrError::Request(_)));
+        assert!(matches!(error(-412), AsrError::QuotaExceeded(_)));
+        assert!(matches!(error(-429), AsrError::QuotaExceeded(_)));
+        assert!(matches!(error(-509), AsrError::QuotaExceeded(_)));
+        assert!(matches!(error(-500), AsrError::Transient(_)));
+        assert!(matches!(error(-503), AsrError::Transient(_)));
+        assert!(matches!(error(-504), AsrError::Transient(_)));
+        assert!(matches!(error(-999), AsrError::Request(_)));
+    }
+
+    #[test]
+    fn parses_task_results() {
+        let task: BcutResponse<TaskResult> = serde_json::from_str(
+            r#"{"code":0,"data":{"state":4,"result":"{\"utterances\":[{\"transcript\":\"大家好\",\"start_time\":120,\"end_time\":980}]}"}}"#
+        ).unwrap();
+        let task = task.data.unwrap();
+        assert_eq!(task.state, STATE_COMPLETE);
+
+        let result: BcutResult = serde_json::from_str(&task.result.unwrap()).unwrap();
+        assert_eq!(result.utterances.len(), 1);
+        assert_eq!(result.utterances[0].transcript, "大家好");
+        assert_eq!((result.utterances[0].start_time, result.utterances[0].end_time), (120, 980));
+    }
+
+    #[test]
+    fn failed_tasks_carry_the_remark() {
+        let task: BcutResponse<TaskResult> = serde_json::from_str(
+            r#"{"code":0,"message":"0","data":{"state":3,"remark":"音频无法识别"}}"#
+        ).unwrap();
+        let task = task.data.unwrap();
+        assert_eq!(task.state, STATE_FAILED);
+        assert_eq!(task.remark.as_deref(), Some("音频无法识别"));
+    }
+}
@@ -0,0 +1,389 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+use anyhow::Result;
+use log::{info, warn, debug};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use walkdir::WalkDir;
+
+use crate::asr::base::Transcript;
+
+/// 淘汰时清理到上限的该比例，避免每次写入都触发淘汰
+const EVICT_TARGET_RATIO: f64 = 0.9;
+
+/// 超过该时间的临时文件视为中断的写入留下的，可以删除；
+/// 更新的临时文件可能属于其他线程正在进行的写入
+const STALE_TMP_AGE: Duration = Duration::from_secs(3600);
+
+/// 缓存配置
+#[derive(Debug, Clone)]
+pub struct CacheOptions {
+    /// 缓存目录
+    pub dir: PathBuf,
+    /// 缓存总大小上限（字节），0 表示不限制
+    pub max_bytes: u64,
+    /// 条目最长保留时间，None 表示不过期
+    pub max_age: Option<Duration>,
+}
+
+/// 磁盘上的缓存条目
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    content_hash: String,
+    profile: String,
+    transcript: Transcript,
+}
+
+/// 按片段内容寻址的识别结果缓存
+///
+/// 键由片段内容的 SHA-256、服务名和影响结果的选项组成，
+/// 内容不变时重新处理不会再次调用服务。超过大小上限时按最近使用时间淘汰。
+pub struct AsrCache {
+    options: CacheOptions,
+    total_bytes: Mutex<u64>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl AsrCache {
+    /// 打开缓存目录，并清理过期和超出上限的条目
+    pub fn open(options: CacheOptions) -> Result<Self> {
+        fs::create_dir_all(&options.dir)?;
+
+        let cache = Self {
+            options,
+            total_bytes: Mutex::new(0),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        };
+        cache.evict()?;
+
+        info!("ASR缓存目录: {} ({:.1} MB)",
+            cache.options.dir.display(),
+            *cache.total_bytes.lock().unwrap() as f64 / 1024.0 / 1024.0);
+
+        Ok(cache)
+    }
+
+    /// 计算片段文件内容的哈希
+    pub fn hash_file(path: &Path) -> Result<String> {
+        let data = fs::read(path)?;
+        Ok(hex::encode(Sha256::digest(&data)))
+    }
+
+    /// 条目文件路径，按哈希前两位分目录
+    fn entry_path(&self, content_hash: &str, profile: &str) -> PathBuf {
+        let key = hex::encode(Sha256::digest(format!("{}\n{}", content_hash, profile).as_bytes()));
+        self.options.dir.join(&key[..2]).join(format!("{}.json", key))
+    }
+
+    /// 查找缓存的识别结果
+    pub fn get(&self, content_hash: &str, profile: &str) -> Option<Transcript> {
+        let path = self.entry_path(content_hash, profile);
+
+        let entry = fs::read_to_string(&path).ok()
+            .and_then(|contents| serde_json::from_str::<CacheEntry>(&contents).ok())
+            .filter(|entry| entry.content_hash == content_hash && entry.profile == profile)
+            .filter(|_| !self.is_expired(&path));
+
+        match entry {
+            Some(entry) => {
+                // 更新修改时间，作为最近使用时间
+                if let Ok(file) = fs::File::options().append(true).open(&path) {
+                    let _ = file.set_modified(SystemTime::now());
+                }
+                debug!("缓存命中: {} [{}]", &content_hash[..12], profile);
+                Some(entry.transcript)
+            }
+            None => None,
+        }
+    }
+
+    /// 保存识别结果
+    pub fn put(&self, content_hash: &str, profile: &str, transcript: &Transcript) -> Result<()> {
+        let path = self.entry_path(content_hash, profile);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let contents = serde_json::to_string(&CacheEntry {
+            content_hash: content_hash.to_string(),
+            profile: profile.to_string(),
+            transcript: transcript.clone(),
+        })?;
+
+        // 先写到唯一的临时文件再改名，避免并发读到半个文件，
+        // 也避免同一条目的并发写入交错写进同一个临时文件
+        let dir = path.parent().unwrap_or(&self.options.dir);
+        let mut tmp_file = tempfile::Builder::new().prefix(".").suffix(".tmp").tempfile_in(dir)?;
+        tmp_file.write_all(contents.as_bytes())?;
+
+        let over_limit = {
+            let mut total = self.total_bytes.lock().unwrap();
+            // 覆盖已有条目时减去旧文件的大小
+            let replaced = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+            tmp_file.persist(&path)?;
+            *total = total.saturating_sub(replaced) + contents.len() as u64;
+            self.options.max_bytes > 0 && *total > self.options.max_bytes
+        };
+        if over_limit {
+            self.evict()?;
+        }
+
+        Ok(())
+    }
+
+    /// 记录一次命中或未命中
+    pub fn record_lookup(&self, hit: bool) {
+        if hit {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// 命中和未命中次数
+    pub fn stats(&self) -> (u64, u64) {
+        (self.hits.load(Ordering::Relaxed), self.misses.load(Ordering::Relaxed))
+    }
+
+    fn is_expired(&self, path: &Path) -> bool {
+        match (self.options.max_age, modified_age(path)) {
+            (Some(max_age), Some(age)) => age > max_age,
+            _ => false,
+        }
+    }
+
+    /// 删除过期条目和中断写入留下的临时文件，总大小超出上限时按最近使用时间淘汰
+    fn evict(&self) -> Result<()> {
+        let mut entries = Vec::new();
+        let mut removed = 0;
+
+        for entry in WalkDir::new(&self.options.dir).into_iter().filter_map(|e| e.ok()) {
+            if !entry.file_type().is_file() {
+                continue;
+            }
+            let path = entry.path();
+            let metadata = match entry.metadata() {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+
+            if path.extension().map(|e| e == "tmp").unwrap_or(false) {
+                let stale = modified_age(path).map(|age| age > STALE_TMP_AGE).unwrap_or(false);
+                if stale && fs::remove_file(path).is_ok() {
+                    removed += 1;
+                }
+                continue;
+            }
+
+            if self.is_expired(path) {
+                if fs::remove_file(path).is_ok() {
+                    removed += 1;
+                }
+                continue;
+            }
+
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            entries.push((modified, metadata.len(), path.to_path_buf()));
+        }
+
+        let mut total: u64 = entries.iter().map(|e| e.1).sum();
+        if self.options.max_bytes > 0 && total > self.options.max_bytes {
+            let target = (self.options.max_bytes as f64 * EVICT_TARGET_RATIO) as u64;
+            entries.sort_by_key(|e| e.0);
+
+            for (_, size, path) in &entries {
+                if total <= target {
+                    break;
+                }
+                match fs::remove_file(path) {
+                    Ok(()) => {
+                        total = total.saturating_sub(*size);
+                        removed += 1;
+                    }
+                    Err(e) => warn!("删除缓存条目失败 {}: {}", path.display(), e),
+                }
+            }
+        }
+
+        if removed > 0 {
+            debug!("已清理 {} 个缓存条目", removed);
+        }
+        *self.total_bytes.lock().unwrap() = total;
+
+        Ok(())
+    }
+}
+
+/// 文件距上次修改的时间
+fn modified_age(path: &Path) -> Option<Duration> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    SystemTime::now().duration_since(modified).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asr::base::Utterance;
+
+    fn open_cache(dir: &Path, max_bytes: u64) -> AsrCache {
+        AsrCache::open(CacheOptions { dir: dir.to_path_buf(), max_bytes, max_age: None }).unwrap()
+    }
+
+    fn transcript(text: &str) -> Transcript {
+        Transcript::new("mock", vec![Utterance::new(0.0, 1.0, text)])
+    }
+
+    fn set_age(path: &Path, age: Duration) {
+        let file = fs::File::options().append(true).open(path).unwrap();
+        file.set_modified(SystemTime::now() - age).unwrap();
+    }
+
+    #[test]
+    fn entries_are_keyed_by_content_and_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = open_cache(dir.path(), 0);
+
+        cache.put("hash", "bcut", &transcript("你好")).unwrap();
+        assert_eq!(cache.get("hash", "bcut").unwrap().text(), "你好");
+        assert!(cache.get("hash", "jianying").is_none());
+        assert!(cache.get("other", "bcut").is_none());
+
+        // 重新打开后仍然有效
+        let cache = open_cache(dir.path(), 0);
+        assert_eq!(cache.get("hash", "bcut").unwrap().text(), "你好");
+
+        cache.record_lookup(true);
+        cache.record_lookup(false);
+        cache.record_lookup(false);
+        assert_eq!(cache.stats(), (1, 2));
+    }
+
+    #[test]
+    fn hash_file_is_the_sha256_of_the_contents() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("part.mp3");
+        fs::write(&path, b"abc").unwrap();
+
+        assert_eq!(AsrCache::hash_file(&path).unwrap(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    }
+
+    #[test]
+    fn expired_entries_are_missed_and_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = CacheOptions { dir: dir.path().to_path_buf(), max_bytes: 0, max_age: Some(Duration::from_secs(3600)) };
+        let cache = AsrCache::open(options.clone()).unwrap();
+
+        cache.put("old", "mock", &transcript("旧的")).unwrap();
+        cache.put("new", "mock", &transcript("新的")).unwrap();
+        let old_path = cache.entry_path("old", "mock");
+        set_age(&old_path, Duration::from_secs(7200));
+        assert!(cache.get("old", "mock").is_none());
+
+        let cache = AsrCache::open(options).unwrap();
+        assert!(!old_path.exists());
+        assert_eq!(cache.get("new", "mock").unwrap().text(), "新的");
+    }
+
+    #[test]
+    fn evicts_least_recently_used_entries_over_the_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = open_cache(dir.path(), 0);
+        cache.put("a", "mock", &transcript("同样长度")).unwrap();
+        let entry_size = *cache.total_bytes.lock().unwrap();
+
+        let cache = open_cache(dir.path(), entry_size * 5 / 2);
+        cache.put("b", "mock", &transcript("同样长度")).unwrap();
+        set_age(&cache.entry_path("a", "mock"), Duration::from_secs(60));
+        set_age(&cache.entry_path("b", "mock"), Duration::from_secs(120));
+        // 读取会刷新最近使用时间，b 因此比 a 新
+        assert!(cache.get("b", "mock").is_some());
+
+        cache.put("c", "mock", &transcript("同样长度")).unwrap();
+        assert!(cache.get("a", "mock").is_none());
+        assert!(cache.get("b", "mock").is_some());
+        assert!(cache.get("c", "mock").is_some());
+        assert_eq!(*cache.total_bytes.lock().unwrap(), entry_size * 2);
+    }
+
+    #[test]
+    fn overwriting_an_entry_does_not_grow_the_total() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = open_cache(dir.path(), 0);
+
+        cache.put("hash", "mock", &transcript("你好")).unwrap();
+        let once = *cache.total_bytes.lock().unwrap();
+        cache.put("hash", "mock", &transcript("你好")).unwrap();
+
+        assert_eq!(*cache.total_bytes.lock().unwrap(), once);
+        assert_eq!(cache.get("hash", "mock").unwrap().text(), "你好");
+    }
+
+    #[test]
+    fn concurrent_puts_of_the_same_entry_leave_a_whole_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = open_cache(dir.path(), 0);
+        let long_text = "字".repeat(20_000);
+
+        std::thread::scope(|scope| {
+            for _ in 0..8 {
+                scope.spawn(|| cache.put("hash", "mock", &transcript(&long_text)).unwrap());
+            }
+        });
+
+        assert_eq!(cache.get("hash", "mock").unwrap().text(), long_text);
+        let leftovers = WalkDir::new(dir.path()).into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().extension().map(|ext| ext == "tmp").unwrap_or(false))
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+
+    #[test]
+    fn only_stale_temp_files_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let fresh = dir.path().join(".fresh.tmp");
+        let stale = dir.path().join(".stale.tmp");
+        fs::write(&fresh, b"{").unwrap();
+        fs::write(&stale, b"{").unwrap();
+        set_age(&stale, STALE_TMP_AGE + Duration::from_secs(60));
+
+        let cache = open_cache(dir.path(), 0);
+        assert!(fresh.exists());
+        assert!(!stale.exists());
+        // 未完成的写入不计入总大小
+        assert_eq!(*cache.total_bytes.lock().unwrap(), 0);
+    }
+
+    #[test]
+    fn eviction_does_not_break_puts_in_progress() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = open_cache(dir.path(), 4096);
+        let text = "字".repeat(500);
+
+        std::thread::scope(|scope| {
+            for worker in 0..4 {
+                let cache = &cache;
+                let text = &text;
+                scope.spawn(move || {
+                    for i in 0..50 {
+                        cache.put(&format!("{}-{}", worker, i), "mock", &transcript(text)).unwrap();
+                    }
+                });
+            }
+            scope.spawn(|| {
+                for _ in 0..200 {
+                    cache.evict().unwrap();
+                }
+            });
+        });
+
+        assert!(*cache.total_bytes.lock().unwrap() <= 4096);
+    }
+}
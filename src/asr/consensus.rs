@@ -0,0 +1,374 @@
+//! 多服务投票合并
+//!
+//! 参照 ROVER 的做法：以权重最高的结果为骨架，把其余结果逐个按编辑距离对齐到
+//! 同一组列上，然后逐列投票。中文按字、其他语言按词切分，标点跟随前一个词，
+//! 比较时忽略标点和大小写。得票相同时选支持者中权重最高的一方，
+//! 仍相同时按服务顺序。
+
+use std::collections::HashMap;
+
+use crate::asr::base::{Disagreement, Transcript, Utterance};
+
+/// 参与投票的一个结果
+pub struct Hypothesis<'a> {
+    pub weight: f64,
+    pub transcript: &'a Transcript,
+}
+
+/// 切分后的词
+#[derive(Debug, Clone)]
+struct Token {
+    /// 原文，包含跟随的标点
+    display: String,
+    /// 用于比较的形式
+    key: String,
+    /// 所属语句在原结果中的索引
+    utterance: usize,
+}
+
+/// 对齐后的一列，entries[i] 为第 i 个结果在该列的词索引，None 表示缺失
+#[derive(Debug, Clone)]
+struct Column {
+    entries: Vec<Option<usize>>,
+}
+
+/// 投票合并多个结果，hypotheses 按权重从高到低排列，第一个作为时间骨架；没有结果时返回空结果
+pub fn merge(provider: &str, hypotheses: &[Hypothesis]) -> Transcript {
+    if hypotheses.is_empty() {
+        return Transcript::new(provider, Vec::new());
+    }
+
+    let tokens: Vec<Vec<Token>> = hypotheses.iter().map(|h| tokenize(h.transcript)).collect();
+
+    let mut columns: Vec<Column> = (0..tokens[0].len())
+        .map(|i| Column { entries: vec![Some(i)] })
+        .collect();
+    for h in 1..tokens.len() {
+        columns = align(&columns, &tokens, h);
+    }
+
+    // 每列归属的骨架语句，插入的列跟随前一列
+    let reference = &hypotheses[0].transcript.utterances;
+    let mut column_utterance = Vec::with_capacity(columns.len());
+    let mut current = columns.iter()
+        .find_map(|c| c.entries[0])
+        .map(|i| tokens[0][i].utterance)
+        .unwrap_or(0);
+    for column in &columns {
+        if let Some(i) = column.entries[0] {
+            current = tokens[0][i].utterance;
+        }
+        column_utterance.push(current);
+    }
+
+    let winners: Vec<(Option<String>, bool)> = columns.iter()
+        .map(|c| vote(c, &tokens, hypotheses))
+        .collect();
+
+    let mut utterances = Vec::new();
+    for (u_idx, source) in reference.iter().enumerate() {
+        let parts: Vec<&str> = columns.iter().enumerate()
+            .filter(|(c, _)| column_utterance[*c] == u_idx)
+            .filter_map(|(c, _)| winners[c].0.as_deref())
+            .collect();
+        let text = join_tokens(&parts);
+        if !text.is_empty() {
+            let mut utterance = Utterance::new(source.start, source.end, &text);
+            utterance.confidence = source.confidence;
+            utterance.speaker = source.speaker.clone();
+            utterances.push(utterance);
+        }
+    }
+
+    // 相邻的分歧列合并为一段
+    let mut disagreements = Vec::new();
+    let mut c = 0;
+    while c < columns.len() {
+        if !winners[c].1 {
+            c += 1;
+            continue;
+        }
+
+        let u_idx = column_utterance[c];
+        let mut end = c;
+        while end < columns.len() && winners[end].1 && column_utterance[end] == u_idx {
+            end += 1;
+        }
+
+        let span = &columns[c..end];
+        let chosen: Vec<&str> = winners[c..end].iter().filter_map(|w| w.0.as_deref()).collect();
+        let chosen = join_tokens(&chosen);
+
+        let mut alternatives = Vec::new();
+        for (h, hypothesis) in hypotheses.iter().enumerate() {
+            let parts: Vec<&str> = span.iter()
+                .filter_map(|col| col.entries[h])
+                .map(|i| tokens[h][i].display.as_str())
+                .collect();
+            let text = join_tokens(&parts);
+            if normalize(&text) != normalize(&chosen) {
+                alternatives.push((hypothesis.transcript.provider.clone(), text));
+            }
+        }
+
+        let (start, end_time) = reference.get(u_idx)
+            .map(|u| (u.start, u.end))
+            .unwrap_or((0.0, 0.0));
+        disagreements.push(Disagreement { start, end: end_time, chosen, alternatives });
+        c = end;
+    }
+
+    let mut transcript = Transcript::new(provider, utterances);
+    transcript.disagreements = disagreements;
+    transcript.language = hypotheses.iter().find_map(|h| h.transcript.language.clone());
+    transcript
+}
+
+/// 对一列投票，返回选中的原文（None 表示该位置不输出）和是否存在分歧
+fn vote(column: &Column, tokens: &[Vec<Token>], hypotheses: &[Hypothesis]) -> (Option<String>, bool) {
+    // key -> (总权重, 最高单个权重, 首个支持者序号, 原文)
+    let mut candidates: HashMap<Option<&str>, (f64, f64, usize, Option<&str>)> = HashMap::new();
+
+    for (h, hypothesis) in hypotheses.iter().enumerate() {
+        let entry = column.entries.get(h).copied().flatten().map(|i| &tokens[h][i]);
+        let key = entry.map(|t| t.key.as_str());
+        let candidate = candidates.entry(key).or_insert((0.0, 0.0, h, entry.map(|t| t.display.as_str())));
+        candidate.0 += hypothesis.weight;
+        candidate.1 = candidate.1.max(hypothesis.weight);
+    }
+
+    let disputed = candidates.len() > 1;
+    let winner = candidates.values()
+        .max_by(|a, b| {
+            a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal)
+                .then(a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+                .then(b.2.cmp(&a.2))
+        })
+        .and_then(|c| c.3)
+        .map(str::to_string);
+
+    (winner, disputed)
+}
+
+/// 把第 h 个结果对齐到已有的列上，返回新的列
+fn align(columns: &[Column], tokens: &[Vec<Token>], h: usize) -> Vec<Column> {
+    let hyp = &tokens[h];
+    let (n, m) = (columns.len(), hyp.len());
+
+    let matches = |c: usize, t: usize| {
+        columns[c].entries.iter()
+            .enumerate()
+            .any(|(k, e)| e.map(|i| tokens[k][i].key == hyp[t].key).unwrap_or(false))
+    };
+
+    let mut cost = vec![vec![0u32; m + 1]; n + 1];
+    for (c, row) in cost.iter_mut().enumerate() {
+        row[0] = c as u32;
+    }
+    for (t, cell) in cost[0].iter_mut().enumerate() {
+        *cell = t as u32;
+    }
+    for c in 1..=n {
+        for t in 1..=m {
+            let substitute = cost[c - 1][t - 1] + if matches(c - 1, t - 1) { 0 } else { 1 };
+            let skip_column = cost[c - 1][t] + 1;
+            let insert = cost[c][t - 1] + 1;
+            cost[c][t] = substitute.min(skip_column).min(insert);
+        }
+    }
+
+    // 回溯，优先匹配
+    let mut aligned = Vec::with_capacity(n.max(m));
+    let (mut c, mut t) = (n, m);
+    while c > 0 || t > 0 {
+        if c > 0 && t > 0 && cost[c][t] == cost[c - 1][t - 1] + if matches(c - 1, t - 1) { 0 } else { 1 } {
+            let mut column = columns[c - 1].clone();
+            column.entries.resize(h, None);
+            column.entries.push(Some(t - 1));
+            aligned.push(column);
+            c -= 1;
+            t -= 1;
+        } else if c > 0 && cost[c][t] == cost[c - 1][t] + 1 {
+            let mut column = columns[c - 1].clone();
+            column.entries.resize(h + 1, None);
+            aligned.push(column);
+            c -= 1;
+        } else {
+            let mut entries = vec![None; h];
+            entries.push(Some(t - 1));
+            aligned.push(Column { entries });
+            t -= 1;
+        }
+    }
+
+    aligned.reverse();
+    aligned
+}
+
+/// 中文按字、其他按空白和标点切分
+fn tokenize(transcript: &Transcript) -> Vec<Token> {
+    let mut tokens: Vec<Token> = Vec::new();
+
+    for (u_idx, utterance) in transcript.utterances.iter().enumerate() {
+        let first = tokens.len();
+        let mut word = String::new();
+
+        let flush = |word: &mut String, tokens: &mut Vec<Token>| {
+            if !word.is_empty() {
+                tokens.push(Token {
+                    key: word.to_lowercase(),
+                    display: std::mem::take(word),
+                    utterance: u_idx,
+                });
+            }
+        };
+
+        for ch in utterance.text.chars() {
+            if is_cjk_char(ch) {
+                flush(&mut word, &mut tokens);
+                tokens.push(Token { display: ch.to_string(), key: ch.to_string(), utterance: u_idx });
+            } else if ch.is_alphanumeric() || ch == '\'' {
+                word.push(ch);
+            } else {
+                flush(&mut word, &mut tokens);
+                // 标点附在本句前一个词后面，句首标点丢弃
+                if !ch.is_whitespace() && tokens.len() > first {
+                    if let Some(last) = tokens.last_mut() {
+                        last.display.push(ch);
+                    }
+                }
+            }
+        }
+        flush(&mut word, &mut tokens);
+    }
+
+    tokens
+}
+
+/// 拼接词，两个非中文词之间补空格
+fn join_tokens(parts: &[&str]) -> String {
+    let mut text = String::new();
+    for part in parts {
+        let needs_space = match (text.chars().last(), part.chars().next()) {
+            (Some(prev), Some(next)) => !is_cjk_char(prev) && !is_cjk_char(next) && !prev.is_whitespace(),
+            _ => false,
+        };
+        if needs_space {
+            text.push(' ');
+        }
+        text.push_str(part);
+    }
+    text
+}
+
+/// 去掉标点、空白并转为小写，用于判断两段文本是否相同
+fn normalize(text: &str) -> String {
+    text.chars().filter(|c| c.is_alphanumeric()).collect::<String>().to_lowercase()
+}
+
+/// 中日韩文字，按单字投票
+fn is_cjk_char(c: char) -> bool {
+    matches!(c as u32, 0x4E00..=0x9FFF | 0x3400..=0x4DBF | 0x3040..=0x30FF | 0xAC00..=0xD7AF)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transcript(provider: &str, texts: &[&str]) -> Transcript {
+        let utterances = texts.iter().enumerate()
+            .map(|(i, text)| Utterance::new(i as f64, i as f64 + 1.0, text))
+            .collect();
+        Transcript::new(provider, utterances)
+    }
+
+    fn vote_on(results: &[(f64, &Transcript)]) -> Transcript {
+        let hypotheses: Vec<Hypothesis> = results.iter()
+            .map(|(weight, transcript)| Hypothesis { weight: *weight, transcript })
+            .collect();
+        merge("consensus", &hypotheses)
+    }
+
+    #[test]
+    fn no_hypotheses_yield_an_empty_transcript() {
+        let merged = merge("consensus", &[]);
+        assert_eq!(merged.provider, "consensus");
+        assert!(merged.is_empty());
+    }
+
+    #[test]
+    fn majority_overrules_the_skeleton() {
+        let (a, b, c) = (transcript("a", &["今天天汽很好"]), transcript("b", &["今天天气很好"]), transcript("c", &["今天天气很好"]));
+        let merged = vote_on(&[(1.0, &a), (1.0, &b), (1.0, &c)]);
+
+        assert_eq!(merged.text(), "今天天气很好");
+        assert_eq!(merged.disagreements, vec![Disagreement {
+            start: 0.0,
+            end: 1.0,
+            chosen: "气".to_string(),
+            alternatives: vec![("a".to_string(), "汽".to_string())],
+        }]);
+    }
+
+    #[test]
+    fn tie_goes_to_the_heaviest_supporter() {
+        let (a, b, c) = (transcript("a", &["see you"]), transcript("b", &["see you"]), transcript("c", &["see ya"]));
+        // 两边总权重都是 2，c 的单个权重更高
+        let merged = vote_on(&[(1.0, &a), (1.0, &b), (2.0, &c)]);
+
+        assert_eq!(merged.text(), "see ya");
+    }
+
+    #[test]
+    fn full_tie_goes_to_the_earlier_provider() {
+        let (a, b) = (transcript("a", &["see you"]), transcript("b", &["see ya"]));
+
+        assert_eq!(vote_on(&[(1.0, &a), (1.0, &b)]).text(), "see you");
+        assert_eq!(vote_on(&[(1.0, &b), (1.0, &a)]).text(), "see ya");
+    }
+
+    #[test]
+    fn inserted_and_deleted_words_are_voted_on() {
+        let a = transcript("a", &["we go to the park"]);
+        let b = transcript("b", &["we go the park"]);
+        let c = transcript("c", &["we go to the big park"]);
+        let merged = vote_on(&[(1.0, &a), (1.0, &b), (1.0, &c)]);
+
+        assert_eq!(merged.text(), "we go to the park");
+        assert_eq!(merged.disagreements.len(), 2);
+        assert_eq!(merged.disagreements[0].chosen, "to");
+        assert_eq!(merged.disagreements[0].alternatives, vec![("b".to_string(), String::new())]);
+        assert_eq!(merged.disagreements[1].chosen, "");
+        assert_eq!(merged.disagreements[1].alternatives, vec![("c".to_string(), "big".to_string())]);
+    }
+
+    #[test]
+    fn mixed_cjk_and_latin_ignores_case_and_punctuation() {
+        let a = transcript("a", &["我用 iPhone 拍照"]);
+        let b = transcript("b", &["我用iphone拍照。"]);
+        let c = transcript("c", &["我用 Android 拍照"]);
+
+        let merged = vote_on(&[(1.0, &a), (1.0, &b)]);
+        assert_eq!(merged.text(), "我用iPhone拍照");
+        assert!(merged.disagreements.is_empty());
+
+        let merged = vote_on(&[(1.0, &a), (1.0, &b), (1.0, &c)]);
+        assert_eq!(merged.text(), "我用iPhone拍照");
+        assert_eq!(merged.disagreements[0].chosen, "iPhone");
+        assert_eq!(merged.disagreements[0].alternatives, vec![("c".to_string(), "Android".to_string())]);
+    }
+
+    #[test]
+    fn adjacent_disputed_columns_form_one_disagreement() {
+        let (a, b) = (transcript("a", &["今天天气很好", "明天下雨"]), transcript("b", &["今天天汽狠好", "明天下雪"]));
+        let merged = vote_on(&[(2.0, &a), (1.0, &b)]);
+
+        assert_eq!(merged.text(), "今天天气很好明天下雨");
+        assert_eq!(merged.disagreements.len(), 2);
+        assert_eq!(merged.disagreements[0].chosen, "气很");
+        assert_eq!(merged.disagreements[0].alternatives, vec![("b".to_string(), "汽狠".to_string())]);
+        // 分歧不跨语句合并，时间取所在语句
+        assert_eq!((merged.disagreements[1].start, merged.disagreements[1].end), (1.0, 2.0));
+        assert_eq!(merged.disagreements[1].chosen, "雨");
+    }
+}
@@ -1,5 +1,41 @@
-use crate::asr::base::AsrProvider;
+use async_trait::async_trait;
 
+use crate::asr::base::{AsrProvider, AsrCapabilities, AsrError, AudioSegment, Transcript};
+
+/// Google ASR服务
+#[derive(Default)]
 pub struct GoogleProvider {
     // Google ASR provider implementation
-}
\ No newline at end of file
+}
+
+impl GoogleProvider {
+    /// 创建新的Google ASR服务
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl AsrProvider for GoogleProvider {
+    fn name(&self) -> &str {
+        "google"
+    }
+
+    fn capabilities(&self) -> AsrCapabilities {
+        AsrCapabilities {
+            requires_network: true,
+            timestamps: true,
+            hotwords: false,
+            languages: Vec::new(),
+            max_segment_duration: None,
+        }
+    }
+
+    async fn is_available(&self) -> bool {
+        false
+    }
+
+    async fn recognize(&self, _segment: &AudioSegment) -> Result<Transcript, AsrError> {
+        Err(AsrError::Unavailable("Google ASR尚未实现".to_string()))
+    }
+}
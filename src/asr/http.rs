@@ -0,0 +1,868 @@
+//! 各ASR服务共用的HTTP客户端，支持录制和回放
+//!
+//! 录制模式下照常发送请求，并把每次请求和响应保存为夹具目录下的一个JSON文件；
+//! 回放模式下不联网，按 方法 + URL（查询参数排序后）匹配录制的响应，
+//! 同一URL多次请求时按录制顺序依次返回，找不到匹配时请求失败。
+//! 请求头和请求体不参与匹配，因为其中含有时间戳和随机ID。
+//! 录制时响应头、JSON 请求体和响应体以及查询参数中 Set-Cookie、secret_access_key
+//! 等敏感值会被隐藏，回放时查询参数按同样的规则隐藏后再匹配。
+//!
+//! 代理、超时、User-Agent 和额外的根证书来自配置中的 http 段，
+//! 可按服务覆盖；设置相同的服务共用同一个底层客户端和连接池。
+//! 代理来自 HTTP_PROXY 等环境变量时，配置的 no_proxy 同样生效。
+
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use anyhow::{Result, Context, anyhow};
+use log::{info, warn, debug};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{RequestBuilder, StatusCode};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+
+use crate::asr::base::AsrError;
+use crate::core::credentials::{mask_secrets, mask_url_credentials};
+
+/// 选择夹具模式的环境变量
+pub const FIXTURES_MODE_ENV: &str = "ASR_HTTP_FIXTURES";
+
+/// 指定夹具目录的环境变量
+pub const FIXTURES_DIR_ENV: &str = "ASR_FIXTURES_DIR";
+
+/// 录制时保存的请求体上限，超过的只记录长度
+const MAX_RECORDED_REQUEST_BODY: usize = 64 * 1024;
+
+/// 默认连接超时（秒）
+pub const DEFAULT_CONNECT_TIMEOUT: f64 = 10.0;
+
+/// 默认请求超时（秒）
+pub const DEFAULT_TIMEOUT: f64 = 300.0;
+
+/// 默认读取超时（秒）
+pub const DEFAULT_READ_TIMEOUT: f64 = 120.0;
+
+/// 夹具模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixtureMode {
+    /// 正常联网
+    Off,
+    /// 联网并保存请求和响应
+    Record,
+    /// 只从夹具返回响应
+    Replay,
+}
+
+impl FixtureMode {
+    /// 从配置字符串解析模式
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "" | "off" | "none" => Some(FixtureMode::Off),
+            "record" => Some(FixtureMode::Record),
+            "replay" => Some(FixtureMode::Replay),
+            _ => None,
+        }
+    }
+}
+
+/// 配置中 http 段的设置，未写出的超时取默认值
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpOptions {
+    /// 代理地址；不设置时使用 HTTP_PROXY 等环境变量，"none" 表示不使用代理
+    pub proxy: Option<String>,
+    /// 不经过代理的主机，如 "localhost"、".example.com"、"10.0.0.0/8"
+    pub no_proxy: Vec<String>,
+    /// 连接超时（秒）
+    pub connect_timeout: Option<f64>,
+    /// 请求超时（秒），从发出请求到读完响应
+    pub timeout: Option<f64>,
+    /// 读取超时（秒），等待响应头或两次收到响应数据之间的最长间隔
+    pub read_timeout: Option<f64>,
+    /// 默认的 User-Agent
+    pub user_agent: Option<String>,
+    /// 额外信任的根证书（PEM 文件，可含多个证书）
+    pub ca_certs: Vec<PathBuf>,
+    /// 按服务名覆盖的设置
+    pub providers: HashMap<String, HttpOverride>,
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            no_proxy: Vec::new(),
+            connect_timeout: Some(DEFAULT_CONNECT_TIMEOUT),
+            timeout: Some(DEFAULT_TIMEOUT),
+            read_timeout: Some(DEFAULT_READ_TIMEOUT),
+            user_agent: None,
+            ca_certs: Vec::new(),
+            providers: HashMap::new(),
+        }
+    }
+}
+
+/// 单个服务覆盖的HTTP设置，未设置的项沿用 http 段的值
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HttpOverride {
+    pub proxy: Option<String>,
+    pub connect_timeout: Option<f64>,
+    pub timeout: Option<f64>,
+    pub read_timeout: Option<f64>,
+    pub user_agent: Option<String>,
+}
+
+/// 决定底层客户端的设置，相同设置的服务共用一个客户端
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+struct ClientSettings {
+    proxy: Option<String>,
+    connect_timeout: Option<f64>,
+    timeout: Option<f64>,
+    read_timeout: Option<f64>,
+    user_agent: Option<String>,
+}
+
+impl ClientSettings {
+    fn overridden(&self, over: &HttpOverride) -> Self {
+        Self {
+            proxy: over.proxy.clone().or_else(|| self.proxy.clone()),
+            connect_timeout: over.connect_timeout.or(self.connect_timeout),
+            timeout: over.timeout.or(self.timeout),
+            read_timeout: over.read_timeout.or(self.read_timeout),
+            user_agent: over.user_agent.clone().or_else(|| self.user_agent.clone()),
+        }
+    }
+
+    fn key(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout.filter(|s| *s > 0.0).map(Duration::from_secs_f64)
+    }
+}
+
+/// 从环境变量读取的代理设置，同一变量先取大写再取小写
+#[derive(Debug, Default, PartialEq)]
+struct EnvProxy {
+    http: Option<String>,
+    https: Option<String>,
+    no_proxy: Option<String>,
+}
+
+impl EnvProxy {
+    fn read(var: impl Fn(&str) -> Option<String>) -> Self {
+        let get = |name: &str| [name.to_uppercase(), name.to_lowercase()].iter()
+            .filter_map(|n| var(n))
+            .map(|v| v.trim().to_string())
+            .find(|v| !v.is_empty());
+        let all = get("all_proxy");
+
+        Self {
+            http: get("http_proxy").or_else(|| all.clone()),
+            https: get("https_proxy").or(all),
+            no_proxy: get("no_proxy"),
+        }
+    }
+}
+
+/// 环境变量中的代理，加上配置的 no_proxy；指定了代理后 reqwest 不再读取环境变量，
+/// 所以配置的 no_proxy 和环境变量中的 NO_PROXY 合并后一起使用
+fn env_proxies(env: &EnvProxy, no_proxy: &[String]) -> Result<Vec<reqwest::Proxy>> {
+    let exclusions = no_proxy.iter().cloned().chain(env.no_proxy.clone()).collect::<Vec<_>>().join(",");
+
+    let mut proxies = Vec::new();
+    if let Some(url) = &env.http {
+        proxies.push(reqwest::Proxy::http(url).with_context(|| format!("HTTP_PROXY 无效: {}", url))?);
+    }
+    if let Some(url) = &env.https {
+        proxies.push(reqwest::Proxy::https(url).with_context(|| format!("HTTPS_PROXY 无效: {}", url))?);
+    }
+    Ok(proxies.into_iter().map(|p| p.no_proxy(reqwest::NoProxy::from_string(&exclusions))).collect())
+}
+
+/// 按设置创建 reqwest 客户端
+fn build_client(settings: &ClientSettings, no_proxy: &[String], certs: &[reqwest::Certificate]) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder();
+
+    match settings.proxy.as_deref().map(str::trim) {
+        None | Some("") if no_proxy.is_empty() => {}
+        None | Some("") => {
+            for proxy in env_proxies(&EnvProxy::read(|name| std::env::var(name).ok()), no_proxy)? {
+                builder = builder.proxy(proxy);
+            }
+        }
+        Some(name) if matches!(name.to_lowercase().as_str(), "none" | "off") => {
+            builder = builder.no_proxy();
+        }
+        Some(url) => {
+            let proxy = reqwest::Proxy::all(url)
+                .with_context(|| format!("代理地址无效: {}", url))?
+                .no_proxy(reqwest::NoProxy::from_string(&no_proxy.join(",")));
+            builder = builder.proxy(proxy);
+        }
+    }
+    if let Some(secs) = settings.connect_timeout.filter(|s| *s > 0.0) {
+        builder = builder.connect_timeout(Duration::from_secs_f64(secs));
+    }
+    if let Some(secs) = settings.timeout.filter(|s| *s > 0.0) {
+        builder = builder.timeout(Duration::from_secs_f64(secs));
+    }
+    if let Some(user_agent) = &settings.user_agent {
+        builder = builder.user_agent(user_agent.as_str());
+    }
+    for cert in certs {
+        builder = builder.add_root_certificate(cert.clone());
+    }
+
+    builder.build().map_err(|e| anyhow!("创建HTTP客户端失败: {}", e))
+}
+
+/// 读取 PEM 格式的根证书
+fn load_certificates(paths: &[PathBuf]) -> Result<Vec<reqwest::Certificate>> {
+    let mut certs = Vec::new();
+    for path in paths {
+        let pem = fs::read(path)
+            .with_context(|| format!("无法读取根证书: {}", path.display()))?;
+        let bundle = reqwest::Certificate::from_pem_bundle(&pem)
+            .with_context(|| format!("根证书格式错误: {}", path.display()))?;
+        if bundle.is_empty() {
+            return Err(anyhow!("根证书文件中没有证书: {}", path.display()));
+        }
+        certs.extend(bundle);
+    }
+    Ok(certs)
+}
+
+/// 各服务共用的客户端池
+#[derive(Default)]
+struct ClientPool {
+    base: ClientSettings,
+    overrides: HashMap<String, HttpOverride>,
+    no_proxy: Vec<String>,
+    certs: Vec<reqwest::Certificate>,
+    clients: Mutex<HashMap<String, reqwest::Client>>,
+}
+
+impl ClientPool {
+    fn client(&self, settings: &ClientSettings) -> Result<reqwest::Client> {
+        let mut clients = self.clients.lock().unwrap();
+        if let Some(client) = clients.get(&settings.key()) {
+            return Ok(client.clone());
+        }
+        let client = build_client(settings, &self.no_proxy, &self.certs)?;
+        clients.insert(settings.key(), client.clone());
+        Ok(client)
+    }
+}
+
+/// 一次录制的请求和响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    method: String,
+    url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    request_body: Option<String>,
+    status: u16,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+    #[serde(default)]
+    body: String,
+}
+
+/// 夹具存储
+struct Fixtures {
+    mode: FixtureMode,
+    dir: PathBuf,
+    next_id: AtomicU64,
+    /// 回放队列，键为 方法 + 规范化的URL
+    replay: Mutex<HashMap<String, VecDeque<Interaction>>>,
+}
+
+impl Fixtures {
+    fn open(mode: FixtureMode, dir: &Path) -> Result<Self> {
+        let mut replay: HashMap<String, VecDeque<Interaction>> = HashMap::new();
+        let mut files: Vec<PathBuf> = match fs::read_dir(dir) {
+            Ok(entries) => entries.filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| p.extension().map(|e| e == "json").unwrap_or(false))
+                .collect(),
+            Err(_) if mode == FixtureMode::Record => Vec::new(),
+            Err(e) => return Err(anyhow!("无法读取夹具目录 {}: {}", dir.display(), e)),
+        };
+        files.sort();
+
+        match mode {
+            FixtureMode::Replay => {
+                for file in &files {
+                    let contents = fs::read_to_string(file)?;
+                    let interaction: Interaction = serde_json::from_str(&contents)
+                        .map_err(|e| anyhow!("夹具格式错误 {}: {}", file.display(), e))?;
+                    replay.entry(fixture_key(&interaction.method, &interaction.url))
+                        .or_default()
+                        .push_back(interaction);
+                }
+                info!("HTTP回放模式: 从 {} 载入 {} 条记录", dir.display(), files.len());
+            }
+            FixtureMode::Record => {
+                fs::create_dir_all(dir)?;
+                if !files.is_empty() {
+                    warn!("夹具目录已有 {} 条记录，新记录将追加在后面: {}", files.len(), dir.display());
+                }
+                info!("HTTP录制模式: 保存到 {}", dir.display());
+            }
+            FixtureMode::Off => {}
+        }
+
+        Ok(Self {
+            mode,
+            dir: dir.to_path_buf(),
+            next_id: AtomicU64::new(files.len() as u64 + 1),
+            replay: Mutex::new(replay),
+        })
+    }
+
+    fn take(&self, method: &str, url: &str) -> Option<Interaction> {
+        let mut replay = self.replay.lock().unwrap();
+        replay.get_mut(&fixture_key(method, url))?.pop_front()
+    }
+
+    fn save(&self, interaction: &Interaction) -> Result<()> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let path_part: String = reqwest::Url::parse(&interaction.url)
+            .map(|u| u.path().to_string())
+            .unwrap_or_default()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        let file = self.dir.join(format!("{:05}-{}{}.json", id, interaction.method, path_part.trim_end_matches('_')));
+
+        fs::write(&file, serde_json::to_string_pretty(interaction)?)?;
+        Ok(())
+    }
+}
+
+/// 服务返回的响应，响应体已完整读取
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// 响应体文本
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.body).to_string()
+    }
+}
+
+/// 各服务共用的HTTP客户端
+#[derive(Clone)]
+pub struct HttpClient {
+    client: reqwest::Client,
+    user_agent: Option<String>,
+    read_timeout: Option<Duration>,
+    fixtures: Option<Arc<Fixtures>>,
+    pool: Arc<ClientPool>,
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            user_agent: None,
+            read_timeout: None,
+            fixtures: None,
+            pool: Arc::new(ClientPool::default()),
+        }
+    }
+}
+
+impl HttpClient {
+    /// 根据配置创建客户端，配置未指定夹具模式或目录时读取环境变量；
+    /// 顶层的 http_proxy 优先于 http 段中的 proxy
+    pub fn from_config(config: &HashMap<String, Value>) -> Result<Self> {
+        let get = |key: &str, env: &str, unset: &[&str]| config.get(key)
+            .and_then(|v| v.as_str())
+            .filter(|v| !unset.contains(v))
+            .map(str::to_string)
+            .or_else(|| std::env::var(env).ok())
+            .unwrap_or_default();
+
+        let mode_name = get("http_fixtures_mode", FIXTURES_MODE_ENV, &["", "off"]);
+        let mode = FixtureMode::parse(&mode_name)
+            .ok_or_else(|| anyhow!("未知的HTTP夹具模式: {}", mode_name))?;
+
+        let fixtures = match mode {
+            FixtureMode::Off => None,
+            _ => {
+                let dir = get("http_fixtures_dir", FIXTURES_DIR_ENV, &[""]);
+                if dir.is_empty() {
+                    return Err(anyhow!("使用HTTP夹具时必须指定夹具目录"));
+                }
+                Some(Arc::new(Fixtures::open(mode, Path::new(&dir))?))
+            }
+        };
+
+        let mut options: HttpOptions = match config.get("http") {
+            Some(value) => serde_json::from_value(value.clone())
+                .map_err(|e| anyhow!("http 配置无效: {}", e))?,
+            None => HttpOptions::default(),
+        };
+        if let Some(proxy) = config.get("http_proxy").and_then(|v| v.as_str()).filter(|p| !p.is_empty()) {
+            options.proxy = Some(proxy.to_string());
+        }
+
+        let pool = ClientPool {
+            base: ClientSettings {
+                proxy: options.proxy,
+                connect_timeout: options.connect_timeout,
+                timeout: options.timeout,
+                read_timeout: options.read_timeout,
+                user_agent: options.user_agent,
+            },
+            overrides: options.providers,
+            no_proxy: options.no_proxy,
+            certs: load_certificates(&options.ca_certs)?,
+            clients: Mutex::new(HashMap::new()),
+        };
+        if let Some(proxy) = &pool.base.proxy {
+            info!("HTTP代理: {}", mask_url_credentials(proxy));
+        }
+
+        Ok(Self {
+            client: pool.client(&pool.base)?,
+            user_agent: pool.base.user_agent.clone(),
+            read_timeout: pool.base.read_timeout(),
+            fixtures,
+            pool: Arc::new(pool),
+        })
+    }
+
+    /// 按服务名取得客户端，服务有单独的HTTP设置时使用对应的底层客户端
+    pub fn for_provider(&self, name: &str) -> Result<Self> {
+        let over = match self.pool.overrides.get(name) {
+            Some(over) => over,
+            None => return Ok(self.clone()),
+        };
+        let settings = self.pool.base.overridden(over);
+
+        Ok(Self {
+            client: self.pool.client(&settings)?,
+            read_timeout: settings.read_timeout(),
+            user_agent: settings.user_agent,
+            fixtures: self.fixtures.clone(),
+            pool: Arc::clone(&self.pool),
+        })
+    }
+
+    /// 配置中指定的 User-Agent，未指定时服务使用各自的默认值
+    pub fn user_agent(&self) -> Option<&str> {
+        self.user_agent.as_deref()
+    }
+
+    pub fn get(&self, url: impl reqwest::IntoUrl) -> RequestBuilder {
+        self.client.get(url)
+    }
+
+    pub fn post(&self, url: impl reqwest::IntoUrl) -> RequestBuilder {
+        self.client.post(url)
+    }
+
+    pub fn put(&self, url: impl reqwest::IntoUrl) -> RequestBuilder {
+        self.client.put(url)
+    }
+
+    /// 发送请求并读取完整响应，按夹具模式录制或回放
+    pub async fn execute(&self, builder: RequestBuilder) -> Result<HttpResponse, AsrError> {
+        let request = builder.build()?;
+        let method = request.method().to_string();
+        let url = request.url().to_string();
+
+        let fixtures = match &self.fixtures {
+            Some(fixtures) => fixtures,
+            None => return self.send(request).await,
+        };
+
+        match fixtures.mode {
+            FixtureMode::Replay => {
+                let interaction = fixtures.take(&method, &url)
+                    .ok_or_else(|| AsrError::Request(format!("回放夹具中没有匹配的请求: {} {}", method, url)))?;
+                debug!("回放: {} {} -> {}", method, url, interaction.status);
+                interaction_response(&interaction)
+            }
+            FixtureMode::Record => {
+                let request_body = request.body()
+                    .and_then(|b| b.as_bytes())
+                    .map(|b| if b.len() <= MAX_RECORDED_REQUEST_BODY {
+                        recorded_body(&String::from_utf8_lossy(b))
+                    } else {
+                        format!("<{} 字节>", b.len())
+                    });
+
+                let response = self.send(request).await?;
+                let interaction = Interaction {
+                    method,
+                    url: recorded_url(&url),
+                    request_body,
+                    status: response.status.as_u16(),
+                    headers: recorded_headers(&response.headers),
+                    body: recorded_body(&response.text()),
+                };
+                if let Err(e) = fixtures.save(&interaction) {
+                    warn!("保存HTTP夹具失败: {}", e);
+                }
+                Ok(response)
+            }
+            FixtureMode::Off => self.send(request).await,
+        }
+    }
+
+    /// 发送请求并读取响应，等待响应头和每块响应数据都受读取超时限制
+    async fn send(&self, request: reqwest::Request) -> Result<HttpResponse, AsrError> {
+        let mut response = self.with_read_timeout(self.client.execute(request)).await??;
+        let status = response.status();
+        let headers = response.headers().clone();
+
+        let mut body = Vec::new();
+        while let Some(chunk) = self.with_read_timeout(response.chunk()).await?? {
+            body.extend_from_slice(&chunk);
+        }
+
+        Ok(HttpResponse { status, headers, body })
+    }
+
+    async fn with_read_timeout<T>(&self, future: impl std::future::Future<Output = T>) -> Result<T, AsrError> {
+        match self.read_timeout {
+            Some(limit) => tokio::time::timeout(limit, future).await
+                .map_err(|_| AsrError::Timeout(format!("{} 秒内没有收到响应数据", limit.as_secs_f64()))),
+            None => Ok(future.await),
+        }
+    }
+}
+
+/// 键名表示敏感信息时隐藏值
+fn masked(key: &str, value: String) -> String {
+    match mask_secrets(key, &Value::String(value)) {
+        Value::String(v) => v,
+        other => other.to_string(),
+    }
+}
+
+/// 录制用的响应头，Set-Cookie 之类的敏感值会被隐藏
+fn recorded_headers(headers: &HeaderMap) -> HashMap<String, String> {
+    headers.iter()
+        .filter_map(|(k, v)| v.to_str().ok().map(|v| (k.to_string(), v.to_string())))
+        .map(|(k, v)| {
+            let value = masked(&k, v);
+            (k, value)
+        })
+        .collect()
+}
+
+/// 录制用的请求体或响应体，JSON 中敏感键下的值会被隐藏，其他内容原样保存
+fn recorded_body(body: &str) -> String {
+    match serde_json::from_str::<Value>(body) {
+        Ok(value @ (Value::Object(_) | Value::Array(_))) => mask_secrets("", &value).to_string(),
+        _ => body.to_string(),
+    }
+}
+
+/// 录制用的URL，token 之类的查询参数会被隐藏
+fn recorded_url(url: &str) -> String {
+    let mut parsed = match reqwest::Url::parse(url) {
+        Ok(parsed) if parsed.query().is_some() => parsed,
+        _ => return url.to_string(),
+    };
+    let pairs: Vec<(String, String)> = parsed.query_pairs()
+        .map(|(k, v)| (k.to_string(), masked(&k, v.to_string())))
+        .collect();
+    parsed.query_pairs_mut().clear().extend_pairs(pairs);
+    parsed.to_string()
+}
+
+/// 把录制的记录还原为响应
+fn interaction_response(interaction: &Interaction) -> Result<HttpResponse, AsrError> {
+    let status = StatusCode::from_u16(interaction.status)
+        .map_err(|e| AsrError::InvalidResponse(format!("夹具状态码无效: {}", e)))?;
+
+    let mut headers = HeaderMap::new();
+    for (name, value) in &interaction.headers {
+        if let (Ok(name), Ok(value)) = (HeaderName::from_bytes(name.as_bytes()), HeaderValue::from_str(value)) {
+            headers.insert(name, value);
+        }
+    }
+
+    Ok(HttpResponse {
+        status,
+        headers,
+        body: interaction.body.as_bytes().to_vec(),
+    })
+}
+
+/// 匹配键：方法 + 查询参数排序后的URL，敏感的查询参数按录制时的规则隐藏
+fn fixture_key(method: &str, url: &str) -> String {
+    let url = match reqwest::Url::parse(&recorded_url(url)) {
+        Ok(url) => url,
+        Err(_) => return format!("{} {}", method, url),
+    };
+
+    let mut pairs: Vec<(String, String)> = url.query_pairs()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    pairs.sort();
+
+    let mut normalized = url.clone();
+    normalized.set_query(None);
+    if !pairs.is_empty() {
+        normalized.query_pairs_mut().extend_pairs(pairs);
+    }
+
+    format!("{} {}", method.to_uppercase(), normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_fixture_modes() {
+        assert_eq!(FixtureMode::parse(""), Some(FixtureMode::Off));
+        assert_eq!(FixtureMode::parse("Record"), Some(FixtureMode::Record));
+        assert_eq!(FixtureMode::parse("replay"), Some(FixtureMode::Replay));
+        assert_eq!(FixtureMode::parse("rewind"), None);
+    }
+
+    #[test]
+    fn fixture_key_ignores_query_order() {
+        assert_eq!(
+            fixture_key("get", "https://example.com/task?b=2&a=1"),
+            fixture_key("GET", "https://example.com/task?a=1&b=2"),
+        );
+        assert_ne!(
+            fixture_key("GET", "https://example.com/task?a=1"),
+            fixture_key("POST", "https://example.com/task?a=1"),
+        );
+        assert_eq!(fixture_key("GET", "not a url"), "GET not a url");
+    }
+
+    #[test]
+    fn recorded_headers_hide_secrets() {
+        let mut headers = HeaderMap::new();
+        headers.insert("content-type", HeaderValue::from_static("application/json"));
+        headers.insert("set-cookie", HeaderValue::from_static("SESSDATA=abc; Path=/"));
+        headers.insert("authorization", HeaderValue::from_static("Bearer sk-123"));
+        headers.insert("x-api-key", HeaderValue::from_static("sk-456"));
+
+        let recorded = recorded_headers(&headers);
+        assert_eq!(recorded["content-type"], "application/json");
+        assert_eq!(recorded["set-cookie"], "******");
+        assert_eq!(recorded["authorization"], "******");
+        assert_eq!(recorded["x-api-key"], "******");
+    }
+
+    #[test]
+    fn providers_with_same_settings_share_a_client() {
+        let config = HashMap::from([("http".to_string(), serde_json::json!({
+            "proxy": "http://127.0.0.1:7890",
+            "no_proxy": ["localhost"],
+            "timeout": 30,
+            "user_agent": "asr-cli",
+            "providers": {
+                "bcut": { "proxy": "none" },
+                "kuaishou": { "proxy": "none" },
+                "openai": { "user_agent": "asr-cli-openai" }
+            }
+        }))]);
+        let client = HttpClient::from_config(&config).unwrap();
+        assert_eq!(client.user_agent(), Some("asr-cli"));
+
+        let bcut = client.for_provider("bcut").unwrap();
+        let kuaishou = client.for_provider("kuaishou").unwrap();
+        let openai = client.for_provider("openai").unwrap();
+        client.for_provider("jianying").unwrap();
+        assert_eq!(openai.user_agent(), Some("asr-cli-openai"));
+        assert_eq!(bcut.user_agent(), Some("asr-cli"));
+        // 默认、不走代理、单独UA 三种设置
+        assert_eq!(client.pool.clients.lock().unwrap().len(), 3);
+        assert!(Arc::ptr_eq(&bcut.pool, &kuaishou.pool));
+    }
+
+    #[test]
+    fn recorded_bodies_and_queries_hide_secrets() {
+        let body = r#"{"ret":"0","data":{"access_key_id":"AK","secret_access_key":"SK","session_token":"ST","region":"cn"}}"#;
+        let recorded: Value = serde_json::from_str(&recorded_body(body)).unwrap();
+        assert_eq!(recorded["data"]["secret_access_key"], "******");
+        assert_eq!(recorded["data"]["session_token"], "******");
+        assert_eq!(recorded["data"]["access_key_id"], "******");
+        assert_eq!(recorded["data"]["region"], "cn");
+        assert_eq!(recorded_body("1:abcdef"), "1:abcdef");
+
+        let url = recorded_url("https://example.com/task?task_id=t&access_token=abc");
+        assert_eq!(url, "https://example.com/task?task_id=t&access_token=******");
+        assert_eq!(
+            fixture_key("GET", "https://example.com/task?access_token=xyz&task_id=t"),
+            fixture_key("GET", &url),
+        );
+    }
+
+    #[tokio::test]
+    async fn recording_masks_credentials_in_saved_fixtures() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = socket.read(&mut buf).await;
+            let body = r#"{"data":{"secret_access_key":"live-secret"}}"#;
+            let response = format!("HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}", body.len(), body);
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let dir = tempfile::tempdir().unwrap();
+        let config = HashMap::from([
+            ("http".to_string(), serde_json::json!({ "proxy": "none" })),
+            ("http_fixtures_mode".to_string(), serde_json::json!("record")),
+            ("http_fixtures_dir".to_string(), serde_json::json!(dir.path().to_string_lossy())),
+        ]);
+        let client = HttpClient::from_config(&config).unwrap();
+        let url = format!("http://{}/sign?token=live-token", addr);
+        let response = client.execute(client.post(&url).json(&serde_json::json!({ "cookie": "SESSDATA=live" }))).await.unwrap();
+        // 调用方拿到的仍是原始响应
+        assert!(response.text().contains("live-secret"));
+
+        let file = fs::read_dir(dir.path()).unwrap().next().unwrap().unwrap().path();
+        let saved = fs::read_to_string(file).unwrap();
+        assert!(!saved.contains("live-"), "{}", saved);
+        assert!(saved.contains("secret_access_key"));
+    }
+
+    #[test]
+    fn partial_http_section_keeps_default_timeouts() {
+        let config = HashMap::from([("http".to_string(), serde_json::json!({ "proxy": "none" }))]);
+        let client = HttpClient::from_config(&config).unwrap();
+        let base = &client.pool.base;
+        assert_eq!(base.connect_timeout, Some(DEFAULT_CONNECT_TIMEOUT));
+        assert_eq!(base.timeout, Some(DEFAULT_TIMEOUT));
+        assert_eq!(client.read_timeout, Some(Duration::from_secs_f64(DEFAULT_READ_TIMEOUT)));
+    }
+
+    #[test]
+    fn environment_proxy_is_read_in_either_case() {
+        let vars = HashMap::from([
+            ("https_proxy", "http://10.0.0.1:3128"),
+            ("ALL_PROXY", "http://10.0.0.2:3128"),
+            ("NO_PROXY", "internal.example.com"),
+        ]);
+        let env = EnvProxy::read(|name| vars.get(name).map(|v| v.to_string()));
+        assert_eq!(env, EnvProxy {
+            http: Some("http://10.0.0.2:3128".to_string()),
+            https: Some("http://10.0.0.1:3128".to_string()),
+            no_proxy: Some("internal.example.com".to_string()),
+        });
+        assert_eq!(EnvProxy::read(|_| None), EnvProxy::default());
+    }
+
+    #[test]
+    fn configured_no_proxy_applies_to_environment_proxy() {
+        let env = EnvProxy {
+            http: Some("http://10.0.0.1:3128".to_string()),
+            https: Some("http://10.0.0.1:3128".to_string()),
+            no_proxy: Some("internal.example.com".to_string()),
+        };
+        let proxies = env_proxies(&env, &["localhost".to_string(), ".corp.example.com".to_string()]).unwrap();
+        assert_eq!(proxies.len(), 2);
+        for proxy in &proxies {
+            let debug = format!("{:?}", proxy);
+            assert!(debug.contains("localhost") && debug.contains("corp.example.com") && debug.contains("internal.example.com"), "{}", debug);
+        }
+
+        let bad = EnvProxy { http: Some("::not a url::".to_string()), ..EnvProxy::default() };
+        assert!(env_proxies(&bad, &[]).is_err());
+    }
+
+    #[tokio::test]
+    async fn stalled_responses_hit_the_read_timeout() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            use tokio::io::AsyncWriteExt;
+            let (mut socket, _) = listener.accept().await.unwrap();
+            // 只发响应头和一部分响应体，然后停住
+            socket.write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 10\r\n\r\nhello").await.unwrap();
+            tokio::time::sleep(Duration::from_secs(5)).await;
+        });
+
+        let config = HashMap::from([("http".to_string(), serde_json::json!({ "proxy": "none", "read_timeout": 0.2 }))]);
+        let client = HttpClient::from_config(&config).unwrap();
+        let started = std::time::Instant::now();
+        let result = client.execute(client.get(format!("http://{}/", addr))).await;
+
+        assert!(matches!(result, Err(AsrError::Timeout(_))), "{:?}", result.map(|r| r.text()));
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn cli_proxy_overrides_config_and_bad_settings_fail() {
+        let config = HashMap::from([
+            ("http".to_string(), serde_json::json!({ "proxy": "none" })),
+            ("http_proxy".to_string(), serde_json::json!("http://10.0.0.1:3128")),
+        ]);
+        let client = HttpClient::from_config(&config).unwrap();
+        assert_eq!(client.pool.base.proxy.as_deref(), Some("http://10.0.0.1:3128"));
+
+        let bad_proxy = HashMap::from([("http".to_string(), serde_json::json!({ "proxy": "::not a url::" }))]);
+        assert!(HttpClient::from_config(&bad_proxy).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let cert = dir.path().join("ca.pem");
+        fs::write(&cert, "not a certificate").unwrap();
+        let bad_cert = HashMap::from([("http".to_string(), serde_json::json!({ "ca_certs": [cert] }))]);
+        assert!(HttpClient::from_config(&bad_cert).is_err());
+    }
+
+    #[tokio::test]
+    async fn replays_interactions_in_recorded_order() {
+        let dir = tempfile::tempdir().unwrap();
+        for (idx, body) in ["first", "second"].iter().enumerate() {
+            let interaction = Interaction {
+                method: "GET".to_string(),
+                url: "https://example.com/poll?id=1".to_string(),
+                request_body: None,
+                status: 200,
+                headers: HashMap::from([("content-type".to_string(), "text/plain".to_string())]),
+                body: body.to_string(),
+            };
+            fs::write(dir.path().join(format!("{:05}.json", idx + 1)), serde_json::to_string(&interaction).unwrap()).unwrap();
+        }
+        let client = HttpClient {
+            fixtures: Some(Arc::new(Fixtures::open(FixtureMode::Replay, dir.path()).unwrap())),
+            ..HttpClient::default()
+        };
+
+        let first = client.execute(client.get("https://example.com/poll?id=1")).await.unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(first.headers()["content-type"], "text/plain");
+        assert_eq!(first.text(), "first");
+        assert_eq!(client.execute(client.get("https://example.com/poll?id=1")).await.unwrap().text(), "second");
+        assert!(matches!(
+            client.execute(client.get("https://example.com/poll?id=1")).await,
+            Err(AsrError::Request(_))
+        ));
+    }
+}
@@ -1,5 +1,481 @@
-use crate::asr::base::AsrProvider;
+use std::path::Path;
+use std::time::Duration;
+use async_trait::async_trait;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use log::debug;
+use reqwest::RequestBuilder;
+use md5::{Md5, Digest};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::Sha256;
 
+use crate::asr::base::{AsrProvider, AsrCapabilities, AsrError, AudioSegment, Transcript, Utterance};
+use crate::asr::http::HttpClient;
+use crate::asr::utils::get_audio_duration;
+
+/// 剪映接口默认地址
+pub const DEFAULT_BASE_URL: &str = "https://lv-pc-api-sinfonlinec.ulikecam.com";
+
+/// 字节点播上传接口默认地址
+pub const DEFAULT_VOD_URL: &str = "https://vod.bytedanceapi.com";
+
+/// 默认设备ID
+pub const DEFAULT_DEVICE_ID: &str = "3943278516897751";
+
+const USER_AGENT: &str = "Cronet/TTNetVersion:01594da2 2023-03-14 QuicVersion:46688bb4 2022-11-28";
+const APP_VERSION: &str = "4.0.0";
+const PLATFORM: &str = "4";
+const SPACE_NAME: &str = "lv-mac-recognition";
+const VOD_REGION: &str = "cn";
+const VOD_SERVICE: &str = "vod";
+/// ApplyUploadInner 的 s 参数，取自剪映桌面客户端抓包，含义未公开；
+/// 不带该参数时点播接口不返回上传地址，所以原样照发
+const VOD_CLIENT_PARAM: &str = "5y0udbjapi";
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 上传凭证
+#[derive(Debug, Deserialize)]
+struct UploadToken {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: String,
+}
+
+/// 点播上传地址
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct UploadAddress {
+    store_infos: Vec<StoreInfo>,
+    upload_hosts: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct StoreInfo {
+    store_uri: String,
+    auth: String,
+    #[serde(rename = "UploadID")]
+    upload_id: String,
+}
+
+/// 识别结果中的一句话，时间单位为毫秒
+#[derive(Debug, Deserialize)]
+struct JianyingUtterance {
+    text: String,
+    start_time: u64,
+    end_time: u64,
+}
+
+/// 剪映ASR服务
 pub struct JianyingProvider {
-    // Jianying ASR provider implementation
-}
\ No newline at end of file
+    client: HttpClient,
+    cookie: Option<String>,
+    base_url: String,
+    vod_url: String,
+    device_id: String,
+    poll_interval: Duration,
+    max_polls: u32,
+}
+
+impl Default for JianyingProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JianyingProvider {
+    /// 创建新的剪映ASR服务
+    pub fn new() -> Self {
+        Self::with_endpoints(DEFAULT_BASE_URL, DEFAULT_VOD_URL)
+    }
+
+    /// 使用指定的接口地址创建剪映ASR服务
+    pub fn with_endpoints(base_url: &str, vod_url: &str) -> Self {
+        Self {
+            client: HttpClient::default(),
+            cookie: None,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            vod_url: vod_url.trim_end_matches('/').to_string(),
+            device_id: DEFAULT_DEVICE_ID.to_string(),
+            poll_interval: Duration::from_secs(1),
+            max_polls: 300,
+        }
+    }
+
+    /// 使用共享的HTTP客户端
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// 设置随接口请求发送的 Cookie
+    pub fn with_cookie(mut self, cookie: Option<String>) -> Self {
+        self.cookie = cookie;
+        self
+    }
+
+    /// 加上登录凭据
+    fn authorize(&self, builder: RequestBuilder) -> RequestBuilder {
+        match &self.cookie {
+            Some(cookie) => builder.header(reqwest::header::COOKIE, cookie),
+            None => builder,
+        }
+    }
+
+    /// 设置设备ID
+    pub fn with_device_id(mut self, device_id: &str) -> Self {
+        if !device_id.is_empty() {
+            self.device_id = device_id.to_string();
+        }
+        self
+    }
+
+    /// 设置查询结果的间隔和最大次数
+    pub fn with_polling(mut self, poll_interval: Duration, max_polls: u32) -> Self {
+        self.poll_interval = poll_interval;
+        self.max_polls = max_polls.max(1);
+        self
+    }
+
+    /// 生成剪映接口签名，返回 (sign, device_time)
+    fn sign_request(&self, path: &str) -> (String, String) {
+        let device_time = Utc::now().timestamp().to_string();
+        let sign = request_sign(path, &device_time, &self.device_id);
+        (sign, device_time)
+    }
+
+    /// 向剪映接口发送签名请求
+    async fn post_signed(&self, path: &str, body: &Value) -> Result<Value, AsrError> {
+        let (sign, device_time) = self.sign_request(path);
+
+        let response = self.client.execute(
+            self.authorize(self.client.post(format!("{}{}", self.base_url, path)))
+                .header(reqwest::header::USER_AGENT, self.client.user_agent().unwrap_or(USER_AGENT))
+                .header("appvr", APP_VERSION)
+                .header("device-time", device_time)
+                .header("pf", PLATFORM)
+                .header("sign", sign)
+                .header("sign-ver", "1")
+                .header("tdid", &self.device_id)
+                .json(body)
+        ).await?;
+
+        let status = response.status();
+        let text = response.text();
+        if !status.is_success() {
+            return Err(AsrError::from_status(status, &text));
+        }
+
+        let value: Value = serde_json::from_str(&text)
+            .map_err(|e| AsrError::InvalidResponse(format!("{}: {}", e, text)))?;
+
+        let ret = value.get("ret")
+            .map(|r| r.as_str().map(str::to_string).unwrap_or_else(|| r.to_string()))
+            .unwrap_or_else(|| "0".to_string());
+        // ret 的具体含义没有公开，非 0 一律按请求错误处理
+        if ret != "0" {
+            let message = format!("ret {}: {}", ret, value.get("errmsg").and_then(|m| m.as_str()).unwrap_or(""));
+            return Err(AsrError::Request(message));
+        }
+
+        Ok(value)
+    }
+
+    /// 申请上传凭证
+    async fn upload_sign(&self) -> Result<UploadToken, AsrError> {
+        let value = self.post_signed("/lv/v1/upload_sign", &json!({ "biz": "pc-recognition" })).await?;
+
+        serde_json::from_value(value["data"].clone())
+            .map_err(|e| AsrError::InvalidResponse(format!("上传凭证解析失败: {}", e)))
+    }
+
+    /// 申请点播上传地址
+    async fn apply_upload(&self, token: &UploadToken, file_size: usize) -> Result<(StoreInfo, String), AsrError> {
+        let query = format!(
+            "Action=ApplyUploadInner&FileSize={}&FileType=object&IsInner=1&SpaceName={}&Version=2020-11-19&s={}",
+            file_size, SPACE_NAME, VOD_CLIENT_PARAM
+        );
+
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let headers = [
+            ("x-amz-date", amz_date.as_str()),
+            ("x-amz-security-token", token.session_token.as_str()),
+        ];
+        let authorization = aws_authorization(
+            &token.access_key_id,
+            &token.secret_access_key,
+            "GET",
+            &query,
+            &headers,
+            "",
+        );
+
+        let response = self.client.execute(
+            self.client.get(format!("{}/?{}", self.vod_url, query))
+                .header("x-amz-date", &amz_date)
+                .header("x-amz-security-token", &token.session_token)
+                .header(reqwest::header::AUTHORIZATION, authorization)
+        ).await?;
+
+        let status = response.status();
+        let text = response.text();
+        if !status.is_success() {
+            return Err(AsrError::from_status(status, &text));
+        }
+
+        let value: Value = serde_json::from_str(&text)
+            .map_err(|e| AsrError::InvalidResponse(format!("{}: {}", e, text)))?;
+        let address: UploadAddress = serde_json::from_value(value["Result"]["UploadAddress"].clone())
+            .map_err(|e| AsrError::InvalidResponse(format!("上传地址解析失败: {}", e)))?;
+
+        let store_info = address.store_infos.into_iter().next()
+            .ok_or_else(|| AsrError::InvalidResponse("缺少StoreInfos".to_string()))?;
+        let upload_host = address.upload_hosts.into_iter().next()
+            .ok_or_else(|| AsrError::InvalidResponse("缺少UploadHosts".to_string()))?;
+
+        Ok((store_info, upload_host))
+    }
+
+    /// 上传地址，上传主机不带协议时沿用点播地址的协议
+    fn upload_url(&self, upload_host: &str, store_uri: &str) -> String {
+        if upload_host.contains("://") {
+            format!("{}/{}", upload_host.trim_end_matches('/'), store_uri)
+        } else {
+            let scheme = self.vod_url.split("://").next().unwrap_or("https");
+            format!("{}://{}/{}", scheme, upload_host, store_uri)
+        }
+    }
+
+    /// 上传文件并确认，返回音频的存储URI
+    async fn upload(&self, data: &[u8]) -> Result<String, AsrError> {
+        let token = self.upload_sign().await?;
+        let (store_info, upload_host) = self.apply_upload(&token, data.len()).await?;
+        let url = self.upload_url(&upload_host, &store_info.store_uri);
+        let crc32 = format!("{:08x}", crc32fast::hash(data));
+
+        let response = self.client.execute(
+            self.client.put(&url)
+                .query(&[("partNumber", "1"), ("uploadID", store_info.upload_id.as_str())])
+                .header(reqwest::header::AUTHORIZATION, &store_info.auth)
+                .header("Content-CRC32", &crc32)
+                .body(data.to_vec())
+        ).await?;
+
+        let status = response.status();
+        let text = response.text();
+        if !status.is_success() {
+            return Err(AsrError::from_status(status, &text));
+        }
+        let value: Value = serde_json::from_str(&text)
+            .map_err(|e| AsrError::InvalidResponse(format!("{}: {}", e, text)))?;
+        if value.get("success").and_then(|s| s.as_i64()) != Some(0) {
+            return Err(AsrError::Request(format!("文件上传失败: {}", text)));
+        }
+
+        let response = self.client.execute(
+            self.client.post(&url)
+                .query(&[("uploadID", store_info.upload_id.as_str())])
+                .header(reqwest::header::AUTHORIZATION, &store_info.auth)
+                .header(reqwest::header::CONTENT_TYPE, "text/plain;charset=UTF-8")
+                .body(format!("1:{}", crc32))
+        ).await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(AsrError::from_status(status, &response.text()));
+        }
+
+        debug!("剪映上传完成: {}", store_info.store_uri);
+
+        Ok(store_info.store_uri)
+    }
+
+    /// 提交识别任务，返回任务ID
+    async fn submit(&self, store_uri: &str, duration_ms: u64) -> Result<String, AsrError> {
+        let request_id = hex::encode(Md5::digest(format!("{}{}", store_uri, Utc::now().timestamp_nanos_opt().unwrap_or_default())));
+
+        let value = self.post_signed("/lv/v1/audio_subtitle/submit", &json!({
+            "adjust_endtime": 200,
+            "audio": store_uri,
+            "caption_type": 2,
+            "client_request_id": request_id,
+            "max_lines": 1,
+            "songs_info": [{ "end_time": duration_ms, "id": store_uri, "start_time": 0 }],
+            "words_per_line": 16,
+        })).await?;
+
+        value["data"]["id"].as_str()
+            .map(str::to_string)
+            .ok_or_else(|| AsrError::InvalidResponse("提交结果缺少任务ID".to_string()))
+    }
+
+    /// 查询识别结果直到返回字幕
+    async fn query(&self, query_id: &str) -> Result<Vec<JianyingUtterance>, AsrError> {
+        for _ in 0..self.max_polls {
+            let value = self.post_signed("/lv/v1/audio_subtitle/query", &json!({
+                "id": query_id,
+                "pack_options": { "need_attribute": true },
+            })).await?;
+
+            if let Some(utterances) = value["data"].get("utterances") {
+                return serde_json::from_value(utterances.clone())
+                    .map_err(|e| AsrError::InvalidResponse(format!("识别结果解析失败: {}", e)));
+            }
+
+            tokio::time::sleep(self.poll_interval).await;
+        }
+
+        Err(AsrError::Timeout(format!("任务 {} 在 {} 次查询后仍未完成", query_id, self.max_polls)))
+    }
+}
+
+#[async_trait]
+impl AsrProvider for JianyingProvider {
+    fn name(&self) -> &str {
+        "jianying"
+    }
+
+    fn capabilities(&self) -> AsrCapabilities {
+        AsrCapabilities {
+            requires_network: true,
+            timestamps: true,
+            hotwords: false,
+            languages: vec!["zh".to_string(), "en".to_string()],
+            max_segment_duration: None,
+        }
+    }
+
+    async fn is_available(&self) -> bool {
+        true
+    }
+
+    async fn recognize(&self, segment: &AudioSegment) -> Result<Transcript, AsrError> {
+        let data = tokio::fs::read(&segment.path).await?;
+        // 剪映需要音频结束时间，片段没有记录时长时从文件读取；先读时长，读不出时不必白白上传
+        let duration = match segment.duration {
+            Some(duration) => duration,
+            None => probe_duration(&segment.path).await?,
+        };
+        let duration_ms = (duration * 1000.0) as u64;
+
+        let store_uri = self.upload(&data).await?;
+        let query_id = self.submit(&store_uri, duration_ms).await?;
+        debug!("剪映任务已提交: {} ({})", query_id, segment.file_name());
+
+        let utterances = self.query(&query_id).await?
+            .iter()
+            .map(|u| Utterance::new(u.start_time as f64 / 1000.0, u.end_time as f64 / 1000.0, &u.text))
+            .collect();
+
+        Ok(Transcript::new(self.name(), utterances))
+    }
+}
+
+/// 剪映接口签名：路径末7位、平台、版本、时间和设备ID拼接后取MD5
+fn request_sign(path: &str, device_time: &str, device_id: &str) -> String {
+    let tail: String = {
+        let chars: Vec<char> = path.chars().collect();
+        chars[chars.len().saturating_sub(7)..].iter().collect()
+    };
+
+    let sign_str = format!("9e2c|{}|{}|{}|{}|{}|11ac", tail, PLATFORM, APP_VERSION, device_time, device_id);
+    hex::encode(Md5::digest(sign_str.as_bytes()))
+}
+
+/// 读取片段文件的时长（秒）
+async fn probe_duration(path: &Path) -> Result<f64, AsrError> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || get_audio_duration(&path))
+        .await
+        .map_err(|e| AsrError::Request(format!("读取音频时长失败: {}", e)))?
+        .map_err(|e| AsrError::Request(format!("读取音频时长失败: {}", e)))
+}
+
+/// 计算字节点播接口的 AWS4-HMAC-SHA256 Authorization 头
+fn aws_authorization(
+    access_key: &str,
+    secret_key: &str,
+    method: &str,
+    query: &str,
+    headers: &[(&str, &str)],
+    payload: &str,
+) -> String {
+    let amz_date = headers.iter()
+        .find(|(k, _)| *k == "x-amz-date")
+        .map(|(_, v)| *v)
+        .unwrap_or_default();
+    let datestamp = amz_date.split('T').next().unwrap_or_default();
+
+    let canonical_headers: String = headers.iter().map(|(k, v)| format!("{}:{}\n", k, v)).collect();
+    let signed_headers = headers.iter().map(|(k, _)| *k).collect::<Vec<_>>().join(";");
+    let payload_hash = hex::encode(Sha256::digest(payload.as_bytes()));
+
+    let canonical_request = format!(
+        "{}\n/\n{}\n{}\n{}\n{}",
+        method, query, canonical_headers, signed_headers, payload_hash
+    );
+
+    let credential_scope = format!("{}/{}/{}/aws4_request", datestamp, VOD_REGION, VOD_SERVICE);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        credential_scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let k_date = hmac_sha256(format!("AWS4{}", secret_key).as_bytes(), datestamp.as_bytes());
+    let k_region = hmac_sha256(&k_date, VOD_REGION.as_bytes());
+    let k_service = hmac_sha256(&k_region, VOD_SERVICE.as_bytes());
+    let k_signing = hmac_sha256(&k_service, b"aws4_request");
+    let signature = hex::encode(hmac_sha256(&k_signing, string_to_sign.as_bytes()));
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        access_key, credential_scope, signed_headers, signature
+    )
+}
+
+fn hmac_sha256(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC可以接受任意长度的密钥");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn request_sign_uses_the_path_tail() {
+        assert_eq!(
+            request_sign("/lv/v1/audio_subtitle/submit", "1700000000", DEFAULT_DEVICE_ID),
+            "5f8f78dd458c162725939323c77a3f45"
+        );
+    }
+
+    #[test]
+    fn aws_authorization_matches_reference_signature() {
+        let query = format!(
+            "Action=ApplyUploadInner&FileSize=16&FileType=object&IsInner=1&SpaceName={}&Version=2020-11-19&s={}",
+            SPACE_NAME, VOD_CLIENT_PARAM
+        );
+        let headers = [("x-amz-date", "20240102T030405Z"), ("x-amz-security-token", "STSTOKEN")];
+
+        assert_eq!(
+            aws_authorization("AKLTTESTKEY", "TESTSECRET", "GET", &query, &headers, ""),
+            "AWS4-HMAC-SHA256 Credential=AKLTTESTKEY/20240102/cn/vod/aws4_request, \
+             SignedHeaders=x-amz-date;x-amz-security-token, \
+             Signature=5b1f1a39d2fb727c34a89fd486138de9b57e5179568d53ddc8c8d6d93680457e"
+        );
+    }
+
+    #[test]
+    fn upload_url_keeps_the_vod_scheme() {
+        let provider = JianyingProvider::with_endpoints(DEFAULT_BASE_URL, "http://vod.local");
+        assert_eq!(provider.upload_url("tos.local", "bucket/key"), "http://tos.local/bucket/key");
+        assert_eq!(provider.upload_url("https://tos.local/", "bucket/key"), "https://tos.local/bucket/key");
+    }
+}
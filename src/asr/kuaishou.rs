@@ -0,0 +1,198 @@
+use async_trait::async_trait;
+use log::debug;
+use reqwest::RequestBuilder;
+use serde::Deserialize;
+
+use crate::asr::base::{AsrProvider, AsrCapabilities, AsrError, AudioSegment, Transcript, Utterance};
+use crate::asr::http::HttpClient;
+
+/// 快手字幕接口默认地址
+pub const DEFAULT_ENDPOINT: &str = "https://ai.kuaishou.com/api/effects/subtitle_generate";
+
+/// 快手接口的响应
+#[derive(Debug, Deserialize)]
+struct KuaishouResponse {
+    #[serde(default)]
+    result: Option<i64>,
+    #[serde(default)]
+    error_msg: Option<String>,
+    data: Option<KuaishouData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KuaishouData {
+    #[serde(default)]
+    text: Vec<KuaishouUtterance>,
+}
+
+/// 识别结果中的一句话，时间单位为秒
+#[derive(Debug, Deserialize)]
+struct KuaishouUtterance {
+    text: String,
+    start_time: f64,
+    end_time: f64,
+}
+
+/// 快手ASR服务
+pub struct KuaishouProvider {
+    client: HttpClient,
+    cookie: Option<String>,
+    endpoint: String,
+}
+
+impl Default for KuaishouProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl KuaishouProvider {
+    /// 创建新的快手ASR服务
+    pub fn new() -> Self {
+        Self::with_endpoint(DEFAULT_ENDPOINT)
+    }
+
+    /// 使用指定的接口地址创建快手ASR服务
+    pub fn with_endpoint(endpoint: &str) -> Self {
+        Self {
+            client: HttpClient::default(),
+            cookie: None,
+            endpoint: endpoint.to_string(),
+        }
+    }
+
+    /// 使用共享的HTTP客户端
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// 设置随接口请求发送的 Cookie
+    pub fn with_cookie(mut self, cookie: Option<String>) -> Self {
+        self.cookie = cookie;
+        self
+    }
+
+    /// 加上登录凭据
+    fn authorize(&self, builder: RequestBuilder) -> RequestBuilder {
+        match &self.cookie {
+            Some(cookie) => builder.header(reqwest::header::COOKIE, cookie),
+            None => builder,
+        }
+    }
+}
+
+#[async_trait]
+impl AsrProvider for KuaishouProvider {
+    fn name(&self) -> &str {
+        "kuaishou"
+    }
+
+    fn capabilities(&self) -> AsrCapabilities {
+        AsrCapabilities {
+            requires_network: true,
+            timestamps: true,
+            hotwords: false,
+            languages: vec!["zh".to_string()],
+            max_segment_duration: None,
+        }
+    }
+
+    async fn is_available(&self) -> bool {
+        true
+    }
+
+    async fn recognize(&self, segment: &AudioSegment) -> Result<Transcript, AsrError> {
+        let data = tokio::fs::read(&segment.path).await?;
+
+        let file_part = reqwest::multipart::Part::bytes(data)
+            .file_name(segment.file_name())
+            .mime_str(audio_mime(segment))
+            .map_err(|e| AsrError::Request(e.to_string()))?;
+        let form = reqwest::multipart::Form::new()
+            .text("typeId", "1")
+            .part("file", file_part);
+
+        let response = self.client.execute(
+            self.authorize(self.client.post(&self.endpoint)).multipart(form)
+        ).await?;
+
+        let status = response.status();
+        let body = response.text();
+        if !status.is_success() {
+            return Err(AsrError::from_status(status, &body));
+        }
+
+        let utterances = parse_utterances(&body)?;
+        debug!("快手识别完成: {} 句 ({})", utterances.len(), segment.file_name());
+
+        Ok(Transcript::new(self.name(), utterances))
+    }
+}
+
+/// 按片段扩展名选择上传的 MIME 类型，内置解码输出WAV片段
+fn audio_mime(segment: &AudioSegment) -> &'static str {
+    match segment.path.extension().and_then(|e| e.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("wav") => "audio/wav",
+        _ => "audio/mpeg",
+    }
+}
+
+/// 解析快手接口的响应，result 不为 1 时返回错误
+fn parse_utterances(body: &str) -> Result<Vec<Utterance>, AsrError> {
+    let parsed: KuaishouResponse = serde_json::from_str(body)
+        .map_err(|e| AsrError::InvalidResponse(format!("{}: {}", e, body)))?;
+
+    if let Some(code) = parsed.result {
+        if code != 1 {
+            return Err(AsrError::Request(format!(
+                "result {}: {}", code, parsed.error_msg.unwrap_or_default()
+            )));
+        }
+    }
+
+    let data = parsed.data
+        .ok_or_else(|| AsrError::InvalidResponse("响应缺少data字段".to_string()))?;
+
+    Ok(data.text.iter()
+        .map(|u| Utterance::new(u.start_time, u.end_time, &u.text))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_subtitle_sentences() {
+        let utterances = parse_utterances(r#"{
+            "result": 1,
+            "data": {"text": [
+                {"text": " 第一句 ", "start_time": 0.0, "end_time": 1.5},
+                {"text": "第二句", "start_time": 1.5, "end_time": 3.25}
+            ]}
+        }"#).unwrap();
+
+        assert_eq!(utterances, vec![Utterance::new(0.0, 1.5, "第一句"), Utterance::new(1.5, 3.25, "第二句")]);
+    }
+
+    #[test]
+    fn result_other_than_one_is_an_error() {
+        match parse_utterances(r#"{"result": 500, "error_msg": "文件过大"}"#) {
+            Err(AsrError::Request(message)) => assert_eq!(message, "result 500: 文件过大"),
+            other => panic!("应为 Request: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn mime_follows_segment_extension() {
+        assert_eq!(audio_mime(&AudioSegment::new(std::path::Path::new("part_0000.wav"), 0.0, None)), "audio/wav");
+        assert_eq!(audio_mime(&AudioSegment::new(std::path::Path::new("part_0000.mp3"), 0.0, None)), "audio/mpeg");
+    }
+
+    #[test]
+    fn missing_data_is_an_invalid_response() {
+        assert!(matches!(parse_utterances(r#"{"result": 1}"#), Err(AsrError::InvalidResponse(_))));
+        assert!(matches!(parse_utterances("<html>"), Err(AsrError::InvalidResponse(_))));
+    }
+}
@@ -0,0 +1,233 @@
+use std::collections::HashMap;
+use std::path::Path;
+use log::warn;
+use serde_json::Value;
+
+/// 配置中表示自动检测的取值
+pub const AUTO: &str = "auto";
+
+/// 识别语言设置
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum LanguageSetting {
+    /// 不指定，各服务使用自己的默认语言
+    #[default]
+    Unspecified,
+    /// 识别前先采样检测
+    Auto,
+    /// 固定语言代码，如 zh、en
+    Fixed(String),
+}
+
+impl LanguageSetting {
+    /// 从配置字符串解析
+    pub fn parse(value: &str) -> Self {
+        if value.trim().eq_ignore_ascii_case(AUTO) {
+            return LanguageSetting::Auto;
+        }
+        match normalize(value) {
+            Some(code) => LanguageSetting::Fixed(code),
+            None => LanguageSetting::Unspecified,
+        }
+    }
+}
+
+/// 全局和按文件的语言配置
+#[derive(Debug, Clone, Default)]
+pub struct LanguageConfig {
+    /// 全局设置
+    pub default: LanguageSetting,
+    /// 按文件名或不含扩展名的文件名指定的设置
+    pub files: HashMap<String, LanguageSetting>,
+    /// 自动检测时采样的片段数
+    pub sample_segments: usize,
+}
+
+impl LanguageConfig {
+    /// 读取 language、file_languages 和 language_sample_segments 配置
+    pub fn from_config(config: &HashMap<String, Value>) -> Self {
+        let default = config.get("language")
+            .and_then(|v| v.as_str())
+            .map(LanguageSetting::parse)
+            .unwrap_or_default();
+
+        let mut files = HashMap::new();
+        if let Some(map) = config.get("file_languages").and_then(|v| v.as_object()) {
+            for (file, value) in map {
+                match value.as_str() {
+                    Some(value) => {
+                        files.insert(file.clone(), LanguageSetting::parse(value));
+                    }
+                    None => warn!("file_languages 中 {} 的取值不是字符串，已忽略", file),
+                }
+            }
+        }
+
+        let sample_segments = config.get("language_sample_segments")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(3)
+            .max(1) as usize;
+
+        Self { default, files, sample_segments }
+    }
+
+    /// 文件使用的语言设置，按文件名、再按不含扩展名的文件名查找
+    pub fn for_file(&self, path: &Path) -> &LanguageSetting {
+        let name = path.file_name().and_then(|n| n.to_str());
+        let stem = path.file_stem().and_then(|n| n.to_str());
+
+        [name, stem].iter()
+            .flatten()
+            .find_map(|key| self.files.get(*key))
+            .unwrap_or(&self.default)
+    }
+}
+
+/// 规范化语言代码：取主标签并转为小写，常见的英文语言名转为代码
+pub fn normalize(code: &str) -> Option<String> {
+    let code = code.trim().to_lowercase();
+    let primary = code.split(['-', '_']).next().unwrap_or("");
+
+    let code = match primary {
+        "" | AUTO => return None,
+        "chinese" | "mandarin" | "cn" => "zh",
+        "english" => "en",
+        "japanese" => "ja",
+        "korean" => "ko",
+        "cantonese" => "yue",
+        "russian" => "ru",
+        "french" => "fr",
+        "german" => "de",
+        "spanish" => "es",
+        other => other,
+    };
+
+    Some(code.to_string())
+}
+
+/// 服务是否支持该语言，languages 为空表示不限制，未指定语言时总是支持
+pub fn supports(languages: &[String], language: Option<&str>) -> bool {
+    match language {
+        Some(language) => languages.is_empty() || languages.iter().any(|l| l == language),
+        None => true,
+    }
+}
+
+/// 按文字的书写系统粗略判断语言，拉丁字母一律视为英语
+pub fn detect(text: &str) -> Option<&'static str> {
+    let mut counts: HashMap<&'static str, usize> = HashMap::new();
+    let mut kana = 0;
+
+    for c in text.chars() {
+        let script = match c as u32 {
+            0x3040..=0x30FF => {
+                kana += 1;
+                "ja"
+            }
+            0xAC00..=0xD7AF | 0x1100..=0x11FF | 0x3130..=0x318F => "ko",
+            0x4E00..=0x9FFF | 0x3400..=0x4DBF => "zh",
+            0x0400..=0x04FF => "ru",
+            0x0600..=0x06FF => "ar",
+            0x0E00..=0x0E7F => "th",
+            _ if c.is_ascii_alphabetic() => "en",
+            _ => continue,
+        };
+        *counts.entry(script).or_insert(0) += 1;
+    }
+
+    // 日文中夹杂大量汉字，有一定比例的假名就算日文
+    let han = counts.get("zh").copied().unwrap_or(0);
+    if kana > 0 && kana * 5 >= han {
+        *counts.entry("ja").or_insert(0) += han;
+        counts.remove("zh");
+    }
+
+    // 拉丁字母按约 4 个字母一个词折算，与汉字的一字一词大致可比
+    if let Some(latin) = counts.get_mut("en") {
+        *latin = latin.div_ceil(4);
+    }
+
+    counts.into_iter()
+        .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(a.0)))
+        .map(|(language, _)| language)
+}
+
+/// 按权重投票选出主要语言，权重相同时取先出现的
+pub fn dominant<I: IntoIterator<Item = (String, usize)>>(votes: I) -> Option<String> {
+    let mut totals: Vec<(String, usize)> = Vec::new();
+    for (language, weight) in votes {
+        match totals.iter_mut().find(|(l, _)| *l == language) {
+            Some(total) => total.1 += weight,
+            None => totals.push((language, weight)),
+        }
+    }
+
+    let best = totals.iter().map(|(_, w)| *w).max()?;
+    totals.into_iter().find(|(_, w)| *w == best).map(|(l, _)| l)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_tags_and_names() {
+        assert_eq!(normalize("zh-CN").as_deref(), Some("zh"));
+        assert_eq!(normalize(" EN_us ").as_deref(), Some("en"));
+        assert_eq!(normalize("Mandarin").as_deref(), Some("zh"));
+        assert_eq!(normalize("cantonese").as_deref(), Some("yue"));
+        assert_eq!(normalize("auto"), None);
+        assert_eq!(normalize(""), None);
+    }
+
+    #[test]
+    fn parses_settings() {
+        assert_eq!(LanguageSetting::parse("AUTO"), LanguageSetting::Auto);
+        assert_eq!(LanguageSetting::parse("ja-JP"), LanguageSetting::Fixed("ja".to_string()));
+        assert_eq!(LanguageSetting::parse(" "), LanguageSetting::Unspecified);
+    }
+
+    #[test]
+    fn file_settings_override_the_default() {
+        let config: HashMap<String, Value> = serde_json::from_value(serde_json::json!({
+            "language": "zh",
+            "file_languages": {"talk.mp4": "en", "interview": "auto", "bad": 1},
+            "language_sample_segments": 0
+        })).unwrap();
+        let languages = LanguageConfig::from_config(&config);
+
+        assert_eq!(languages.for_file(Path::new("/videos/talk.mp4")), &LanguageSetting::Fixed("en".to_string()));
+        assert_eq!(languages.for_file(Path::new("/videos/interview.mkv")), &LanguageSetting::Auto);
+        assert_eq!(languages.for_file(Path::new("/videos/other.mp4")), &LanguageSetting::Fixed("zh".to_string()));
+        assert!(!languages.files.contains_key("bad"));
+        assert_eq!(languages.sample_segments, 1);
+    }
+
+    #[test]
+    fn supports_unrestricted_and_listed_languages() {
+        let listed = vec!["zh".to_string(), "en".to_string()];
+        assert!(supports(&[], Some("ja")));
+        assert!(supports(&listed, Some("en")));
+        assert!(!supports(&listed, Some("ja")));
+        assert!(supports(&listed, None));
+    }
+
+    #[test]
+    fn detects_scripts() {
+        assert_eq!(detect("今天天气很好"), Some("zh"));
+        assert_eq!(detect("今日はいい天気ですね"), Some("ja"));
+        assert_eq!(detect("안녕하세요"), Some("ko"));
+        assert_eq!(detect("Привет"), Some("ru"));
+        assert_eq!(detect("hello world"), Some("en"));
+        // 拉丁字母按词折算，夹几个英文单词的中文仍是中文
+        assert_eq!(detect("我们用 iPhone 拍照片"), Some("zh"));
+        assert_eq!(detect("123 ..."), None);
+    }
+
+    #[test]
+    fn dominant_sums_weights_and_keeps_first_on_ties() {
+        let votes = vec![("en".to_string(), 5), ("zh".to_string(), 3), ("zh".to_string(), 3)];
+        assert_eq!(dominant(votes).as_deref(), Some("zh"));
+        assert_eq!(dominant(vec![("ja".to_string(), 2), ("ko".to_string(), 2)]).as_deref(), Some("ja"));
+        assert_eq!(dominant(Vec::new()), None);
+    }
+}
@@ -0,0 +1,321 @@
+//! 按服务限流
+//!
+//! 限流的单位是片段：每次 `recognize` 调用消耗一个令牌、占用一个并发名额。
+//! 一个片段在服务内部可能对应多次HTTP请求（分块上传、提交任务、轮询结果），
+//! 这些请求不单独计数，配置速率时需按片段估算。
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use log::{info, debug};
+use serde::Deserialize;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+/// 未配置速率的服务被限流后使用的初始速率（次/秒）
+const THROTTLED_RATE: f64 = 1.0;
+
+/// 被限流时速率的缩减比例
+const THROTTLE_FACTOR: f64 = 0.5;
+
+/// 速率最多降到配置值的该比例
+const MIN_RATE_FACTOR: f64 = 0.05;
+
+/// 每次成功后恢复配置速率的比例
+const RECOVERY_STEP: f64 = 0.1;
+
+/// 单个服务的限流配置，对应 asr_segment_limits 中的一项
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RateLimit {
+    /// 每秒允许开始识别的片段数，不填表示不限速
+    #[serde(default)]
+    pub segments_per_second: Option<f64>,
+
+    /// 令牌桶容量（片段数），默认为 1
+    #[serde(default)]
+    pub burst: Option<f64>,
+
+    /// 同时识别的最大片段数，不填表示只受 max_workers 限制
+    #[serde(default)]
+    pub max_concurrency: Option<usize>,
+}
+
+/// 令牌桶状态
+#[derive(Debug)]
+struct Bucket {
+    /// 当前速率，None 表示不限速
+    rate: Option<f64>,
+    /// 配置的速率，恢复时不会超过它
+    base_rate: Option<f64>,
+    burst: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        if let Some(rate) = self.rate {
+            let elapsed = now.duration_since(self.last_refill).as_secs_f64();
+            self.tokens = (self.tokens + elapsed * rate).min(self.burst);
+        }
+        self.last_refill = now;
+    }
+}
+
+/// 调用许可，释放时归还并发名额
+pub struct LimiterPermit {
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+/// 单个服务的限流器：令牌桶控制速率，信号量控制并发，遇到限流自动降速
+pub struct RateLimiter {
+    name: String,
+    bucket: Mutex<Bucket>,
+    semaphore: Option<Arc<Semaphore>>,
+    waited_micros: AtomicU64,
+    throttled: AtomicU64,
+}
+
+impl RateLimiter {
+    /// 创建新的限流器
+    pub fn new(name: &str, limit: &RateLimit) -> Self {
+        let rate = limit.segments_per_second.filter(|r| *r > 0.0);
+        let burst = limit.burst.unwrap_or(1.0).max(1.0);
+
+        Self {
+            name: name.to_string(),
+            bucket: Mutex::new(Bucket {
+                rate,
+                base_rate: rate,
+                burst,
+                tokens: burst,
+                last_refill: Instant::now(),
+            }),
+            semaphore: limit.max_concurrency.map(|n| Arc::new(Semaphore::new(n.max(1)))),
+            waited_micros: AtomicU64::new(0),
+            throttled: AtomicU64::new(0),
+        }
+    }
+
+    /// 等待并发名额和令牌
+    pub async fn acquire(&self) -> LimiterPermit {
+        let started = Instant::now();
+
+        let permit = match &self.semaphore {
+            Some(semaphore) => Arc::clone(semaphore).acquire_owned().await.ok(),
+            None => None,
+        };
+
+        loop {
+            let wait = {
+                let mut bucket = self.bucket.lock().unwrap();
+                let rate = match bucket.rate {
+                    Some(rate) => rate,
+                    None => break,
+                };
+                bucket.refill(Instant::now());
+                if bucket.tokens >= 1.0 {
+                    bucket.tokens -= 1.0;
+                    break;
+                }
+                Duration::from_secs_f64((1.0 - bucket.tokens) / rate)
+            };
+            tokio::time::sleep(wait).await;
+        }
+
+        let waited = started.elapsed();
+        if waited > Duration::from_millis(10) {
+            debug!("{} 限流等待 {:.1}秒", self.name, waited.as_secs_f64());
+        }
+        self.waited_micros.fetch_add(waited.as_micros() as u64, Ordering::Relaxed);
+
+        LimiterPermit { _permit: permit }
+    }
+
+    /// 服务返回限流错误，降低速率并清空令牌
+    pub fn on_throttle(&self) {
+        self.throttled.fetch_add(1, Ordering::Relaxed);
+
+        let mut bucket = self.bucket.lock().unwrap();
+        let new_rate = match (bucket.rate, bucket.base_rate) {
+            (Some(rate), Some(base)) => (rate * THROTTLE_FACTOR).max(base * MIN_RATE_FACTOR),
+            (Some(rate), None) => (rate * THROTTLE_FACTOR).max(THROTTLED_RATE * MIN_RATE_FACTOR),
+            (None, _) => THROTTLED_RATE,
+        };
+        bucket.refill(Instant::now());
+        bucket.rate = Some(new_rate);
+        bucket.tokens = 0.0;
+
+        info!("{} 触发限流，速率降至 {:.2} 次/秒", self.name, new_rate);
+    }
+
+    /// 调用成功，逐步恢复速率
+    pub fn on_success(&self) {
+        let mut bucket = self.bucket.lock().unwrap();
+        let rate = match bucket.rate {
+            Some(rate) => rate,
+            None => return,
+        };
+
+        // 未配置速率的服务恢复到一定程度后取消限速
+        let target = bucket.base_rate.unwrap_or(THROTTLED_RATE * 10.0);
+        if rate >= target {
+            return;
+        }
+
+        bucket.refill(Instant::now());
+        let recovered = rate + target * RECOVERY_STEP;
+        bucket.rate = if recovered >= target {
+            bucket.base_rate
+        } else {
+            Some(recovered)
+        };
+    }
+
+    /// 累计等待时间
+    pub fn waited(&self) -> Duration {
+        Duration::from_micros(self.waited_micros.load(Ordering::Relaxed))
+    }
+
+    /// 被限流的次数
+    pub fn throttled(&self) -> u64 {
+        self.throttled.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(segments_per_second: Option<f64>, burst: Option<f64>, max_concurrency: Option<usize>) -> RateLimiter {
+        RateLimiter::new("test", &RateLimit { segments_per_second, burst, max_concurrency })
+    }
+
+    fn rate(limiter: &RateLimiter) -> Option<f64> {
+        limiter.bucket.lock().unwrap().rate
+    }
+
+    /// 取得一个许可所用的（虚拟）时间
+    async fn acquire_time(limiter: &RateLimiter) -> Duration {
+        let started = Instant::now();
+        let _permit = limiter.acquire().await;
+        started.elapsed()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn burst_is_free_then_tokens_refill_at_the_rate() {
+        let limiter = limiter(Some(2.0), Some(3.0), None);
+
+        for _ in 0..3 {
+            assert_eq!(acquire_time(&limiter).await, Duration::ZERO);
+        }
+        assert_eq!(acquire_time(&limiter).await, Duration::from_millis(500));
+        assert_eq!(acquire_time(&limiter).await, Duration::from_millis(500));
+
+        // 空闲期间令牌最多攒到 burst 个
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        for _ in 0..3 {
+            assert_eq!(acquire_time(&limiter).await, Duration::ZERO);
+        }
+        assert!(acquire_time(&limiter).await > Duration::ZERO);
+        assert_eq!(limiter.waited(), Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn accepts_segment_rate_keys() {
+        let parsed: RateLimit = serde_json::from_value(serde_json::json!({
+            "segments_per_second": 0.5, "burst": 2, "max_concurrency": 3
+        })).unwrap();
+        assert_eq!(parsed.segments_per_second, Some(0.5));
+        assert_eq!(parsed.burst, Some(2.0));
+        assert_eq!(parsed.max_concurrency, Some(3));
+    }
+
+    #[test]
+    fn rejects_unknown_rate_keys() {
+        for key in ["rate", "requests_per_second"] {
+            let parsed = serde_json::from_value::<RateLimit>(serde_json::json!({ key: 0.5 }));
+            assert!(parsed.is_err(), "{} 应被拒绝", key);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unlimited_rate_never_waits() {
+        let limiter = limiter(None, None, None);
+        for _ in 0..100 {
+            assert_eq!(acquire_time(&limiter).await, Duration::ZERO);
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn semaphore_caps_concurrency() {
+        let limiter = limiter(None, None, Some(2));
+
+        let first = limiter.acquire().await;
+        let _second = limiter.acquire().await;
+        assert!(tokio::time::timeout(Duration::from_secs(60), limiter.acquire()).await.is_err());
+
+        drop(first);
+        assert!(tokio::time::timeout(Duration::from_secs(1), limiter.acquire()).await.is_ok());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn throttle_halves_the_rate_and_drains_tokens() {
+        let limiter = limiter(Some(4.0), Some(4.0), None);
+
+        limiter.on_throttle();
+        assert_eq!(rate(&limiter), Some(2.0));
+        assert_eq!(acquire_time(&limiter).await, Duration::from_millis(500));
+
+        limiter.on_throttle();
+        assert_eq!(rate(&limiter), Some(1.0));
+        assert_eq!(limiter.throttled(), 2);
+
+        // 最低降到配置速率的 5%
+        for _ in 0..10 {
+            limiter.on_throttle();
+        }
+        assert_eq!(rate(&limiter), Some(4.0 * MIN_RATE_FACTOR));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn throttle_limits_an_unlimited_provider() {
+        let limiter = limiter(None, None, None);
+
+        limiter.on_throttle();
+        assert_eq!(rate(&limiter), Some(THROTTLED_RATE));
+        assert_eq!(acquire_time(&limiter).await, Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn success_recovers_the_configured_rate() {
+        let limiter = limiter(Some(4.0), None, None);
+        limiter.on_throttle();
+
+        limiter.on_success();
+        limiter.on_success();
+        assert!((rate(&limiter).unwrap() - 2.8).abs() < 1e-9);
+
+        for _ in 0..10 {
+            limiter.on_success();
+        }
+        assert_eq!(rate(&limiter), Some(4.0));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unlimited_provider_becomes_unlimited_again() {
+        let limiter = limiter(None, None, None);
+        limiter.on_throttle();
+
+        for _ in 0..5 {
+            limiter.on_success();
+        }
+        assert!(rate(&limiter).is_some());
+
+        for _ in 0..5 {
+            limiter.on_success();
+        }
+        assert_eq!(rate(&limiter), None);
+    }
+}
@@ -0,0 +1,368 @@
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use async_trait::async_trait;
+use log::debug;
+use serde::Deserialize;
+use tokio::process::Command;
+
+use crate::asr::base::{AsrProvider, AsrCapabilities, AsrError, AudioSegment, Transcript, Utterance};
+use crate::core::file_utils::find_executable;
+
+/// 本地识别引擎类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocalEngine {
+    /// whisper.cpp 的 whisper-cli，输出JSON
+    WhisperCpp,
+    /// vosk-transcriber 或兼容脚本，输出SRT
+    Vosk,
+}
+
+impl LocalEngine {
+    /// 从配置字符串解析引擎类型
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().replace(['-', '.'], "_").as_str() {
+            "whisper_cpp" | "whispercpp" | "whisper" => Some(LocalEngine::WhisperCpp),
+            "vosk" => Some(LocalEngine::Vosk),
+            _ => None,
+        }
+    }
+}
+
+/// 本地识别服务配置
+#[derive(Debug, Clone)]
+pub struct LocalOptions {
+    pub engine: LocalEngine,
+    pub binary: String,
+    pub model: PathBuf,
+    pub language: Option<String>,
+    pub threads: Option<u32>,
+    /// 热词，whisper.cpp 作为初始提示发送，vosk 不支持
+    pub hotwords: Vec<String>,
+}
+
+/// whisper.cpp 的JSON输出
+#[derive(Debug, Deserialize)]
+struct WhisperOutput {
+    #[serde(default)]
+    transcription: Vec<WhisperSegment>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WhisperSegment {
+    offsets: WhisperOffsets,
+    text: String,
+    /// 完整JSON输出中的逐个token
+    #[serde(default)]
+    tokens: Vec<WhisperToken>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WhisperToken {
+    text: String,
+    #[serde(default)]
+    p: Option<f32>,
+}
+
+impl WhisperSegment {
+    /// 文本token概率的平均值，跳过 [_BEG_] 之类的特殊token
+    fn confidence(&self) -> Option<f32> {
+        let values: Vec<f32> = self.tokens.iter()
+            .filter(|t| !t.text.starts_with("[_"))
+            .filter_map(|t| t.p)
+            .collect();
+        if values.is_empty() {
+            return None;
+        }
+        Some(values.iter().sum::<f32>() / values.len() as f32)
+    }
+}
+
+/// 时间单位为毫秒
+#[derive(Debug, Deserialize)]
+struct WhisperOffsets {
+    from: u64,
+    to: u64,
+}
+
+/// 调用本机识别程序的离线ASR服务
+pub struct LocalProvider {
+    options: LocalOptions,
+}
+
+impl LocalProvider {
+    /// 创建新的本地ASR服务
+    pub fn new(options: LocalOptions) -> Self {
+        Self { options }
+    }
+
+    /// 构造识别命令，片段指定的语言优先于配置，输出写到 output_base 开头的文件
+    fn build_command(&self, binary: &Path, segment: &AudioSegment, output_base: &Path) -> (Command, PathBuf) {
+        let input = &segment.path;
+        let mut command = Command::new(binary);
+
+        let output_file = match self.options.engine {
+            LocalEngine::WhisperCpp => {
+                command.arg("-m").arg(&self.options.model)
+                    .arg("-f").arg(input)
+                    .arg("-ojf")
+                    .arg("-of").arg(output_base)
+                    .arg("-np");
+                if let Some(language) = segment.language.as_ref().or(self.options.language.as_ref()) {
+                    command.arg("-l").arg(language);
+                }
+                if let Some(threads) = self.options.threads {
+                    command.arg("-t").arg(threads.to_string());
+                }
+                if !self.options.hotwords.is_empty() {
+                    command.arg("--prompt").arg(self.options.hotwords.join(", "));
+                }
+                output_base.with_extension("json")
+            }
+            LocalEngine::Vosk => {
+                let output_file = output_base.with_extension("srt");
+                command.arg("-m").arg(&self.options.model)
+                    .arg("-i").arg(input)
+                    .arg("-t").arg("srt")
+                    .arg("-o").arg(&output_file);
+                output_file
+            }
+        };
+
+        command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+        (command, output_file)
+    }
+}
+
+#[async_trait]
+impl AsrProvider for LocalProvider {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    fn capabilities(&self) -> AsrCapabilities {
+        AsrCapabilities {
+            requires_network: false,
+            timestamps: true,
+            hotwords: self.options.engine == LocalEngine::WhisperCpp,
+            languages: match self.options.engine {
+                // vosk 的模型只对应一种语言
+                LocalEngine::Vosk => self.options.language.iter().cloned().collect(),
+                LocalEngine::WhisperCpp => Vec::new(),
+            },
+            max_segment_duration: None,
+        }
+    }
+
+    fn cache_fingerprint(&self) -> String {
+        format!("{:?}|{}|{}|{}",
+            self.options.engine,
+            self.options.model.display(),
+            self.options.language.as_deref().unwrap_or(""),
+            self.options.hotwords.join(","))
+    }
+
+    async fn is_available(&self) -> bool {
+        find_executable(&self.options.binary).is_some() && self.options.model.exists()
+    }
+
+    async fn recognize(&self, segment: &AudioSegment) -> Result<Transcript, AsrError> {
+        let binary = find_executable(&self.options.binary)
+            .ok_or_else(|| AsrError::Unavailable(format!("找不到本地识别程序: {}", self.options.binary)))?;
+        if !self.options.model.exists() {
+            return Err(AsrError::Unavailable(format!("模型文件不存在: {}", self.options.model.display())));
+        }
+
+        let work_dir = tempfile::tempdir()?;
+        let output_base = work_dir.path().join("result");
+        let (mut command, output_file) = self.build_command(&binary, segment, &output_base);
+
+        debug!("调用本地识别程序: {:?}", command.as_std());
+        let output = command.output().await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(AsrError::TaskFailed(format!(
+                "{} 退出码 {:?}: {}",
+                binary.display(),
+                output.status.code(),
+                stderr.lines().last().unwrap_or("")
+            )));
+        }
+
+        let contents = tokio::fs::read_to_string(&output_file).await
+            .map_err(|e| AsrError::InvalidResponse(format!("读取识别结果失败 {}: {}", output_file.display(), e)))?;
+
+        let utterances = match self.options.engine {
+            LocalEngine::WhisperCpp => parse_whisper_json(&contents)?,
+            LocalEngine::Vosk => parse_srt(&contents),
+        };
+
+        Ok(Transcript::new(self.name(), utterances))
+    }
+}
+
+/// 解析 whisper.cpp 的JSON输出为语句
+fn parse_whisper_json(contents: &str) -> Result<Vec<Utterance>, AsrError> {
+    let parsed: WhisperOutput = serde_json::from_str(contents)
+        .map_err(|e| AsrError::InvalidResponse(format!("whisper.cpp 输出解析失败: {}", e)))?;
+    Ok(parsed.transcription.iter()
+        .map(|s| {
+            let mut utterance = Utterance::new(s.offsets.from as f64 / 1000.0, s.offsets.to as f64 / 1000.0, &s.text);
+            utterance.confidence = s.confidence();
+            utterance
+        })
+        .collect())
+}
+
+/// 解析SRT字幕为语句
+pub fn parse_srt(contents: &str) -> Vec<Utterance> {
+    let mut utterances = Vec::new();
+
+    for block in contents.replace("\r\n", "\n").split("\n\n") {
+        let mut lines = block.lines().map(str::trim).filter(|l| !l.is_empty());
+
+        let mut time_line = match lines.next() {
+            Some(line) => line,
+            None => continue,
+        };
+        // 跳过序号行
+        if !time_line.contains("-->") {
+            time_line = match lines.next() {
+                Some(line) => line,
+                None => continue,
+            };
+        }
+
+        let (start, end) = match time_line.split_once("-->") {
+            Some((start, end)) => match (parse_srt_time(start), parse_srt_time(end)) {
+                (Some(start), Some(end)) => (start, end),
+                _ => continue,
+            },
+            None => continue,
+        };
+
+        let text = lines.collect::<Vec<_>>().join(" ");
+        if !text.is_empty() {
+            utterances.push(Utterance::new(start, end, &text));
+        }
+    }
+
+    utterances
+}
+
+/// 解析 HH:MM:SS,mmm 格式的时间为秒
+fn parse_srt_time(value: &str) -> Option<f64> {
+    let value = value.trim().replace(',', ".");
+    let mut parts = value.split(':');
+    let hours: f64 = parts.next()?.parse().ok()?;
+    let minutes: f64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    Some(hours * 3600.0 + minutes * 60.0 + seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_engine_names() {
+        assert_eq!(LocalEngine::parse("whisper.cpp"), Some(LocalEngine::WhisperCpp));
+        assert_eq!(LocalEngine::parse("Whisper-CPP"), Some(LocalEngine::WhisperCpp));
+        assert_eq!(LocalEngine::parse("vosk"), Some(LocalEngine::Vosk));
+        assert_eq!(LocalEngine::parse("kaldi"), None);
+    }
+
+    #[test]
+    fn parses_whisper_json_offsets_in_milliseconds() {
+        let utterances = parse_whisper_json(r#"{
+            "transcription": [
+                {"timestamps": {"from": "00:00:00,000", "to": "00:00:01,500"},
+                 "offsets": {"from": 0, "to": 1500}, "text": " 你好"},
+                {"offsets": {"from": 1500, "to": 3020}, "text": " 世界"}
+            ]
+        }"#).unwrap();
+
+        assert_eq!(utterances, vec![Utterance::new(0.0, 1.5, "你好"), Utterance::new(1.5, 3.02, "世界")]);
+        assert!(matches!(parse_whisper_json("not json"), Err(AsrError::InvalidResponse(_))));
+    }
+
+    #[test]
+    fn whisper_confidence_averages_text_tokens() {
+        let utterances = parse_whisper_json(r#"{
+            "transcription": [
+                {"offsets": {"from": 0, "to": 1000}, "text": " 你好",
+                 "tokens": [{"text": "[_BEG_]", "p": 0.1}, {"text": "你", "p": 0.9}, {"text": "好", "p": 0.7}]},
+                {"offsets": {"from": 1000, "to": 2000}, "text": " 世界", "tokens": [{"text": "世界"}]}
+            ]
+        }"#).unwrap();
+
+        assert!((utterances[0].confidence.unwrap() - 0.8).abs() < 1e-6);
+        assert_eq!(utterances[1].confidence, None);
+    }
+
+    #[test]
+    fn parses_srt_blocks() {
+        let srt = "1\r\n00:00:01,000 --> 00:00:02,500\r\n第一行\r\n第二行\r\n\r\n\
+                   2\n00:01:00,250 --> 01:00:00,000\nhello\n\n\
+                   3\nbroken --> line\nskipped\n\n\
+                   00:00:05,000 --> 00:00:06,000\n\n";
+
+        let utterances = parse_srt(srt);
+        assert_eq!(utterances, vec![
+            Utterance::new(1.0, 2.5, "第一行 第二行"),
+            Utterance::new(60.25, 3600.0, "hello"),
+        ]);
+    }
+
+    #[test]
+    fn whisper_command_passes_model_language_and_threads() {
+        let provider = LocalProvider::new(LocalOptions {
+            engine: LocalEngine::WhisperCpp,
+            binary: "whisper-cli".to_string(),
+            model: PathBuf::from("ggml-base.bin"),
+            language: Some("zh".to_string()),
+            threads: Some(4),
+            hotwords: vec!["剪映".to_string(), "必剪".to_string()],
+        });
+
+        let segment = AudioSegment::new(Path::new("in.wav"), 0.0, None);
+        let args = |segment: &AudioSegment| {
+            let (command, output_file) = provider.build_command(Path::new("whisper-cli"), segment, Path::new("/tmp/x/result"));
+            assert_eq!(output_file, PathBuf::from("/tmp/x/result.json"));
+            command.as_std().get_args().map(|a| a.to_string_lossy().into_owned()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(args(&segment), vec!["-m", "ggml-base.bin", "-f", "in.wav", "-ojf", "-of", "/tmp/x/result", "-np", "-l", "zh", "-t", "4", "--prompt", "剪映, 必剪"]);
+        // 片段指定的语言优先
+        assert_eq!(args(&segment.with_language(Some("en".to_string())))[9], "en");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn runs_the_binary_and_reads_its_output() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let model = dir.path().join("model");
+        std::fs::write(&model, b"").unwrap();
+        // 模拟 vosk-transcriber：把SRT写到 -o 指定的文件
+        let script = dir.path().join("fake-vosk");
+        std::fs::write(&script, "#!/bin/sh\n\
+            while [ $# -gt 0 ]; do [ \"$1\" = -o ] && out=$2; shift; done\n\
+            printf '1\\n00:00:00,000 --> 00:00:01,000\\n测试\\n' > \"$out\"\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let provider = LocalProvider::new(LocalOptions {
+            engine: LocalEngine::Vosk,
+            binary: script.to_string_lossy().into_owned(),
+            model,
+            language: None,
+            threads: None,
+            hotwords: Vec::new(),
+        });
+        assert!(provider.is_available().await);
+
+        let segment = AudioSegment::new(&dir.path().join("part.wav"), 0.0, None);
+        let transcript = provider.recognize(&segment).await.unwrap();
+        assert_eq!(transcript.utterances, vec![Utterance::new(0.0, 1.0, "测试")]);
+    }
+}
@@ -1,3 +1,732 @@
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use anyhow::Result;
+use indexmap::IndexMap;
+use tokio::task::JoinSet;
+use log::{info, warn, debug};
+use serde_json::Value;
+
+use crate::asr::base::{AsrProvider, AsrError, AudioSegment, Transcript};
+use crate::asr::bcut::{self, BcutProvider};
+use crate::asr::consensus::{self, Hypothesis};
+use crate::asr::google::GoogleProvider;
+use crate::asr::http::HttpClient;
+use crate::asr::limiter::{RateLimit, RateLimiter};
+use crate::asr::jianying::{self, JianyingProvider};
+use crate::asr::language;
+use crate::asr::kuaishou::{self, KuaishouProvider};
+use crate::asr::local::{LocalEngine, LocalOptions, LocalProvider};
+use crate::asr::mock::{MockOptions, MockProvider};
+use crate::asr::openai::{self, OpenAiOptions, OpenAiProvider};
+use crate::asr::plugin::{PluginProvider, PluginSpec};
+use crate::asr::selector::AsrSelector;
+use crate::core::credentials::CredentialStore;
+use crate::core::hotwords::load_hotwords;
+
+/// 默认的服务回退顺序
+pub const DEFAULT_CHAIN: &[&str] = &["bcut", "jianying"];
+
+/// 片段识别策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AsrStrategy {
+    /// 按顺序尝试，取第一个有效结果
+    #[default]
+    Fallback,
+    /// 同时发给多个服务，投票合并结果
+    Consensus,
+}
+
+impl AsrStrategy {
+    /// 从配置字符串解析策略
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "fallback" => Some(AsrStrategy::Fallback),
+            "consensus" => Some(AsrStrategy::Consensus),
+            _ => None,
+        }
+    }
+}
+
+/// 单个服务的使用统计
+#[derive(Debug, Default, Clone)]
+struct ServiceStats {
+    count: u64,
+    success: u64,
+    failure: u64,
+}
+
+/// ASR服务管理器，按优先级持有所有服务
 pub struct AsrManager {
-    // ASR provider manager
-}
\ No newline at end of file
+    providers: Vec<Arc<dyn AsrProvider>>,
+    selector: AsrSelector,
+    stats: Mutex<IndexMap<String, ServiceStats>>,
+    weights: HashMap<String, f64>,
+    consensus_size: usize,
+    limiters: HashMap<String, Arc<RateLimiter>>,
+}
+
+impl AsrManager {
+    /// 根据配置创建ASR管理器，asr_chain 中的顺序即回退顺序
+    pub fn new(config: &HashMap<String, Value>) -> Result<Self> {
+        for legacy in ["use_jianying_first", "use_kuaishou", "use_bcut", "use_openai", "use_local_asr"] {
+            if config.contains_key(legacy) {
+                warn!("配置项 {} 已废弃，请改用 asr_chain", legacy);
+            }
+        }
+
+        let dry_run = config.get("dry_run_asr").and_then(|v| v.as_bool()).unwrap_or(false);
+        let chain: Vec<String> = if dry_run {
+            info!("ASR演练模式，只使用模拟服务");
+            vec!["mock".to_string()]
+        } else {
+            match config.get("asr_chain").and_then(|v| v.as_array()) {
+                Some(chain) => chain.iter().filter_map(|v| v.as_str()).map(str::to_string).collect(),
+                None => DEFAULT_CHAIN.iter().map(|s| s.to_string()).collect(),
+            }
+        };
+
+        let client = HttpClient::from_config(config)?;
+        let hotwords = load_hotwords(config)?;
+        let credentials = CredentialStore::from_config(config)?;
+        if !hotwords.is_empty() {
+            info!("已加载 {} 个热词", hotwords.len());
+        }
+
+        let mut providers: Vec<Arc<dyn AsrProvider>> = Vec::new();
+        for name in &chain {
+            if providers.iter().any(|p| p.name() == name) {
+                warn!("ASR服务重复出现在 asr_chain 中: {}", name);
+                continue;
+            }
+            let client = client.for_provider(name)?;
+            match build_provider(name, config, &client, &hotwords, &credentials) {
+                Some(provider) => providers.push(provider),
+                None => warn!("未知的ASR服务: {}", name),
+            }
+        }
+
+        let selector = AsrSelector::new(
+            config.get("circuit_failure_threshold").and_then(|v| v.as_u64()).unwrap_or(3) as u32,
+            Duration::from_secs_f64(config.get("circuit_cooldown").and_then(|v| v.as_f64()).unwrap_or(60.0)),
+        );
+        // 探测超过请求超时仍未返回时视为放弃
+        let selector = match config.get("http").and_then(|h| h.get("timeout")).and_then(|v| v.as_f64()) {
+            Some(timeout) if timeout > 0.0 => selector.with_probe_timeout(Duration::from_secs_f64(timeout)),
+            _ => selector,
+        };
+
+        // 未单独配置优先级的服务按 asr_chain 顺序排列
+        let priorities = config.get("asr_priorities").and_then(|v| v.as_object());
+        for (idx, provider) in providers.iter().enumerate() {
+            let priority = priorities
+                .and_then(|p| p.get(provider.name()))
+                .and_then(|v| v.as_u64())
+                .unwrap_or(idx as u64);
+            selector.register(provider.name(), priority as u32);
+        }
+
+        let mut manager = Self::with_providers(providers, selector);
+        if let Some(weights) = config.get("consensus_weights").and_then(|v| v.as_object()) {
+            manager.weights = weights.iter()
+                .filter_map(|(name, w)| w.as_f64().map(|w| (name.clone(), w)))
+                .collect();
+        }
+        if let Some(size) = config.get("consensus_providers").and_then(|v| v.as_u64()) {
+            manager.consensus_size = (size as usize).max(2);
+        }
+        if let Some(limits) = config.get("asr_segment_limits").and_then(|v| v.as_object()) {
+            for (name, limit) in limits {
+                if !manager.limiters.contains_key(name) {
+                    continue;
+                }
+                match serde_json::from_value::<RateLimit>(limit.clone()) {
+                    Ok(limit) => {
+                        manager.limiters.insert(name.clone(), Arc::new(RateLimiter::new(name, &limit)));
+                    }
+                    Err(e) => warn!("限流配置无效 {}: {}", name, e),
+                }
+            }
+        }
+        Ok(manager)
+    }
+
+    /// 使用给定的服务列表和选择器创建ASR管理器
+    pub fn with_providers(providers: Vec<Arc<dyn AsrProvider>>, selector: AsrSelector) -> Self {
+        let stats = providers.iter()
+            .map(|p| (p.name().to_string(), ServiceStats::default()))
+            .collect();
+
+        let limiters = providers.iter()
+            .map(|p| (p.name().to_string(), Arc::new(RateLimiter::new(p.name(), &RateLimit::default()))))
+            .collect();
+
+        info!("已注册ASR服务: {}", providers.iter().map(|p| p.name()).collect::<Vec<_>>().join(", "));
+
+        Self {
+            providers,
+            selector,
+            stats: Mutex::new(stats),
+            weights: HashMap::new(),
+            consensus_size: 3,
+            limiters,
+        }
+    }
+
+    /// 按名称查找服务
+    fn provider(&self, name: &str) -> Option<&Arc<dyn AsrProvider>> {
+        self.providers.iter().find(|p| p.name() == name)
+    }
+
+    /// 按指定策略识别一个音频片段，结果中没有语言时记为片段指定的语言
+    pub async fn recognize_with(&self, segment: &AudioSegment, strategy: AsrStrategy) -> std::result::Result<Transcript, AsrError> {
+        if let Some(language) = &segment.language {
+            if !self.providers.iter().any(|p| language::supports(&p.capabilities().languages, Some(language))) {
+                return Err(AsrError::Unavailable(format!("没有支持语言 {} 的ASR服务", language)));
+            }
+        }
+
+        let result = match strategy {
+            AsrStrategy::Fallback => self.recognize(segment).await,
+            AsrStrategy::Consensus => self.recognize_consensus(segment).await,
+        };
+
+        result.map(|mut transcript| {
+            if transcript.language.is_none() {
+                transcript.language = segment.language.clone();
+            }
+            transcript
+        })
+    }
+
+    /// 服务是否支持该语言，未知的服务名（如投票合并的结果）视为不支持
+    pub fn supports_language(&self, name: &str, language: Option<&str>) -> bool {
+        self.provider(name)
+            .map(|p| language::supports(&p.capabilities().languages, language))
+            .unwrap_or(false)
+    }
+
+    /// 识别一个音频片段，按选择器排序依次尝试，失败或结果为空时回退到下一个服务
+    pub async fn recognize(&self, segment: &AudioSegment) -> std::result::Result<Transcript, AsrError> {
+        let names: Vec<String> = self.providers.iter().map(|p| p.name().to_string()).collect();
+        let mut last_error = None;
+        let mut empty_result = None;
+
+        for name in self.selector.rank(&names) {
+            let provider = match self.claim(&name, segment).await {
+                Some(provider) => provider,
+                None => continue,
+            };
+
+            let _permit = self.limiter(&name).acquire().await;
+            let started = Instant::now();
+            let result = provider.recognize(segment).await;
+
+            match self.finish(&name, started.elapsed(), result) {
+                Ok(transcript) if !transcript.is_empty() => return Ok(transcript),
+                Ok(transcript) => {
+                    debug!("{} 返回空结果，尝试下一个服务: {}", name, segment.file_name());
+                    empty_result = Some(transcript);
+                }
+                Err(e) => {
+                    debug!("{} 识别失败，尝试下一个服务: {} ({})", name, segment.file_name(), e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        if let Some(transcript) = empty_result {
+            return Ok(transcript);
+        }
+
+        Err(last_error.unwrap_or_else(|| AsrError::Unavailable("没有可用的ASR服务".to_string())))
+    }
+
+    /// 同时用排在最前的若干服务识别片段，按权重投票合并；只有一个有效结果时直接返回
+    pub async fn recognize_consensus(&self, segment: &AudioSegment) -> std::result::Result<Transcript, AsrError> {
+        let names: Vec<String> = self.providers.iter().map(|p| p.name().to_string()).collect();
+        let mut tasks = JoinSet::new();
+
+        for name in self.selector.rank(&names) {
+            if tasks.len() >= self.consensus_size {
+                break;
+            }
+            let provider = match self.claim(&name, segment).await {
+                Some(provider) => Arc::clone(provider),
+                None => continue,
+            };
+
+            let limiter = self.limiter(&name);
+            let segment = segment.clone();
+            tasks.spawn(async move {
+                let _permit = limiter.acquire().await;
+                let started = Instant::now();
+                let result = provider.recognize(&segment).await;
+                (name, started.elapsed(), result)
+            });
+        }
+
+        let mut transcripts = Vec::new();
+        let mut last_error = None;
+        while let Some(joined) = tasks.join_next().await {
+            let (name, latency, result) = match joined {
+                Ok(joined) => joined,
+                Err(e) => {
+                    warn!("识别任务异常退出: {}", e);
+                    continue;
+                }
+            };
+            match self.finish(&name, latency, result) {
+                Ok(transcript) if !transcript.is_empty() => transcripts.push(transcript),
+                Ok(_) => debug!("{} 返回空结果，不参与投票: {}", name, segment.file_name()),
+                Err(e) => {
+                    debug!("{} 识别失败，不参与投票: {} ({})", name, segment.file_name(), e);
+                    last_error = Some(e);
+                }
+            }
+        }
+
+        if transcripts.len() < 2 {
+            return match transcripts.pop() {
+                Some(transcript) => {
+                    debug!("只有 {} 给出结果，跳过投票: {}", transcript.provider, segment.file_name());
+                    Ok(transcript)
+                }
+                None => Err(last_error.unwrap_or_else(|| AsrError::Unavailable("没有可用的ASR服务".to_string()))),
+            };
+        }
+
+        // 权重高的排在前面，权重相同按 asr_chain 顺序
+        let order = |provider: &str| self.providers.iter().position(|p| p.name() == provider).unwrap_or(usize::MAX);
+        transcripts.sort_by(|a, b| {
+            self.weight(&b.provider).partial_cmp(&self.weight(&a.provider))
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .then(order(&a.provider).cmp(&order(&b.provider)))
+        });
+
+        let name = format!("consensus:{}", transcripts.iter().map(|t| t.provider.as_str()).collect::<Vec<_>>().join("+"));
+        let hypotheses: Vec<Hypothesis> = transcripts.iter()
+            .map(|t| Hypothesis { weight: self.weight(&t.provider), transcript: t })
+            .collect();
+        let merged = consensus::merge(&name, &hypotheses);
+
+        if !merged.disagreements.is_empty() {
+            debug!("片段 {} 存在 {} 处分歧", segment.file_name(), merged.disagreements.len());
+        }
+
+        Ok(merged)
+    }
+
+    /// 识别时已使用热词的服务
+    pub fn native_hotword_providers(&self) -> HashSet<String> {
+        self.providers.iter()
+            .filter(|p| p.capabilities().hotwords)
+            .map(|p| p.name().to_string())
+            .collect()
+    }
+
+    /// 缓存查找顺序：回退模式依次为各服务，投票模式为参与投票的服务组合
+    pub fn cache_profiles(&self, strategy: AsrStrategy) -> Vec<String> {
+        let profiles: Vec<String> = self.providers.iter()
+            .map(|p| format!("{}|{}", p.name(), p.cache_fingerprint()))
+            .collect();
+
+        match strategy {
+            AsrStrategy::Fallback => profiles,
+            AsrStrategy::Consensus => {
+                let mut weights: Vec<_> = self.weights.iter().collect();
+                weights.sort_by(|a, b| a.0.cmp(b.0));
+                vec![format!("consensus|{}|{}|{:?}", self.consensus_size, profiles.join(";"), weights)]
+            }
+        }
+    }
+
+    /// 识别结果应保存到的缓存键
+    pub fn cache_profile_for(&self, strategy: AsrStrategy, transcript: &Transcript) -> Option<String> {
+        match strategy {
+            AsrStrategy::Fallback => self.provider(&transcript.provider)
+                .map(|p| format!("{}|{}", p.name(), p.cache_fingerprint())),
+            AsrStrategy::Consensus => self.cache_profiles(strategy).pop(),
+        }
+    }
+
+    /// 投票权重，未配置时为 1
+    fn weight(&self, name: &str) -> f64 {
+        self.weights.get(name).copied().unwrap_or(1.0)
+    }
+
+    /// 服务的限流器
+    fn limiter(&self, name: &str) -> Arc<RateLimiter> {
+        self.limiters.get(name)
+            .cloned()
+            .unwrap_or_else(|| Arc::new(RateLimiter::new(name, &RateLimit::default())))
+    }
+
+    /// 检查服务支持片段语言且可用，并占用调用机会，不可调用时返回 None
+    async fn claim(&self, name: &str, segment: &AudioSegment) -> Option<&Arc<dyn AsrProvider>> {
+        let provider = self.provider(name)?;
+
+        if !language::supports(&provider.capabilities().languages, segment.language.as_deref()) {
+            debug!("{} 不支持语言 {}，跳过", name, segment.language.as_deref().unwrap_or(""));
+            return None;
+        }
+
+        if !provider.is_available().await {
+            debug!("ASR服务不可用，跳过: {}", name);
+            self.selector.record_skip(name, "服务报告不可用");
+            return None;
+        }
+
+        if !self.selector.acquire(name) {
+            return None;
+        }
+
+        Some(provider)
+    }
+
+    /// 记录一次调用的结果。认证失败立即熔断；服务端错误、响应格式不符和任务失败计入连续失败；
+    /// 空结果可能是静音，本地IO或请求本身的错误换个服务也一样，都不计入熔断
+    fn finish(
+        &self,
+        name: &str,
+        latency: Duration,
+        result: std::result::Result<Transcript, AsrError>,
+    ) -> std::result::Result<Transcript, AsrError> {
+        self.record_result(name, matches!(&result, Ok(t) if !t.is_empty()));
+
+        match &result {
+            Ok(_) => self.selector.record_success(name, latency),
+            Err(e @ AsrError::Unauthorized(_)) => self.selector.trip(name, latency, &e.to_string()),
+            Err(e) if e.is_retryable() || matches!(e, AsrError::InvalidResponse(_) | AsrError::TaskFailed(_)) => {
+                self.selector.record_failure(name, latency, &e.to_string())
+            }
+            Err(_) => self.selector.release(name),
+        }
+
+        if let Some(limiter) = self.limiters.get(name) {
+            match &result {
+                Err(AsrError::QuotaExceeded(_)) => limiter.on_throttle(),
+                Ok(_) => limiter.on_success(),
+                Err(_) => {}
+            }
+        }
+
+        result
+    }
+
+    /// 记录一次调用结果
+    fn record_result(&self, name: &str, success: bool) {
+        let mut stats = self.stats.lock().unwrap();
+        let entry = stats.entry(name.to_string()).or_default();
+        entry.count += 1;
+        if success {
+            entry.success += 1;
+        } else {
+            entry.failure += 1;
+        }
+    }
+
+    /// 获取各服务的使用统计
+    pub fn get_service_stats(&self) -> IndexMap<String, HashMap<String, Value>> {
+        let stats = self.stats.lock().unwrap();
+
+        stats.iter()
+            .map(|(name, stat)| {
+                let success_rate = if stat.count > 0 {
+                    stat.success as f64 / stat.count as f64
+                } else {
+                    0.0
+                };
+
+                let health = self.selector.snapshot(name);
+
+                let mut entry = HashMap::from([
+                    ("count".to_string(), Value::from(stat.count)),
+                    ("success".to_string(), Value::from(stat.success)),
+                    ("failure".to_string(), Value::from(stat.failure)),
+                    ("success_rate".to_string(), Value::from(success_rate)),
+                    ("available".to_string(), Value::Bool(health.as_ref().map(|h| h.available).unwrap_or(true))),
+                ]);
+
+                if let Some(limiter) = self.limiters.get(name) {
+                    entry.insert("wait_time".to_string(), Value::from(limiter.waited().as_secs_f64()));
+                    entry.insert("throttled".to_string(), Value::from(limiter.throttled()));
+                }
+
+                if let Some(health) = health {
+                    if let Some(latency) = health.avg_latency {
+                        entry.insert("avg_latency".to_string(), Value::from(latency.as_secs_f64()));
+                    }
+                    if let Some(reason) = health.skip_reason {
+                        entry.insert("skip_reason".to_string(), Value::String(reason));
+                    }
+                }
+
+                (name.clone(), entry)
+            })
+            .collect()
+    }
+
+    /// 释放资源
+    pub fn close(&self) -> Result<()> {
+        debug!("关闭ASR管理器");
+        Ok(())
+    }
+}
+
+/// 根据名称和配置创建服务，未知名称返回 None
+fn build_provider(
+    name: &str,
+    config: &HashMap<String, Value>,
+    client: &HttpClient,
+    hotwords: &[String],
+    credentials: &CredentialStore,
+) -> Option<Arc<dyn AsrProvider>> {
+    let get_str = |key: &str, default: &str| config.get(key).and_then(|v| v.as_str()).unwrap_or(default).to_string();
+    let get_opt = |key: &str| config.get(key)
+        .and_then(|v| v.as_str())
+        .filter(|s| !s.is_empty())
+        .map(str::to_string);
+
+    let provider: Arc<dyn AsrProvider> = match name {
+        "bcut" => Arc::new(BcutProvider::with_base_url(&get_str("bcut_base_url", bcut::DEFAULT_BASE_URL))
+            .with_cookie(credentials.get("bcut", "cookie"))
+            .with_client(client.clone())),
+        "jianying" => Arc::new(JianyingProvider::with_endpoints(
+            &get_str("jianying_base_url", jianying::DEFAULT_BASE_URL),
+            &get_str("jianying_vod_url", jianying::DEFAULT_VOD_URL),
+        ).with_device_id(&credentials.get("jianying", "device_id")
+            .unwrap_or_else(|| get_str("jianying_device_id", jianying::DEFAULT_DEVICE_ID)))
+            .with_cookie(credentials.get("jianying", "cookie"))
+            .with_client(client.clone())),
+        "kuaishou" => Arc::new(KuaishouProvider::with_endpoint(&get_str("kuaishou_endpoint", kuaishou::DEFAULT_ENDPOINT))
+            .with_cookie(credentials.get("kuaishou", "cookie"))
+            .with_client(client.clone())),
+        "google" => Arc::new(GoogleProvider::new()),
+        "mock" => {
+            let options = match config.get("mock_asr") {
+                Some(value) => match serde_json::from_value::<MockOptions>(value.clone()) {
+                    Ok(options) => options,
+                    Err(e) => {
+                        warn!("模拟服务配置无效: {}", e);
+                        MockOptions::default()
+                    }
+                },
+                None => MockOptions::default(),
+            };
+            Arc::new(MockProvider::new(options))
+        }
+        "openai" => Arc::new(OpenAiProvider::new(OpenAiOptions {
+            base_url: get_str("openai_base_url", openai::DEFAULT_BASE_URL),
+            model: get_str("openai_model", openai::DEFAULT_MODEL),
+            api_key: credentials.get("openai", "api_key")
+                .or_else(|| get_opt("openai_api_key"))
+                .or_else(|| std::env::var("OPENAI_API_KEY").ok().filter(|k| !k.is_empty())),
+            language: get_opt("openai_language"),
+            hotwords: hotwords.to_vec(),
+        }).with_client(client.clone())),
+        "local" => {
+            let engine_name = get_str("local_asr_engine", "whisper_cpp");
+            let engine = match LocalEngine::parse(&engine_name) {
+                Some(engine) => engine,
+                None => {
+                    warn!("未知的本地识别引擎: {}", engine_name);
+                    return None;
+                }
+            };
+            Arc::new(LocalProvider::new(LocalOptions {
+                engine,
+                binary: get_str("local_asr_binary", "whisper-cli"),
+                model: PathBuf::from(get_str("local_asr_model", "")),
+                language: get_opt("local_asr_language"),
+                threads: config.get("local_asr_threads").and_then(|v| v.as_u64()).map(|t| t as u32),
+                hotwords: hotwords.to_vec(),
+            }))
+        }
+        _ => {
+            // 其余名称在 asr_plugins 中查找
+            let spec = config.get("asr_plugins")?.get(name)?;
+            match serde_json::from_value::<PluginSpec>(spec.clone()) {
+                Ok(spec) => Arc::new(PluginProvider::new(name, spec)
+                    .with_hotwords(hotwords.to_vec())
+                    .with_credentials(credentials.for_provider(name))),
+                Err(e) => {
+                    warn!("插件配置无效 {}: {}", name, e);
+                    return None;
+                }
+            }
+        }
+    };
+
+    Some(provider)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+    use async_trait::async_trait;
+    use crate::asr::base::{AsrCapabilities, Utterance};
+
+    /// 每次调用都返回同样结果的服务
+    struct FixedProvider {
+        name: &'static str,
+        result: fn() -> std::result::Result<Transcript, AsrError>,
+    }
+
+    #[async_trait]
+    impl AsrProvider for FixedProvider {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn capabilities(&self) -> AsrCapabilities {
+            AsrCapabilities::default()
+        }
+
+        async fn is_available(&self) -> bool {
+            true
+        }
+
+        async fn recognize(&self, _segment: &AudioSegment) -> std::result::Result<Transcript, AsrError> {
+            (self.result)()
+        }
+    }
+
+    fn chain(providers: Vec<FixedProvider>) -> AsrManager {
+        let selector = AsrSelector::new(3, Duration::from_secs(60));
+        for (idx, provider) in providers.iter().enumerate() {
+            selector.register(provider.name, idx as u32);
+        }
+        AsrManager::with_providers(
+            providers.into_iter().map(|p| Arc::new(p) as Arc<dyn AsrProvider>).collect(),
+            selector,
+        )
+    }
+
+    fn segment() -> AudioSegment {
+        AudioSegment::new(Path::new("part.mp3"), 0.0, None)
+    }
+
+    fn provider_names(config: serde_json::Value) -> Vec<String> {
+        let config: HashMap<String, Value> = serde_json::from_value(config).unwrap();
+        AsrManager::new(&config).unwrap().providers.iter().map(|p| p.name().to_string()).collect()
+    }
+
+    #[test]
+    fn chain_order_follows_the_config() {
+        assert_eq!(provider_names(serde_json::json!({})), DEFAULT_CHAIN);
+        assert_eq!(provider_names(serde_json::json!({"asr_chain": ["jianying", "google", "bcut"]})),
+            vec!["jianying", "google", "bcut"]);
+    }
+
+    #[test]
+    fn chain_skips_duplicates_and_unknown_names() {
+        assert_eq!(provider_names(serde_json::json!({"asr_chain": ["bcut", "nope", "bcut", "kuaishou"]})),
+            vec!["bcut", "kuaishou"]);
+    }
+
+    #[test]
+    fn chain_resolves_plugin_names() {
+        let names = provider_names(serde_json::json!({
+            "asr_chain": ["my-plugin", "broken-plugin"],
+            "asr_plugins": {"my-plugin": {"command": "sh"}, "broken-plugin": {"args": []}}
+        }));
+        assert_eq!(names, vec!["my-plugin"]);
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_the_next_provider() {
+        let manager = chain(vec![
+            FixedProvider { name: "a", result: || Err(AsrError::Transient("502".to_string())) },
+            FixedProvider { name: "b", result: || Ok(Transcript::new("b", vec![])) },
+            FixedProvider { name: "c", result: || Ok(Transcript::new("c", vec![Utterance::new(0.0, 1.0, "你好")])) },
+        ]);
+
+        let transcript = manager.recognize(&segment()).await.unwrap();
+        assert_eq!(transcript.provider, "c");
+        let stats = manager.get_service_stats();
+        assert_eq!(stats["a"]["failure"], Value::from(1));
+        assert_eq!(stats["b"]["failure"], Value::from(1));
+        assert_eq!(stats["c"]["success"], Value::from(1));
+    }
+
+    #[tokio::test]
+    async fn empty_result_wins_over_errors() {
+        let manager = chain(vec![
+            FixedProvider { name: "a", result: || Ok(Transcript::new("a", vec![])) },
+            FixedProvider { name: "b", result: || Err(AsrError::Transient("502".to_string())) },
+        ]);
+        assert_eq!(manager.recognize(&segment()).await.unwrap().provider, "a");
+
+        let manager = chain(vec![
+            FixedProvider { name: "a", result: || Err(AsrError::Request("400".to_string())) },
+            FixedProvider { name: "b", result: || Err(AsrError::Transient("502".to_string())) },
+        ]);
+        assert!(matches!(manager.recognize(&segment()).await, Err(AsrError::Transient(_))));
+    }
+
+    #[tokio::test]
+    async fn consensus_merges_the_top_providers() {
+        let manager = chain(vec![
+            FixedProvider { name: "a", result: || Ok(Transcript::new("a", vec![Utterance::new(0.0, 1.0, "今天天汽")])) },
+            FixedProvider { name: "b", result: || Ok(Transcript::new("b", vec![Utterance::new(0.0, 1.0, "今天天气")])) },
+            FixedProvider { name: "c", result: || Ok(Transcript::new("c", vec![Utterance::new(0.0, 1.0, "今天天气")])) },
+        ]);
+
+        let merged = manager.recognize_with(&segment(), AsrStrategy::Consensus).await.unwrap();
+        assert_eq!(merged.provider, "consensus:a+b+c");
+        assert_eq!(merged.text(), "今天天气");
+        assert_eq!(merged.disagreements.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn consensus_with_one_result_returns_it_unchanged() {
+        let manager = chain(vec![
+            FixedProvider { name: "a", result: || Err(AsrError::Transient("502".to_string())) },
+            FixedProvider { name: "b", result: || Ok(Transcript::new("b", vec![Utterance::new(0.0, 1.0, "你好")])) },
+        ]);
+
+        assert_eq!(manager.recognize_consensus(&segment()).await.unwrap().provider, "b");
+    }
+
+    async fn failures_after_three_calls(error: fn() -> std::result::Result<Transcript, AsrError>) -> u32 {
+        let manager = chain(vec![FixedProvider { name: "failing", result: error }]);
+
+        for _ in 0..3 {
+            assert!(manager.recognize(&segment()).await.is_err());
+        }
+        manager.selector.snapshot("failing").unwrap().consecutive_failures
+    }
+
+    #[tokio::test]
+    async fn only_service_side_errors_trip_the_circuit() {
+        assert_eq!(failures_after_three_calls(|| Err(AsrError::Transient("502".to_string()))).await, 3);
+        assert_eq!(failures_after_three_calls(|| Err(AsrError::Timeout("slow".to_string()))).await, 3);
+        assert_eq!(failures_after_three_calls(|| Err(AsrError::QuotaExceeded("429".to_string()))).await, 3);
+        assert_eq!(failures_after_three_calls(|| Err(AsrError::InvalidResponse("<html>".to_string()))).await, 3);
+        assert_eq!(failures_after_three_calls(|| Err(AsrError::TaskFailed("state 3".to_string()))).await, 3);
+
+        assert_eq!(failures_after_three_calls(|| Err(AsrError::Request("400".to_string()))).await, 0);
+        assert_eq!(failures_after_three_calls(|| Err(std::io::Error::from(std::io::ErrorKind::NotFound).into())).await, 0);
+    }
+
+    #[tokio::test]
+    async fn unauthorized_provider_is_taken_out_of_the_chain() {
+        let manager = chain(vec![
+            FixedProvider { name: "expired", result: || Err(AsrError::Unauthorized("HTTP 401: cookie".to_string())) },
+            FixedProvider { name: "b", result: || Ok(Transcript::new("b", vec![Utterance::new(0.0, 1.0, "你好")])) },
+        ]);
+
+        for _ in 0..3 {
+            assert_eq!(manager.recognize(&segment()).await.unwrap().provider, "b");
+        }
+
+        let health = manager.selector.snapshot("expired").unwrap();
+        assert!(!health.available);
+        assert_eq!(health.consecutive_failures, 1);
+        assert!(health.skip_reason.unwrap().contains("认证失败"));
+        assert_eq!(manager.get_service_stats()["expired"]["count"], Value::from(1));
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use async_trait::async_trait;
+use log::debug;
+use serde::Deserialize;
+
+use crate::asr::base::{AsrProvider, AsrCapabilities, AsrError, AudioSegment, Transcript, Utterance};
+
+/// 片段时长未知时假定的时长（秒）
+const DEFAULT_SEGMENT_SECS: f64 = 30.0;
+
+/// 每句的时长（秒）
+const UTTERANCE_SECS: f64 = 5.0;
+
+/// 模拟服务配置，概率取值 0~1
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct MockOptions {
+    /// 返回错误的概率
+    #[serde(default)]
+    pub failure_rate: f64,
+
+    /// 返回空结果的概率
+    #[serde(default)]
+    pub empty_rate: f64,
+
+    /// 每次调用的固定延迟（毫秒）
+    #[serde(default)]
+    pub latency_ms: u64,
+
+    /// 额外的随机延迟上限（毫秒）
+    #[serde(default)]
+    pub latency_jitter_ms: u64,
+
+    /// 随机种子，相同种子下结果完全相同
+    #[serde(default)]
+    pub seed: u64,
+
+    /// 说话人数，大于 0 时各句轮流标上说话人
+    #[serde(default)]
+    pub speakers: u32,
+}
+
+/// 不联网的模拟ASR服务
+///
+/// 文本和时间戳由片段文件名和时长决定；失败、空结果和延迟按文件名、
+/// 调用次数和种子计算，同一片段的重试会得到不同的结果，但每次运行都一样。
+/// 调用次数按片段路径和起始位置分别计数，不同目录下的同名片段互不影响。
+pub struct MockProvider {
+    name: String,
+    options: MockOptions,
+    attempts: Mutex<HashMap<String, u32>>,
+}
+
+impl MockProvider {
+    /// 创建新的模拟服务
+    pub fn new(options: MockOptions) -> Self {
+        Self {
+            name: "mock".to_string(),
+            options,
+            attempts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 设置服务名，便于同时注册多个模拟服务
+    pub fn with_name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// 按文件名、调用次数和用途生成 [0, 1) 的确定性随机数
+    fn roll(&self, file_name: &str, attempt: u32, purpose: &str) -> f64 {
+        let key = format!("{}|{}|{}|{}", self.options.seed, file_name, attempt, purpose);
+        crc32fast::hash(key.as_bytes()) as f64 / (u32::MAX as f64 + 1.0)
+    }
+}
+
+impl Default for MockProvider {
+    fn default() -> Self {
+        Self::new(MockOptions::default())
+    }
+}
+
+#[async_trait]
+impl AsrProvider for MockProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn capabilities(&self) -> AsrCapabilities {
+        AsrCapabilities {
+            requires_network: false,
+            timestamps: true,
+            hotwords: false,
+            languages: Vec::new(),
+            max_segment_duration: None,
+        }
+    }
+
+    fn cache_fingerprint(&self) -> String {
+        format!("{}|{}|{}|{}", self.options.seed, self.options.failure_rate, self.options.empty_rate, self.options.speakers)
+    }
+
+    async fn is_available(&self) -> bool {
+        true
+    }
+
+    async fn recognize(&self, segment: &AudioSegment) -> Result<Transcript, AsrError> {
+        let file_name = segment.file_name();
+        let attempt = {
+            let mut attempts = self.attempts.lock().unwrap();
+            let key = format!("{}@{}", segment.path.display(), segment.offset);
+            let count = attempts.entry(key).or_insert(0);
+            *count += 1;
+            *count
+        };
+
+        let jitter = (self.roll(&file_name, attempt, "latency") * self.options.latency_jitter_ms as f64) as u64;
+        let latency = Duration::from_millis(self.options.latency_ms + jitter);
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+
+        if self.roll(&file_name, attempt, "failure") < self.options.failure_rate {
+            debug!("模拟识别失败: {} (第 {} 次)", file_name, attempt);
+            return Err(AsrError::Transient(format!("模拟失败: {}", file_name)));
+        }
+
+        if self.roll(&file_name, attempt, "empty") < self.options.empty_rate {
+            debug!("模拟空结果: {} (第 {} 次)", file_name, attempt);
+            return Ok(Transcript::new(self.name(), Vec::new()));
+        }
+
+        let stem = segment.path.file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| file_name.clone());
+        let duration = segment.duration.filter(|d| *d > 0.0).unwrap_or(DEFAULT_SEGMENT_SECS);
+
+        let mut utterances = Vec::new();
+        let mut start = 0.0;
+        while start < duration {
+            let end = (start + UTTERANCE_SECS).min(duration);
+            let text = format!("模拟文本 {} 第{}句。", stem, utterances.len() + 1);
+            let mut utterance = Utterance::new(start, end, &text);
+            utterance.confidence = Some(0.5 + 0.5 * self.roll(&file_name, utterances.len() as u32, "confidence") as f32);
+            if self.options.speakers > 0 {
+                utterance.speaker = Some(format!("spk{}", utterances.len() % self.options.speakers as usize));
+            }
+            utterances.push(utterance);
+            start = end;
+        }
+
+        Ok(Transcript::new(self.name(), utterances))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[tokio::test]
+    async fn attempts_are_counted_per_segment_path() {
+        let provider = MockProvider::default();
+        let first = AudioSegment::new(Path::new("/tmp/a/part_0000.wav"), 0.0, Some(10.0));
+        let second = AudioSegment::new(Path::new("/tmp/b/part_0000.wav"), 0.0, Some(10.0));
+
+        provider.recognize(&first).await.unwrap();
+        provider.recognize(&first).await.unwrap();
+        provider.recognize(&second).await.unwrap();
+
+        // 不同目录下的同名片段分别计数
+        let attempts = provider.attempts.lock().unwrap();
+        let mut counts: Vec<u32> = attempts.values().copied().collect();
+        counts.sort_unstable();
+        assert_eq!(counts, [1, 2]);
+    }
+
+    #[tokio::test]
+    async fn uses_the_configured_name() {
+        let provider = MockProvider::default().with_name("mock-b");
+        let segment = AudioSegment::new(Path::new("/tmp/a/part_0000.wav"), 0.0, Some(10.0));
+
+        assert_eq!(provider.recognize(&segment).await.unwrap().provider, "mock-b");
+    }
+}
@@ -1,6 +1,17 @@
 pub mod base;
+pub mod http;
+pub mod language;
 pub mod bcut;
 pub mod google;
 pub mod jianying;
+pub mod kuaishou;
+pub mod local;
+pub mod mock;
+pub mod openai;
+pub mod plugin;
 pub mod selector;
-pub mod manager;
\ No newline at end of file
+pub mod limiter;
+pub mod cache;
+pub mod consensus;
+pub mod manager;
+pub mod utils;
\ No newline at end of file
@@ -0,0 +1,336 @@
+use async_trait::async_trait;
+use log::debug;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::asr::base::{AsrProvider, AsrCapabilities, AsrError, AudioSegment, Transcript, Utterance, Word};
+use crate::asr::http::HttpClient;
+use crate::asr::language;
+
+/// 默认接口地址
+pub const DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
+/// 默认模型
+pub const DEFAULT_MODEL: &str = "whisper-1";
+
+/// verbose_json 格式的响应
+#[derive(Debug, Deserialize)]
+struct VerboseTranscription {
+    #[serde(default)]
+    text: String,
+    #[serde(default)]
+    duration: Option<f64>,
+    #[serde(default)]
+    language: Option<String>,
+    #[serde(default)]
+    segments: Vec<OpenAiSegment>,
+    #[serde(default)]
+    words: Vec<OpenAiWord>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiSegment {
+    start: f64,
+    end: f64,
+    text: String,
+    #[serde(default)]
+    avg_logprob: Option<f64>,
+    /// 支持说话人分离的兼容接口返回
+    #[serde(default)]
+    speaker: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct OpenAiWord {
+    word: String,
+    start: f64,
+    end: f64,
+    #[serde(default)]
+    probability: Option<f64>,
+}
+
+/// OpenAI兼容的 /audio/transcriptions 接口配置
+#[derive(Debug, Clone)]
+pub struct OpenAiOptions {
+    pub base_url: String,
+    pub model: String,
+    pub api_key: Option<String>,
+    pub language: Option<String>,
+    /// 热词，作为 prompt 发送
+    pub hotwords: Vec<String>,
+}
+
+impl Default for OpenAiOptions {
+    fn default() -> Self {
+        Self {
+            base_url: DEFAULT_BASE_URL.to_string(),
+            model: DEFAULT_MODEL.to_string(),
+            api_key: None,
+            language: None,
+            hotwords: Vec::new(),
+        }
+    }
+}
+
+/// OpenAI兼容接口的ASR服务，可对接自建的 whisper 服务
+pub struct OpenAiProvider {
+    client: HttpClient,
+    options: OpenAiOptions,
+}
+
+impl OpenAiProvider {
+    /// 创建新的OpenAI兼容ASR服务
+    pub fn new(options: OpenAiOptions) -> Self {
+        Self {
+            client: HttpClient::default(),
+            options,
+        }
+    }
+
+    /// 使用共享的HTTP客户端
+    pub fn with_client(mut self, client: HttpClient) -> Self {
+        self.client = client;
+        self
+    }
+
+    fn endpoint(&self) -> String {
+        format!("{}/audio/transcriptions", self.options.base_url.trim_end_matches('/'))
+    }
+}
+
+#[async_trait]
+impl AsrProvider for OpenAiProvider {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    fn capabilities(&self) -> AsrCapabilities {
+        AsrCapabilities {
+            requires_network: true,
+            timestamps: true,
+            hotwords: true,
+            languages: Vec::new(),
+            max_segment_duration: None,
+        }
+    }
+
+    fn cache_fingerprint(&self) -> String {
+        format!("{}|{}|{}|{}",
+            self.options.base_url,
+            self.options.model,
+            self.options.language.as_deref().unwrap_or(""),
+            self.options.hotwords.join(","))
+    }
+
+    async fn is_available(&self) -> bool {
+        true
+    }
+
+    async fn recognize(&self, segment: &AudioSegment) -> Result<Transcript, AsrError> {
+        let data = tokio::fs::read(&segment.path).await?;
+
+        let file_part = reqwest::multipart::Part::bytes(data)
+            .file_name(segment.file_name());
+        let mut form = reqwest::multipart::Form::new()
+            .part("file", file_part)
+            .text("model", self.options.model.clone())
+            .text("response_format", "verbose_json")
+            .text("timestamp_granularities[]", "segment")
+            .text("timestamp_granularities[]", "word");
+        if let Some(language) = segment.language.as_ref().or(self.options.language.as_ref()) {
+            form = form.text("language", language.clone());
+        }
+        if !self.options.hotwords.is_empty() {
+            form = form.text("prompt", self.options.hotwords.join(", "));
+        }
+
+        let mut request = self.client.post(self.endpoint()).multipart(form);
+        if let Some(api_key) = &self.options.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let response = self.client.execute(request).await?;
+        let status = response.status();
+        let body = response.text();
+        if !status.is_success() {
+            return Err(AsrError::from_status(status, &body));
+        }
+
+        let parsed: VerboseTranscription = serde_json::from_str(&body)
+            .map_err(|e| AsrError::InvalidResponse(format!("{}: {}", e, body)))?;
+        debug!("OpenAI兼容接口识别完成: {} 段, {} 词 ({})", parsed.segments.len(), parsed.words.len(), segment.file_name());
+
+        // 接口返回的是语言名称，如 english
+        let language = parsed.language.as_deref().and_then(language::normalize);
+        let mut transcript = Transcript::new(self.name(), to_utterances(parsed));
+        transcript.language = language;
+        Ok(transcript)
+    }
+}
+
+/// 说话人可能是数字或字符串
+fn speaker_id(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.is_empty() => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// 词所属的段落：包含词开始时间的段落，落在段落之间或之外时取最近的
+fn segment_index(segments: &[OpenAiSegment], start: f64) -> usize {
+    segments.iter()
+        .position(|s| start >= s.start && start < s.end)
+        .unwrap_or_else(|| {
+            let distance = |s: &OpenAiSegment| (s.start - start).max(start - s.end);
+            (0..segments.len())
+                .min_by(|&a, &b| distance(&segments[a]).total_cmp(&distance(&segments[b])))
+                .unwrap_or(0)
+        })
+}
+
+/// 把段落和词级结果合并为语句，词按时间归入所在或最近的段落
+fn to_utterances(parsed: VerboseTranscription) -> Vec<Utterance> {
+    let words: Vec<Word> = parsed.words.into_iter()
+        .map(|w| Word {
+            start: w.start,
+            end: w.end,
+            text: w.word.trim().to_string(),
+            confidence: w.probability.map(|p| p as f32),
+        })
+        .collect();
+
+    if parsed.segments.is_empty() {
+        if parsed.text.trim().is_empty() {
+            return Vec::new();
+        }
+
+        let start = words.first().map(|w| w.start).unwrap_or(0.0);
+        let end = words.last().map(|w| w.end).or(parsed.duration).unwrap_or(start);
+        let mut utterance = Utterance::new(start, end, &parsed.text);
+        utterance.words = words;
+        return vec![utterance];
+    }
+
+    let mut utterances: Vec<Utterance> = parsed.segments.iter()
+        .map(|s| {
+            let mut utterance = Utterance::new(s.start, s.end, &s.text);
+            utterance.confidence = s.avg_logprob.map(|lp| lp.exp().clamp(0.0, 1.0) as f32);
+            utterance.speaker = s.speaker.as_ref().and_then(speaker_id);
+            utterance
+        })
+        .collect();
+    for word in words {
+        utterances[segment_index(&parsed.segments, word.start)].words.push(word);
+    }
+    utterances
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(body: &str) -> Vec<Utterance> {
+        to_utterances(serde_json::from_str(body).unwrap())
+    }
+
+    #[test]
+    fn words_are_grouped_into_their_segments() {
+        let utterances = parse(r#"{
+            "text": "hello world again",
+            "segments": [
+                {"start": 0.0, "end": 1.0, "text": " hello world", "avg_logprob": -0.1},
+                {"start": 1.0, "end": 2.0, "text": " again"}
+            ],
+            "words": [
+                {"word": "hello", "start": 0.0, "end": 0.4, "probability": 0.9},
+                {"word": " world", "start": 0.5, "end": 0.9},
+                {"word": "again", "start": 1.2, "end": 1.8}
+            ]
+        }"#);
+
+        assert_eq!(utterances.len(), 2);
+        assert_eq!(utterances[0].text, "hello world");
+        let words: Vec<&str> = utterances[0].words.iter().map(|w| w.text.as_str()).collect();
+        assert_eq!(words, vec!["hello", "world"]);
+        assert_eq!(utterances[0].words[0].confidence, Some(0.9));
+        assert_eq!(utterances[0].words[1].confidence, None);
+        assert_eq!(utterances[1].words.len(), 1);
+        assert_eq!(utterances[1].words[0].start, 1.2);
+    }
+
+    #[test]
+    fn words_outside_segments_go_to_the_nearest_one() {
+        let utterances = parse(r#"{
+            "text": "hello world again",
+            "segments": [
+                {"start": 0.0, "end": 1.0, "text": " hello"},
+                {"start": 1.5, "end": 2.0, "text": " world again"}
+            ],
+            "words": [
+                {"word": "hello", "start": 0.2, "end": 0.6},
+                {"word": "world", "start": 1.1, "end": 1.4, "probability": 0.4},
+                {"word": "again", "start": 2.0, "end": 2.3, "probability": 0.8}
+            ]
+        }"#);
+
+        let words = |idx: usize| utterances[idx].words.iter().map(|w| w.text.as_str()).collect::<Vec<_>>();
+        assert_eq!(words(0), vec!["hello", "world"]);
+        assert_eq!(words(1), vec!["again"]);
+        assert_eq!(utterances[1].words[0].confidence, Some(0.8));
+    }
+
+    #[test]
+    fn segment_confidence_comes_from_avg_logprob() {
+        let utterances = parse(r#"{
+            "segments": [
+                {"start": 0.0, "end": 1.0, "text": "a", "avg_logprob": -0.5},
+                {"start": 1.0, "end": 2.0, "text": "b", "avg_logprob": 0.3},
+                {"start": 2.0, "end": 3.0, "text": "c"}
+            ]
+        }"#);
+
+        let confidence = utterances[0].confidence.unwrap();
+        assert!((confidence - (-0.5f64).exp() as f32).abs() < 1e-6);
+        // 正的对数概率截断到1
+        assert_eq!(utterances[1].confidence, Some(1.0));
+        assert_eq!(utterances[2].confidence, None);
+    }
+
+    #[test]
+    fn text_without_segments_becomes_one_utterance() {
+        let utterances = parse(r#"{
+            "text": " 你好世界 ",
+            "duration": 2.5,
+            "words": [{"word": "你好", "start": 0.2, "end": 0.8}]
+        }"#);
+
+        assert_eq!(utterances.len(), 1);
+        assert_eq!(utterances[0].text, "你好世界");
+        assert_eq!(utterances[0].start, 0.2);
+        assert_eq!(utterances[0].end, 0.8);
+
+        let utterances = parse(r#"{"text": "你好", "duration": 2.5}"#);
+        assert_eq!((utterances[0].start, utterances[0].end), (0.0, 2.5));
+    }
+
+    #[test]
+    fn speakers_are_taken_from_segments() {
+        let utterances = parse(r#"{
+            "segments": [
+                {"start": 0.0, "end": 1.0, "text": "a", "speaker": "SPEAKER_00"},
+                {"start": 1.0, "end": 2.0, "text": "b", "speaker": 1},
+                {"start": 2.0, "end": 3.0, "text": "c", "speaker": ""},
+                {"start": 3.0, "end": 4.0, "text": "d"}
+            ]
+        }"#);
+
+        let speakers: Vec<Option<&str>> = utterances.iter().map(|u| u.speaker.as_deref()).collect();
+        assert_eq!(speakers, vec![Some("SPEAKER_00"), Some("1"), None, None]);
+    }
+
+    #[test]
+    fn empty_text_yields_no_utterances() {
+        assert!(parse(r#"{"text": "  "}"#).is_empty());
+    }
+}
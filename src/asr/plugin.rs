@@ -0,0 +1,430 @@
+//! 外部进程插件协议
+//!
+//! 插件是一个长期运行的可执行文件，通过标准输入输出逐行交换JSON。
+//! 每个请求占一行：
+//!
+//! ```json
+//! {"id": 1, "path": "/tmp/segments/part_001.mp3", "offset": 30.0, "duration": 30.0, "hints": {}}
+//! ```
+//!
+//! 配置中 `hotwords` 为 true 时，请求还会带上 `"hotwords": ["词1", "词2"]`。
+//! 指定了识别语言时，请求带上 `"language": "zh"`；配置中的 `languages`
+//! 列出插件支持的语言，为空表示不限制。
+//!
+//! 插件对每个请求输出一行响应，`id` 与请求一致。成功时返回语句列表
+//! （时间相对片段起点，单位为秒）：
+//!
+//! ```json
+//! {"id": 1, "utterances": [{"start": 0.0, "end": 2.5, "text": "你好", "confidence": 0.92}]}
+//! ```
+//!
+//! 支持说话人分离的插件可以在语句中加上 `"speaker": "1"`。
+//!
+//! 失败时返回错误，`kind` 可选 `quota`、`auth`、`transient`、`unavailable`，其余按普通错误处理：
+//!
+//! ```json
+//! {"id": 1, "error": {"kind": "transient", "message": "model busy"}}
+//! ```
+//!
+//! 插件的标准错误输出会被转发到日志。凭据中该插件的各字段以
+//! `ASR_<插件名>_<字段>` 环境变量传给插件进程。
+
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use async_trait::async_trait;
+use log::{debug, warn};
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::Mutex;
+
+use crate::asr::base::{AsrProvider, AsrCapabilities, AsrError, AudioSegment, Transcript, Utterance};
+use crate::core::credentials::env_var_name;
+use crate::core::file_utils::find_executable;
+
+/// 插件配置
+#[derive(Debug, Clone, Deserialize)]
+pub struct PluginSpec {
+    /// 可执行文件路径或命令名
+    pub command: String,
+
+    /// 启动参数
+    #[serde(default)]
+    pub args: Vec<String>,
+
+    /// 单个片段的超时时间（秒）
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+
+    /// 随每个请求发送的固定提示
+    #[serde(default)]
+    pub hints: HashMap<String, Value>,
+
+    /// 插件是否需要联网
+    #[serde(default)]
+    pub requires_network: bool,
+
+    /// 插件是否使用请求中的热词
+    #[serde(default)]
+    pub hotwords: bool,
+
+    /// 插件支持的语言代码，为空表示不限制
+    #[serde(default)]
+    pub languages: Vec<String>,
+}
+
+fn default_timeout() -> u64 {
+    300
+}
+
+/// 发给插件的请求
+#[derive(Debug, Serialize)]
+struct PluginRequest<'a> {
+    id: u64,
+    path: String,
+    offset: f64,
+    duration: Option<f64>,
+    hints: &'a HashMap<String, Value>,
+    #[serde(skip_serializing_if = "<[String]>::is_empty")]
+    hotwords: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    language: Option<&'a str>,
+}
+
+/// 插件返回的响应
+#[derive(Debug, Deserialize)]
+struct PluginResponse {
+    id: u64,
+    #[serde(default)]
+    utterances: Option<Vec<Utterance>>,
+    #[serde(default)]
+    error: Option<PluginError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PluginError {
+    #[serde(default)]
+    kind: String,
+    #[serde(default)]
+    message: String,
+}
+
+impl PluginError {
+    /// 按 kind 映射为对应的错误类型
+    fn into_asr_error(self) -> AsrError {
+        match self.kind.as_str() {
+            "quota" => AsrError::QuotaExceeded(self.message),
+            "auth" => AsrError::Unauthorized(self.message),
+            "transient" => AsrError::Transient(self.message),
+            "unavailable" => AsrError::Unavailable(self.message),
+            _ => AsrError::TaskFailed(self.message),
+        }
+    }
+}
+
+/// 运行中的插件进程
+struct PluginProcess {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+}
+
+/// 通过外部进程识别的ASR服务
+pub struct PluginProvider {
+    name: String,
+    spec: PluginSpec,
+    hotwords: Vec<String>,
+    credentials: HashMap<String, String>,
+    next_id: AtomicU64,
+    process: Mutex<Option<PluginProcess>>,
+}
+
+impl PluginProvider {
+    /// 创建新的插件服务
+    pub fn new(name: &str, spec: PluginSpec) -> Self {
+        Self {
+            name: name.to_string(),
+            spec,
+            hotwords: Vec::new(),
+            credentials: HashMap::new(),
+            next_id: AtomicU64::new(1),
+            process: Mutex::new(None),
+        }
+    }
+
+    /// 设置随请求发送的热词，只在插件声明支持热词时发送
+    pub fn with_hotwords(mut self, hotwords: Vec<String>) -> Self {
+        if self.spec.hotwords {
+            self.hotwords = hotwords;
+        }
+        self
+    }
+
+    /// 设置插件的凭据，启动时以 ASR_<插件名>_<字段> 环境变量传给插件
+    pub fn with_credentials(mut self, credentials: HashMap<String, String>) -> Self {
+        self.credentials = credentials;
+        self
+    }
+
+    /// 启动插件进程
+    fn spawn(&self) -> Result<PluginProcess, AsrError> {
+        let program = find_executable(&self.spec.command)
+            .ok_or_else(|| AsrError::Unavailable(format!("找不到插件程序: {}", self.spec.command)))?;
+
+        let mut child = Command::new(program)
+            .args(&self.spec.args)
+            .envs(self.credentials.iter().map(|(field, value)| (env_var_name(&self.name, field), value)))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        let stdin = child.stdin.take()
+            .ok_or_else(|| AsrError::Unavailable("无法打开插件标准输入".to_string()))?;
+        let stdout = child.stdout.take()
+            .ok_or_else(|| AsrError::Unavailable("无法打开插件标准输出".to_string()))?;
+
+        if let Some(stderr) = child.stderr.take() {
+            let name = self.name.clone();
+            tokio::spawn(async move {
+                let mut lines = BufReader::new(stderr).lines();
+                while let Ok(Some(line)) = lines.next_line().await {
+                    debug!("[插件 {}] {}", name, line);
+                }
+            });
+        }
+
+        debug!("插件已启动: {}", self.name);
+
+        Ok(PluginProcess {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout).lines(),
+        })
+    }
+
+    /// 发送一个请求并等待对应的响应
+    async fn exchange(process: &mut PluginProcess, id: u64, line: &str) -> Result<PluginResponse, AsrError> {
+        process.stdin.write_all(line.as_bytes()).await?;
+        process.stdin.write_all(b"\n").await?;
+        process.stdin.flush().await?;
+
+        loop {
+            let response_line = process.stdout.next_line().await?
+                .ok_or_else(|| AsrError::Transient("插件进程已退出".to_string()))?;
+
+            if response_line.trim().is_empty() {
+                continue;
+            }
+
+            let response: PluginResponse = serde_json::from_str(&response_line)
+                .map_err(|e| AsrError::InvalidResponse(format!("{}: {}", e, response_line)))?;
+
+            if response.id == id {
+                return Ok(response);
+            }
+            warn!("忽略过期的插件响应: id={}", response.id);
+        }
+    }
+}
+
+#[async_trait]
+impl AsrProvider for PluginProvider {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn capabilities(&self) -> AsrCapabilities {
+        AsrCapabilities {
+            requires_network: self.spec.requires_network,
+            timestamps: true,
+            hotwords: self.spec.hotwords,
+            languages: self.spec.languages.clone(),
+            max_segment_duration: None,
+        }
+    }
+
+    fn cache_fingerprint(&self) -> String {
+        let mut hints: Vec<_> = self.spec.hints.iter().collect();
+        hints.sort_by(|a, b| a.0.cmp(b.0));
+        format!("{}|{}|{:?}|{}", self.spec.command, self.spec.args.join(" "), hints, self.hotwords.join(","))
+    }
+
+    async fn is_available(&self) -> bool {
+        find_executable(&self.spec.command).is_some()
+    }
+
+    async fn recognize(&self, segment: &AudioSegment) -> Result<Transcript, AsrError> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = serde_json::to_string(&PluginRequest {
+            id,
+            path: segment.path.to_string_lossy().to_string(),
+            offset: segment.offset,
+            duration: segment.duration,
+            hints: &self.spec.hints,
+            hotwords: &self.hotwords,
+            language: segment.language.as_deref(),
+        }).map_err(|e| AsrError::Request(e.to_string()))?;
+
+        // 插件一次只处理一个请求
+        let mut guard = self.process.lock().await;
+        if guard.is_none() {
+            *guard = Some(self.spawn()?);
+        }
+        let process = guard.as_mut().expect("插件进程已启动");
+
+        let timeout = Duration::from_secs(self.spec.timeout.max(1));
+        let result = match tokio::time::timeout(timeout, Self::exchange(process, id, &request)).await {
+            Ok(result) => result,
+            Err(_) => Err(AsrError::Timeout(format!("插件 {} 在 {} 秒内没有响应", self.name, timeout.as_secs()))),
+        };
+
+        let response = match result {
+            Ok(response) => response,
+            Err(e) => {
+                // 通信出错后重启插件，避免后续请求读到错位的响应
+                if let Some(mut process) = guard.take() {
+                    let _ = process.child.start_kill();
+                }
+                return Err(e);
+            }
+        };
+
+        if let Some(error) = response.error {
+            return Err(error.into_asr_error());
+        }
+
+        let utterances = response.utterances
+            .ok_or_else(|| AsrError::InvalidResponse("插件响应缺少utterances".to_string()))?;
+
+        Ok(Transcript::new(&self.name, utterances))
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    /// 用 sh 运行脚本作为插件；脚本从请求行里取出 id
+    fn script_spec(dir: &Path, body: &str, timeout: u64) -> PluginSpec {
+        let script = dir.join("plugin.sh");
+        std::fs::write(&script, format!(
+            "while read line; do\nid=$(echo \"$line\" | sed 's/^{{\"id\":\\([0-9]*\\).*/\\1/')\n{}\ndone\n", body
+        )).unwrap();
+
+        PluginSpec {
+            command: "sh".to_string(),
+            args: vec![script.to_string_lossy().into_owned()],
+            timeout,
+            hints: HashMap::new(),
+            requires_network: false,
+            hotwords: false,
+            languages: Vec::new(),
+        }
+    }
+
+    fn script_plugin(dir: &Path, body: &str, timeout: u64) -> PluginProvider {
+        PluginProvider::new("test-plugin", script_spec(dir, body, timeout))
+    }
+
+    fn segment(dir: &Path) -> AudioSegment {
+        AudioSegment::new(&dir.join("part_001.mp3"), 30.0, Some(30.0))
+    }
+
+    #[tokio::test]
+    async fn skips_stale_responses_until_the_id_matches() {
+        let dir = tempfile::tempdir().unwrap();
+        let provider = script_plugin(dir.path(), r#"
+echo '{"id":0,"utterances":[{"start":0.0,"end":1.0,"text":"过期"}]}'
+echo ''
+echo "{\"id\":$id,\"utterances\":[{\"start\":0.5,\"end\":2.0,\"text\":\"你好\",\"confidence\":0.9}]}""#, 10);
+
+        for _ in 0..2 {
+            let transcript = provider.recognize(&segment(dir.path())).await.unwrap();
+            assert_eq!(transcript.provider, "test-plugin");
+            assert_eq!(transcript.utterances.len(), 1);
+            assert_eq!(transcript.utterances[0].text, "你好");
+            assert_eq!(transcript.utterances[0].confidence, Some(0.9));
+        }
+    }
+
+    #[tokio::test]
+    async fn hotwords_are_sent_only_when_the_plugin_supports_them() {
+        let dir = tempfile::tempdir().unwrap();
+        // 把请求里是否带热词作为识别文本返回
+        let body = r#"
+case "$line" in *'"hotwords":["剪映","必剪"]'*) t=yes;; *) t=no;; esac
+echo "{\"id\":$id,\"utterances\":[{\"start\":0.0,\"end\":1.0,\"text\":\"$t\"}]}""#;
+        let hotwords = vec!["剪映".to_string(), "必剪".to_string()];
+
+        let mut spec = script_spec(dir.path(), body, 10);
+        spec.hotwords = true;
+        let provider = PluginProvider::new("test-plugin", spec).with_hotwords(hotwords.clone());
+        assert_eq!(provider.recognize(&segment(dir.path())).await.unwrap().text(), "yes");
+
+        let provider = script_plugin(dir.path(), body, 10).with_hotwords(hotwords);
+        assert_eq!(provider.recognize(&segment(dir.path())).await.unwrap().text(), "no");
+    }
+
+    #[test]
+    fn maps_error_kinds() {
+        let map = |kind: &str| PluginError { kind: kind.to_string(), message: "x".to_string() }.into_asr_error();
+
+        assert!(matches!(map("quota"), AsrError::QuotaExceeded(_)));
+        assert!(matches!(map("auth"), AsrError::Unauthorized(_)));
+        assert!(matches!(map("transient"), AsrError::Transient(_)));
+        assert!(matches!(map("unavailable"), AsrError::Unavailable(_)));
+        assert!(matches!(map(""), AsrError::TaskFailed(_)));
+        assert!(matches!(map("other"), AsrError::TaskFailed(_)));
+    }
+
+    #[tokio::test]
+    async fn error_responses_become_asr_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let provider = script_plugin(dir.path(),
+            r#"echo "{\"id\":$id,\"error\":{\"kind\":\"quota\",\"message\":\"limit\"}}""#, 10);
+
+        match provider.recognize(&segment(dir.path())).await {
+            Err(AsrError::QuotaExceeded(message)) => assert_eq!(message, "limit"),
+            other => panic!("应为 QuotaExceeded: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn missing_utterances_is_an_invalid_response() {
+        let dir = tempfile::tempdir().unwrap();
+        let provider = script_plugin(dir.path(), r#"echo "{\"id\":$id}""#, 10);
+
+        assert!(matches!(provider.recognize(&segment(dir.path())).await, Err(AsrError::InvalidResponse(_))));
+    }
+
+    #[tokio::test]
+    async fn timeout_kills_and_respawns_the_plugin() {
+        let dir = tempfile::tempdir().unwrap();
+        // 第一个进程收到请求后不再响应，重启后的进程正常返回
+        let marker = dir.path().join("started");
+        let provider = script_plugin(dir.path(), &format!(r#"
+if [ ! -e '{0}' ]; then touch '{0}'; exec sleep 30; fi
+echo "{{\"id\":$id,\"utterances\":[]}}""#, marker.display()), 1);
+
+        assert!(matches!(provider.recognize(&segment(dir.path())).await, Err(AsrError::Timeout(_))));
+        assert!(provider.process.lock().await.is_none());
+
+        let transcript = provider.recognize(&segment(dir.path())).await.unwrap();
+        assert!(transcript.utterances.is_empty());
+    }
+
+    #[tokio::test]
+    async fn exited_plugin_is_transient_and_restarted() {
+        let dir = tempfile::tempdir().unwrap();
+        let provider = script_plugin(dir.path(), "exit 0", 10);
+
+        assert!(matches!(provider.recognize(&segment(dir.path())).await, Err(AsrError::Transient(_))));
+        assert!(provider.process.lock().await.is_none());
+    }
+}
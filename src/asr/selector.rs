@@ -1,3 +1,411 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use log::{info, warn, debug};
+
+/// 成功率和延迟的平滑系数
+const EWMA_ALPHA: f64 = 0.2;
+
+/// 成功率为0时相对优先级增加的分数
+const FAILURE_PENALTY: f64 = 2.0;
+
+/// 延迟达到该值（秒）时增加1分，超过不再增加
+const LATENCY_SCALE_SECS: f64 = 60.0;
+
+/// 默认的探测超时，与HTTP请求的默认超时一致
+const DEFAULT_PROBE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// 熔断状态
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CircuitState {
+    /// 正常调用
+    Closed,
+    /// 熔断中，冷却结束前不再调用
+    Open { until: Instant },
+    /// 冷却结束，允许一次探测请求，probe_started 为正在进行的探测的开始时间
+    HalfOpen { probe_started: Option<Instant> },
+}
+
+/// 单个服务的健康状况
+#[derive(Debug, Clone)]
+struct ProviderHealth {
+    priority: u32,
+    success_rate: f64,
+    avg_latency: Option<Duration>,
+    consecutive_failures: u32,
+    circuit: CircuitState,
+    skip_reason: Option<String>,
+    /// 导致熔断的最近一次错误
+    last_error: Option<String>,
+}
+
+impl ProviderHealth {
+    fn new(priority: u32) -> Self {
+        Self {
+            priority,
+            success_rate: 1.0,
+            avg_latency: None,
+            consecutive_failures: 0,
+            circuit: CircuitState::Closed,
+            skip_reason: None,
+            last_error: None,
+        }
+    }
+
+    /// 综合评分，越小越优先
+    fn score(&self) -> f64 {
+        let latency = self.avg_latency
+            .map(|l| (l.as_secs_f64() / LATENCY_SCALE_SECS).min(1.0))
+            .unwrap_or(0.0);
+
+        self.priority as f64 + (1.0 - self.success_rate) * FAILURE_PENALTY + latency
+    }
+
+    fn update_latency(&mut self, latency: Duration) {
+        self.avg_latency = Some(match self.avg_latency {
+            Some(avg) => avg.mul_f64(1.0 - EWMA_ALPHA) + latency.mul_f64(EWMA_ALPHA),
+            None => latency,
+        });
+    }
+}
+
+/// 服务健康快照，用于统计输出
+#[derive(Debug, Clone)]
+pub struct ProviderHealthSnapshot {
+    pub available: bool,
+    pub success_rate: f64,
+    pub avg_latency: Option<Duration>,
+    pub consecutive_failures: u32,
+    pub skip_reason: Option<String>,
+}
+
+/// ASR服务选择器，按优先级和近期表现排序，并对连续失败的服务熔断
 pub struct AsrSelector {
-    // ASR provider selector
-}
\ No newline at end of file
+    health: Mutex<HashMap<String, ProviderHealth>>,
+    failure_threshold: u32,
+    cooldown: Duration,
+    probe_timeout: Duration,
+}
+
+impl AsrSelector {
+    /// 创建新的选择器
+    pub fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            health: Mutex::new(HashMap::new()),
+            failure_threshold: failure_threshold.max(1),
+            cooldown,
+            probe_timeout: DEFAULT_PROBE_TIMEOUT,
+        }
+    }
+
+    /// 设置探测超时：探测请求超过该时长仍未报告结果（被超时、中断或取消）时视为放弃，
+    /// 允许发送新的探测，避免服务一直停在半开状态
+    pub fn with_probe_timeout(mut self, probe_timeout: Duration) -> Self {
+        self.probe_timeout = probe_timeout;
+        self
+    }
+
+    /// 半开状态下是否有仍在进行的探测；超时的探测视为已放弃并清除
+    fn probe_in_flight(&self, name: &str, entry: &mut ProviderHealth, now: Instant) -> bool {
+        match entry.circuit {
+            CircuitState::HalfOpen { probe_started: Some(started) } => {
+                if now.duration_since(started) < self.probe_timeout {
+                    entry.skip_reason = Some("正在探测恢复情况".to_string());
+                    return true;
+                }
+                warn!("ASR服务 {} 的探测请求 {} 秒未返回，重新探测", name, self.probe_timeout.as_secs());
+                entry.circuit = CircuitState::HalfOpen { probe_started: None };
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// 注册服务，priority 越小越优先
+    pub fn register(&self, name: &str, priority: u32) {
+        let mut health = self.health.lock().unwrap();
+        health.entry(name.to_string())
+            .and_modify(|h| h.priority = priority)
+            .or_insert_with(|| ProviderHealth::new(priority));
+    }
+
+    /// 对候选服务排序，熔断中的服务被跳过并记录原因
+    pub fn rank(&self, candidates: &[String]) -> Vec<String> {
+        let now = Instant::now();
+        let mut health = self.health.lock().unwrap();
+        let mut ranked = Vec::new();
+
+        for name in candidates {
+            let entry = health.entry(name.clone())
+                .or_insert_with(|| ProviderHealth::new(u32::MAX / 2));
+
+            match entry.circuit {
+                CircuitState::Open { until } if now < until => {
+                    let remaining = until.duration_since(now).as_secs();
+                    entry.skip_reason = Some(format!(
+                        "连续失败 {} 次，熔断中（{} 秒后重试）: {}",
+                        entry.consecutive_failures, remaining, entry.last_error.as_deref().unwrap_or("")
+                    ));
+                    continue;
+                }
+                CircuitState::Open { .. } => {
+                    info!("ASR服务 {} 冷却结束，发送探测请求", name);
+                    entry.circuit = CircuitState::HalfOpen { probe_started: None };
+                }
+                _ => {}
+            }
+            if self.probe_in_flight(name, entry, now) {
+                continue;
+            }
+
+            ranked.push((entry.score(), name.clone()));
+        }
+
+        ranked.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+        ranked.into_iter().map(|(_, name)| name).collect()
+    }
+
+    /// 标记即将调用服务，半开状态下只放行一个探测请求
+    pub fn acquire(&self, name: &str) -> bool {
+        let now = Instant::now();
+        let mut health = self.health.lock().unwrap();
+        let entry = match health.get_mut(name) {
+            Some(entry) => entry,
+            None => return true,
+        };
+
+        if self.probe_in_flight(name, entry, now) {
+            return false;
+        }
+
+        match entry.circuit {
+            CircuitState::Closed => true,
+            CircuitState::HalfOpen { .. } => {
+                entry.circuit = CircuitState::HalfOpen { probe_started: Some(now) };
+                true
+            }
+            CircuitState::Open { .. } => false,
+        }
+    }
+
+    /// 记录服务因自身报告不可用而被跳过
+    pub fn record_skip(&self, name: &str, reason: &str) {
+        let mut health = self.health.lock().unwrap();
+        if let Some(entry) = health.get_mut(name) {
+            if let CircuitState::HalfOpen { .. } = entry.circuit {
+                entry.circuit = CircuitState::Open { until: Instant::now() + self.cooldown };
+            }
+            entry.skip_reason = Some(reason.to_string());
+        }
+    }
+
+    /// 记录一次成功调用
+    pub fn record_success(&self, name: &str, latency: Duration) {
+        let mut health = self.health.lock().unwrap();
+        let entry = health.entry(name.to_string())
+            .or_insert_with(|| ProviderHealth::new(u32::MAX / 2));
+
+        if entry.circuit != CircuitState::Closed {
+            info!("ASR服务 {} 已恢复", name);
+        }
+
+        entry.success_rate = entry.success_rate * (1.0 - EWMA_ALPHA) + EWMA_ALPHA;
+        entry.update_latency(latency);
+        entry.consecutive_failures = 0;
+        entry.circuit = CircuitState::Closed;
+        entry.skip_reason = None;
+    }
+
+    /// 记录一次失败调用，连续失败达到阈值时熔断
+    pub fn record_failure(&self, name: &str, latency: Duration, error: &str) {
+        let mut health = self.health.lock().unwrap();
+        let entry = health.entry(name.to_string())
+            .or_insert_with(|| ProviderHealth::new(u32::MAX / 2));
+
+        entry.success_rate *= 1.0 - EWMA_ALPHA;
+        entry.update_latency(latency);
+        entry.consecutive_failures += 1;
+
+        let probe_failed = matches!(entry.circuit, CircuitState::HalfOpen { .. });
+        if probe_failed || entry.consecutive_failures >= self.failure_threshold {
+            if entry.circuit == CircuitState::Closed {
+                warn!("ASR服务 {} 连续失败 {} 次，熔断 {} 秒: {}",
+                    name, entry.consecutive_failures, self.cooldown.as_secs(), error);
+            } else {
+                debug!("ASR服务 {} 探测失败，继续熔断: {}", name, error);
+            }
+            entry.circuit = CircuitState::Open { until: Instant::now() + self.cooldown };
+            entry.skip_reason = Some(format!("连续失败 {} 次: {}", entry.consecutive_failures, error));
+            entry.last_error = Some(error.to_string());
+        }
+    }
+
+    /// 记录一次重试也不会成功的失败（例如凭据失效），立即熔断
+    pub fn trip(&self, name: &str, latency: Duration, error: &str) {
+        let mut health = self.health.lock().unwrap();
+        let entry = health.entry(name.to_string())
+            .or_insert_with(|| ProviderHealth::new(u32::MAX / 2));
+
+        entry.success_rate *= 1.0 - EWMA_ALPHA;
+        entry.update_latency(latency);
+        entry.consecutive_failures += 1;
+
+        if entry.circuit == CircuitState::Closed {
+            warn!("ASR服务 {} 熔断 {} 秒: {}", name, self.cooldown.as_secs(), error);
+        }
+        entry.circuit = CircuitState::Open { until: Instant::now() + self.cooldown };
+        entry.skip_reason = Some(error.to_string());
+        entry.last_error = Some(error.to_string());
+    }
+
+    /// 结束一次不计入健康状况的调用，例如本地IO错误或请求参数错误；
+    /// 半开状态下放弃本次探测，允许重新探测
+    pub fn release(&self, name: &str) {
+        let mut health = self.health.lock().unwrap();
+        if let Some(entry) = health.get_mut(name) {
+            if let CircuitState::HalfOpen { .. } = entry.circuit {
+                entry.circuit = CircuitState::HalfOpen { probe_started: None };
+            }
+        }
+    }
+
+    /// 服务当前是否可被选择
+    pub fn is_available(&self, name: &str) -> bool {
+        let health = self.health.lock().unwrap();
+        match health.get(name).map(|h| h.circuit) {
+            Some(CircuitState::Open { until }) => Instant::now() >= until,
+            _ => true,
+        }
+    }
+
+    /// 获取服务的健康快照
+    pub fn snapshot(&self, name: &str) -> Option<ProviderHealthSnapshot> {
+        let available = self.is_available(name);
+        let health = self.health.lock().unwrap();
+
+        health.get(name).map(|h| ProviderHealthSnapshot {
+            available,
+            success_rate: h.success_rate,
+            avg_latency: h.avg_latency,
+            consecutive_failures: h.consecutive_failures,
+            skip_reason: h.skip_reason.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tripped(probe_timeout: Duration) -> AsrSelector {
+        let selector = AsrSelector::new(1, Duration::ZERO).with_probe_timeout(probe_timeout);
+        selector.register("a", 0);
+        selector.record_failure("a", Duration::ZERO, "boom");
+        selector
+    }
+
+    #[test]
+    fn half_open_allows_a_single_probe() {
+        let selector = tripped(Duration::from_secs(60));
+        let names = vec!["a".to_string()];
+
+        assert_eq!(selector.rank(&names), names);
+        assert!(selector.acquire("a"));
+        assert!(!selector.acquire("a"));
+        assert!(selector.rank(&names).is_empty());
+    }
+
+    #[test]
+    fn abandoned_probe_is_retried_after_timeout() {
+        let selector = tripped(Duration::ZERO);
+        let names = vec!["a".to_string()];
+
+        assert_eq!(selector.rank(&names), names);
+        assert!(selector.acquire("a"));
+        // 探测的 future 被丢弃，没有报告结果
+        assert_eq!(selector.rank(&names), names);
+        assert!(selector.acquire("a"));
+    }
+
+    #[test]
+    fn successful_probe_closes_the_circuit() {
+        let selector = tripped(Duration::from_secs(60));
+        let names = vec!["a".to_string()];
+
+        selector.rank(&names);
+        assert!(selector.acquire("a"));
+        selector.record_success("a", Duration::ZERO);
+        assert!(selector.acquire("a"));
+        assert!(selector.acquire("a"));
+    }
+
+    #[test]
+    fn released_probe_can_be_retried() {
+        let selector = tripped(Duration::from_secs(60));
+        let names = vec!["a".to_string()];
+
+        selector.rank(&names);
+        assert!(selector.acquire("a"));
+        selector.release("a");
+        assert_eq!(selector.rank(&names), names);
+        assert!(selector.acquire("a"));
+        assert_eq!(selector.snapshot("a").unwrap().consecutive_failures, 1);
+    }
+
+    fn names(list: &[&str]) -> Vec<String> {
+        list.iter().map(|n| n.to_string()).collect()
+    }
+
+    #[test]
+    fn priority_wins_when_health_is_equal() {
+        let selector = AsrSelector::new(10, Duration::from_secs(60));
+        selector.register("first", 0);
+        selector.register("second", 1);
+        selector.register("third", 2);
+        for name in ["first", "second", "third"] {
+            selector.record_success(name, Duration::from_secs(2));
+        }
+
+        assert_eq!(selector.rank(&names(&["third", "first", "second"])), names(&["first", "second", "third"]));
+    }
+
+    #[test]
+    fn failing_provider_drops_below_a_healthy_one() {
+        let selector = AsrSelector::new(10, Duration::from_secs(60));
+        selector.register("primary", 0);
+        selector.register("backup", 1);
+        selector.record_success("backup", Duration::from_secs(1));
+
+        // 失败次数少时仍按优先级
+        selector.record_failure("primary", Duration::from_secs(1), "502");
+        assert_eq!(selector.rank(&names(&["primary", "backup"])), names(&["primary", "backup"]));
+
+        for _ in 0..3 {
+            selector.record_failure("primary", Duration::from_secs(1), "502");
+        }
+        assert!(selector.is_available("primary"));
+        assert_eq!(selector.rank(&names(&["primary", "backup"])), names(&["backup", "primary"]));
+
+        // 恢复成功后逐渐回到前面
+        for _ in 0..5 {
+            selector.record_success("primary", Duration::from_secs(1));
+        }
+        assert_eq!(selector.rank(&names(&["primary", "backup"])), names(&["primary", "backup"]));
+    }
+
+    #[test]
+    fn slower_provider_drops_below_a_faster_one() {
+        let selector = AsrSelector::new(10, Duration::from_secs(60));
+        selector.register("slow", 0);
+        selector.register("fast", 0);
+        selector.record_success("slow", Duration::from_secs(45));
+        selector.record_success("fast", Duration::from_secs(2));
+        assert_eq!(selector.rank(&names(&["slow", "fast"])), names(&["fast", "slow"]));
+
+        // 较慢又偶尔失败的高优先级服务排到健康的低优先级服务之后
+        selector.register("backup", 1);
+        selector.record_success("backup", Duration::from_secs(2));
+        selector.record_failure("slow", Duration::from_secs(60), "timeout");
+        assert_eq!(selector.rank(&names(&["slow", "backup"])), names(&["backup", "slow"]));
+    }
+}
@@ -0,0 +1,9 @@
+use std::path::Path;
+use anyhow::Result;
+
+use crate::core::audio_extractor::probe_audio;
+
+/// 获取音频时长（秒），通过 ffprobe 读取
+pub fn get_audio_duration(audio_path: &Path) -> Result<f64> {
+    Ok(probe_audio(audio_path)?.duration)
+}
@@ -1,9 +1,14 @@
-use clap::Parser;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser};
 use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[clap(author, version, about = "音频处理和转写工具")]
 pub struct Cli {
+    /// JSON配置文件，键与配置项相同（如 asr_plugins、asr_segment_limits、http），
+    /// 命令行上明确给出的参数优先
+    #[clap(long)]
+    pub config: Option<PathBuf>,
+    
     /// 媒体文件夹路径
     #[clap(long, default_value = "D:/download/")]
     pub media_folder: PathBuf,
@@ -20,17 +25,61 @@ pub struct Cli {
     #[clap(long, default_value = "4")]
     pub max_workers: u32,
     
-    /// 是否优先使用剪映ASR
+    /// ASR服务回退顺序，逗号分隔，可选 bcut、jianying、kuaishou、openai、local 或插件名
+    #[clap(long, value_delimiter = ',', default_value = "bcut,jianying")]
+    pub asr_chain: Vec<String>,
+    
+    /// 片段识别策略：fallback 逐个回退，consensus 多服务投票
+    #[clap(long, default_value = "fallback")]
+    pub asr_strategy: String,
+    
+    /// 输出格式，逗号分隔，可选 txt、srt、vtt、json，txt 总会输出
+    #[clap(long, value_delimiter = ',')]
+    pub output_formats: Option<Vec<String>>,
+    
+    /// 导出置信度低于该值的内容供人工复核，0 表示不导出
+    #[clap(long)]
+    pub review_threshold: Option<f64>,
+    
+    /// 识别语言代码，如 zh、en；auto 表示按文件自动检测
+    #[clap(long)]
+    pub language: Option<String>,
+    
+    /// 凭据文件（JSON，权限须为 600），格式同配置中的 credentials 段
+    #[clap(long)]
+    pub credentials_file: Option<PathBuf>,
+    
+    /// 热词文件，每行一个词（默认读取媒体目录下的 hotwords.txt）
+    #[clap(long)]
+    pub hotwords_file: Option<PathBuf>,
+    
+    /// 演练模式：只使用不联网的模拟ASR服务，不消耗配额
+    #[clap(long)]
+    pub dry_run_asr: bool,
+    
+    /// 不使用识别结果缓存
+    #[clap(long)]
+    pub no_cache: bool,
+    
+    /// HTTP代理地址，none 表示不使用代理；不指定时使用配置或 HTTP_PROXY 等环境变量
+    #[clap(long)]
+    pub proxy: Option<String>,
+    
+    /// HTTP夹具模式：record 录制请求，replay 离线回放（也可用环境变量 ASR_HTTP_FIXTURES）
+    #[clap(long)]
+    pub http_fixtures: Option<String>,
+    
+    /// HTTP夹具目录（也可用环境变量 ASR_FIXTURES_DIR）
     #[clap(long)]
-    pub use_jianying_first: bool,
+    pub fixtures_dir: Option<PathBuf>,
     
-    /// 是否使用快手ASR
+    /// 分段方式：fixed 固定时长，silence 在静音处切分，vad 按语音检测切分并跳过长时间空白
     #[clap(long)]
-    pub use_kuaishou: bool,
+    pub segment_mode: Option<String>,
     
-    /// 是否使用必剪ASR
+    /// 相邻片段重叠的秒数，重叠部分的重复文字会被去掉
     #[clap(long)]
-    pub use_bcut: bool,
+    pub segment_overlap: Option<f64>,
     
     /// 是否格式化文本
     #[clap(long)]
@@ -61,6 +110,9 @@ pub struct Cli {
     pub log_file: Option<PathBuf>,
 }
 
-pub fn parse_args() -> Cli {
-    Cli::parse()
+/// 解析命令行参数，同时返回原始匹配结果，用于判断参数是否在命令行上明确给出
+pub fn parse_args() -> (Cli, ArgMatches) {
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    (cli, matches)
 }
\ No newline at end of file
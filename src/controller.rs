@@ -4,19 +4,22 @@ use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use log::{info, warn, error};
 use std::fs;
-use std::thread;
 use anyhow::Result;
 use tokio::signal;
-use walkdir::WalkDir;
 
-use crate::core::audio_extractor::AudioExtractor;
-use crate::core::file_utils::format_time_duration;
+use crate::core::audio_extractor::{AudioExtractor, SegmentOptions, native_decodable};
+use crate::core::file_utils::{check_ffmpeg_available, format_time_duration};
 use crate::core::error::ErrorHandler;
 use crate::core::config_manager::ConfigManager;
+use crate::core::hotwords::{load_hotwords, HotwordCorrector};
 use crate::processing::transcription_processor::TranscriptionProcessor;
 use crate::processing::file_processor::FileProcessor;
+use crate::processing::text_processor::OutputFormat;
+use crate::processing::review::{ReviewExporter, ReviewOptions};
 use crate::processing::progress_manager::ProgressManager;
-use crate::asr::manager::AsrManager;
+use crate::asr::cache::{AsrCache, CacheOptions};
+use crate::asr::language::LanguageConfig;
+use crate::asr::manager::{AsrManager, AsrStrategy};
 
 /// 处理器控制器，协调各个组件工作
 pub struct ProcessorController {
@@ -25,13 +28,17 @@ pub struct ProcessorController {
     
     // 临时目录路径
     temp_dir: PathBuf,
+    #[allow(dead_code)]
     temp_segments_dir: PathBuf,
     
     // 组件
     error_handler: Arc<ErrorHandler>,
     progress_manager: Arc<ProgressManager>,
     asr_manager: Arc<AsrManager>,
+    asr_cache: Option<Arc<AsrCache>>,
+    #[allow(dead_code)]
     audio_extractor: Arc<AudioExtractor>,
+    #[allow(dead_code)]
     transcription_processor: Arc<TranscriptionProcessor>,
     file_processor: Arc<FileProcessor>,
     
@@ -72,10 +79,7 @@ impl ProcessorController {
         // 创建临时目录
         let temp_dir = match config.get("temp_dir") {
             Some(value) => PathBuf::from(value.as_str().unwrap_or("")),
-            None => {
-                let dir = tempfile::tempdir()?.into_path();
-                dir
-            }
+            None => tempfile::tempdir()?.keep(),
         };
         
         let temp_segments_dir = temp_dir.join("segments");
@@ -96,11 +100,7 @@ impl ProcessorController {
         ));
         
         // 创建ASR管理器
-        let asr_manager = Arc::new(AsrManager::new(
-            config.get("use_jianying_first").and_then(|v| v.as_bool()).unwrap_or(false),
-            config.get("use_kuaishou").and_then(|v| v.as_bool()).unwrap_or(false),
-            config.get("use_bcut").and_then(|v| v.as_bool()).unwrap_or(false),
-        ));
+        let asr_manager = Arc::new(AsrManager::new(&config)?);
         
         // 创建回调闭包
         let progress_manager_clone = Arc::clone(&progress_manager);
@@ -130,8 +130,8 @@ impl ProcessorController {
             }
             
             if let Some(bar) = progress_manager.get_progress_bar(&progress_name) {
-                if bar.length() != total {
-                    bar.reset(total);
+                if bar.length() != Some(total as u64) {
+                    bar.set_length(total as u64);
                 }
             }
             
@@ -147,23 +147,99 @@ impl ProcessorController {
         };
         
         // 创建音频提取器
+        if !check_ffmpeg_available() {
+            if cfg!(feature = "native-decode") {
+                warn!("未找到 ffmpeg/ffprobe，只能处理可直接解码的音频，视频文件将无法处理");
+            } else {
+                warn!("未找到 ffmpeg/ffprobe，音频处理将失败；可安装 ffmpeg 或启用 native-decode 特性编译");
+            }
+        }
+        // 顶层的 segment_mode、segment_overlap 优先于 segmentation 段中的设置
+        let mut segmentation = config.get("segmentation").cloned().unwrap_or_else(|| serde_json::json!({}));
+        for (key, field) in [("segment_mode", "mode"), ("segment_overlap", "overlap")] {
+            let value = config.get(key).filter(|v| !v.is_null() && v.as_str() != Some(""));
+            if let (Some(value), Some(section)) = (value, segmentation.as_object_mut()) {
+                section.insert(field.to_string(), value.clone());
+            }
+        }
+        let segment_options = serde_json::from_value::<SegmentOptions>(segmentation).unwrap_or_else(|e| {
+            warn!("分段配置无效，使用固定分段: {}", e);
+            SegmentOptions::default()
+        });
         let audio_extractor = Arc::new(AudioExtractor::new(
             &temp_segments_dir,
             Some(Arc::new(progress_callback.clone())),
-        ));
+        ).with_segment_options(segment_options));
+        
+        // 片段识别策略
+        let strategy_name = config.get("asr_strategy").and_then(|v| v.as_str()).unwrap_or("fallback");
+        let strategy = AsrStrategy::parse(strategy_name).unwrap_or_else(|| {
+            warn!("未知的识别策略 {}，使用 fallback", strategy_name);
+            AsrStrategy::Fallback
+        });
+        info!("片段识别策略: {:?}", strategy);
+        
+        // 识别结果缓存
+        // 演练模式下不读写缓存，以便每次都走完整的识别和重试流程
+        let dry_run = config.get("dry_run_asr").and_then(|v| v.as_bool()).unwrap_or(false);
+        let asr_cache = if !dry_run && config.get("asr_cache").and_then(|v| v.as_bool()).unwrap_or(true) {
+            let cache_dir = match config.get("asr_cache_dir").and_then(|v| v.as_str()).filter(|s| !s.is_empty()) {
+                Some(dir) => PathBuf::from(dir),
+                None => PathBuf::from(config.get("output_folder").and_then(|v| v.as_str()).unwrap_or("")).join(".asr_cache"),
+            };
+            let max_age_days = config.get("asr_cache_max_age_days").and_then(|v| v.as_f64()).unwrap_or(30.0);
+            let options = CacheOptions {
+                dir: cache_dir,
+                max_bytes: config.get("asr_cache_max_mb").and_then(|v| v.as_u64()).unwrap_or(500) * 1024 * 1024,
+                max_age: Some(Duration::from_secs_f64(max_age_days * 86400.0)).filter(|_| max_age_days > 0.0),
+            };
+            match AsrCache::open(options) {
+                Ok(cache) => Some(Arc::new(cache)),
+                Err(e) => {
+                    warn!("无法打开ASR缓存，本次不使用缓存: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
         
         // 创建转写处理器
-        let transcription_processor = Arc::new(TranscriptionProcessor::new(
+        let mut transcription_processor = TranscriptionProcessor::new(
             Arc::clone(&asr_manager),
             &temp_segments_dir,
             config.get("max_workers").and_then(|v| v.as_u64()).unwrap_or(4) as usize,
             config.get("max_retries").and_then(|v| v.as_u64()).unwrap_or(3) as u32,
             Some(Arc::new(progress_callback.clone())),
             Arc::clone(&interrupt_flag),
-        ));
+        ).with_strategy(strategy);
+        if let Some(cache) = &asr_cache {
+            transcription_processor = transcription_processor.with_cache(Arc::clone(cache));
+        }
+        let transcription_processor = Arc::new(transcription_processor);
+        
+        // 文本之外的输出格式，txt 总会输出
+        let mut output_formats = Vec::new();
+        for name in config.get("output_formats").and_then(|v| v.as_array()).into_iter().flatten().filter_map(|v| v.as_str()) {
+            match OutputFormat::parse(name) {
+                Some(format) if !output_formats.contains(&format) => output_formats.push(format),
+                Some(_) => {}
+                None if name.eq_ignore_ascii_case("txt") => {}
+                None => warn!("未知的输出格式: {}，已忽略", name),
+            }
+        }
+        
+        // 不支持热词的服务在生成文本时做热词纠正
+        let hotwords = load_hotwords(&config)?;
+        let hotword_corrector = if hotwords.is_empty() {
+            None
+        } else {
+            Some(HotwordCorrector::new(&hotwords, asr_manager.native_hotword_providers())
+                .with_max_distance_ratio(config.get("hotword_max_distance").and_then(|v| v.as_f64()).unwrap_or(0.34)))
+        };
         
         // 创建文件处理器
-        let file_processor = Arc::new(FileProcessor::new(
+        let file_processor = FileProcessor::new(
             config.get("media_folder").and_then(|v| v.as_str()).unwrap_or("").into(),
             config.get("output_folder").and_then(|v| v.as_str()).unwrap_or("").into(),
             &temp_segments_dir,
@@ -176,7 +252,29 @@ impl ProcessorController {
             config.get("include_timestamps").and_then(|v| v.as_bool()).unwrap_or(true),
             config.get("max_part_time").and_then(|v| v.as_u64()).unwrap_or(30) as u32,
             config.get("max_retries").and_then(|v| v.as_u64()).unwrap_or(3) as u32,
-        ));
+            hotword_corrector,
+            LanguageConfig::from_config(&config),
+            output_formats,
+        )?;
+        
+        // 低置信度复核导出，阈值为 0 时不导出
+        let review_threshold = config.get("review_threshold").and_then(|v| v.as_f64()).unwrap_or(0.0);
+        let file_processor = if review_threshold > 0.0 {
+            let review_dir = match config.get("review_dir").and_then(|v| v.as_str()).filter(|s| !s.is_empty()) {
+                Some(dir) => PathBuf::from(dir),
+                None => PathBuf::from(config.get("output_folder").and_then(|v| v.as_str()).unwrap_or("")).join("review"),
+            };
+            info!("低置信度复核: 阈值 {}，输出到 {}", review_threshold, review_dir.display());
+            file_processor.with_review(ReviewExporter::new(ReviewOptions {
+                threshold: review_threshold as f32,
+                dir: review_dir,
+                padding: config.get("review_padding").and_then(|v| v.as_f64()).unwrap_or(0.5),
+                snippets: config.get("review_snippets").and_then(|v| v.as_bool()).unwrap_or(true),
+            }, Arc::clone(&audio_extractor)))
+        } else {
+            file_processor
+        };
+        let file_processor = Arc::new(file_processor);
         
         let controller = Self {
             config_manager,
@@ -185,6 +283,7 @@ impl ProcessorController {
             error_handler,
             progress_manager,
             asr_manager,
+            asr_cache,
             audio_extractor,
             transcription_processor,
             file_processor,
@@ -247,9 +346,18 @@ impl ProcessorController {
             
             info!("\n总耗时: {}", format_time_duration(&duration));
             
+            if let Some(cache) = &self.asr_cache {
+                let (hits, misses) = cache.stats();
+                info!("ASR缓存: 命中 {}, 未命中 {}", hits, misses);
+            }
+            
             // 显示ASR服务统计
             let asr_stats = self.asr_manager.get_service_stats();
+            let total_wait: f64 = asr_stats.values()
+                .filter_map(|stat| stat.get("wait_time").and_then(|v| v.as_f64()))
+                .sum();
             info!("\nASR服务使用统计:");
+            info!("  限流等待合计: {:.1}秒", total_wait);
             for (name, stat) in asr_stats {
                 let available_status = if stat.get("available").and_then(|v| v.as_bool()).unwrap_or(false) {
                     "可用"
@@ -263,6 +371,16 @@ impl ProcessorController {
                     stat.get("success_rate").and_then(|v| v.as_f64()).unwrap_or(0.0),
                     available_status
                 );
+                
+                let wait_time = stat.get("wait_time").and_then(|v| v.as_f64()).unwrap_or(0.0);
+                let throttled = stat.get("throttled").and_then(|v| v.as_u64()).unwrap_or(0);
+                if wait_time > 0.05 || throttled > 0 {
+                    info!("    限流等待: {:.1}秒, 被限流 {} 次", wait_time, throttled);
+                }
+                
+                if let Some(reason) = stat.get("skip_reason").and_then(|v| v.as_str()) {
+                    info!("    跳过原因: {}", reason);
+                }
             }
             
             // 显示错误统计
@@ -285,7 +403,7 @@ impl ProcessorController {
             },
             Err(e) => {
                 error!("更新配置失败: {}", e);
-                Err(e.into())
+                Err(e)
             }
         }
     }
@@ -299,7 +417,7 @@ impl ProcessorController {
             },
             Err(e) => {
                 error!("保存配置失败: {}", e);
-                Err(e.into())
+                Err(e)
             }
         }
     }
@@ -313,15 +431,16 @@ impl ProcessorController {
             
         let mut media_files = Vec::new();
         
-        // 处理MP3文件
+        // 处理MP3文件，启用内置解码时也处理其他可直接解码的音频
         for entry in fs::read_dir(&media_folder)? {
             let entry = entry?;
             let path = entry.path();
             
-            if path.is_file() && path.extension()
+            let is_mp3 = path.extension()
                 .and_then(|ext| ext.to_str())
                 .map(|ext| ext.to_lowercase() == "mp3")
-                .unwrap_or(false) {
+                .unwrap_or(false);
+            if path.is_file() && (is_mp3 || native_decodable(&path)) {
                 media_files.push(path);
             }
         }
@@ -404,7 +523,7 @@ impl ProcessorController {
             
         info!("启动监听模式，监控目录: {}", media_folder);
         
-        let observer = self.file_processor.start_file_monitoring()?;
+        let mut observer = self.file_processor.start_file_monitoring().await?;
         
         // 等待中断信号
         signal::ctrl_c().await?;
@@ -1,17 +1,306 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
 use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 use std::sync::Arc;
-use anyhow::Result;
+#[cfg(feature = "native-decode")]
+use std::sync::Mutex;
+use anyhow::{Result, anyhow};
+use log::{debug, info, warn};
+use serde::{Serialize, Deserialize};
+
+use crate::asr::base::AudioSegment;
+#[cfg(feature = "native-decode")]
+use crate::core::decoder::{self, DecodedAudio};
+use crate::core::file_utils::find_executable;
+use crate::core::vad::{SpeechRegion, Vad, VadOptions};
+use crate::error::AudioProcessorError;
 
 /// 音频提取器的回调函数类型
 pub type ProgressCallback = dyn Fn(usize, usize, Option<String>, Option<String>) + Send + Sync;
 
+/// 默认的音频片段时长（秒）
+pub const SEGMENT_SECS: u32 = 30;
+
+/// 提取和分割音频的采样率，识别服务都接受16kHz单声道
+const SAMPLE_RATE: &str = "16000";
+
+/// 解码为PCM时的采样率，用于语音检测和内置解码
+const PCM_SAMPLE_RATE: u32 = 16000;
+
+/// 分段方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SegmentMode {
+    /// 按固定时长切分
+    Fixed,
+    /// 在目标时长附近的静音处切分
+    Silence,
+    /// 用内置的语音检测切分，跳过长时间的非语音
+    Vad,
+}
+
+/// 分段设置，对应配置中的 segmentation 段
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SegmentOptions {
+    pub mode: SegmentMode,
+    /// 目标片段时长（秒），固定分段时即片段时长
+    pub target: f64,
+    /// 切点可偏离目标时长的范围（秒）
+    pub tolerance: f64,
+    /// 片段最短时长（秒）
+    pub min: f64,
+    /// 片段最长时长（秒），找不到静音时在目标时长处硬切
+    pub max: f64,
+    /// 低于该音量（dB）视为静音
+    pub silence_db: f64,
+    /// 静音至少持续的时长（秒）
+    pub min_silence: f64,
+    /// 语音检测模式下，超过该时长（秒）的非语音不送去识别
+    pub skip_silence: f64,
+    /// 相邻片段重叠的时长（秒），重复的文字在整理文本时去掉
+    pub overlap: f64,
+    /// 语音检测设置
+    pub vad: VadOptions,
+}
+
+impl Default for SegmentOptions {
+    fn default() -> Self {
+        Self {
+            mode: SegmentMode::Fixed,
+            target: SEGMENT_SECS as f64,
+            tolerance: 5.0,
+            min: 10.0,
+            max: 45.0,
+            silence_db: -30.0,
+            min_silence: 0.3,
+            skip_silence: 3.0,
+            overlap: 0.0,
+            vad: VadOptions::default(),
+        }
+    }
+}
+
+/// 解析 silencedetect 输出的静音区间，末尾未结束的静音延续到音频结尾
+fn parse_silences(stderr: &str, total: f64) -> Vec<(f64, f64)> {
+    let value_after = |line: &str, key: &str| -> Option<f64> {
+        let rest = &line[line.find(key)? + key.len()..];
+        rest.split_whitespace().next()?.trim_end_matches('|').parse().ok()
+    };
+
+    let mut silences = Vec::new();
+    let mut start = None;
+    for line in stderr.lines() {
+        if let Some(value) = value_after(line, "silence_start:") {
+            start = Some(value.max(0.0));
+        } else if let Some(end) = value_after(line, "silence_end:") {
+            if let Some(start) = start.take() {
+                silences.push((start, end));
+            }
+        }
+    }
+    if let Some(start) = start {
+        silences.push((start, total.max(start)));
+    }
+    silences
+}
+
+/// 在静音处选择切点：优先取目标时长 ± tolerance 内离目标最近的静音中点，
+/// 其次取 min..max 内离目标最近的，都没有时在目标时长处切分。
+/// 切点不会让剩下的部分短于 min
+fn choose_cuts(silences: &[(f64, f64)], total: f64, options: &SegmentOptions) -> Vec<f64> {
+    let min = options.min.max(1.0);
+    let max = options.max.max(min);
+    let target = options.target.clamp(min, max);
+    let tolerance = options.tolerance.max(0.0);
+
+    let midpoints: Vec<f64> = silences.iter().map(|(start, end)| (start + end) / 2.0).collect();
+    let nearest = |from: f64, lo: f64, hi: f64| -> Option<f64> {
+        midpoints.iter()
+            .copied()
+            .filter(|m| *m >= lo && *m <= hi)
+            .min_by(|a, b| (a - from).abs().total_cmp(&(b - from).abs()))
+    };
+
+    let mut cuts = Vec::new();
+    let mut start = 0.0;
+    while total - start > max {
+        let lo = start + min;
+        let hi = (start + max).min(total - min).max(lo);
+        let goal = (start + target).clamp(lo, hi);
+        let cut = nearest(goal, (goal - tolerance).max(lo), (goal + tolerance).min(hi))
+            .or_else(|| nearest(goal, lo, hi))
+            .unwrap_or(goal);
+        cuts.push(cut);
+        start = cut;
+    }
+    cuts
+}
+
+/// 把语音段分组为需要识别的区间：间隔超过 skip_silence 的语音段分属不同区间，
+/// 区间内过长时在语音段之间的间隙处按 choose_cuts 继续切分
+fn plan_speech_spans(regions: &[SpeechRegion], options: &SegmentOptions) -> Vec<(f64, f64)> {
+    let mut groups: Vec<Vec<SpeechRegion>> = Vec::new();
+    for region in regions {
+        match groups.last_mut() {
+            Some(group) if region.start - group[group.len() - 1].end < options.skip_silence => group.push(*region),
+            _ => groups.push(vec![*region]),
+        }
+    }
+
+    let mut spans = Vec::new();
+    for group in groups {
+        let start = group[0].start;
+        let end = group[group.len() - 1].end;
+        let gaps: Vec<(f64, f64)> = group.windows(2)
+            .map(|w| (w[0].end - start, w[1].start - start))
+            .collect();
+
+        let mut from = start;
+        for cut in choose_cuts(&gaps, end - start, options) {
+            spans.push((from, start + cut));
+            from = start + cut;
+        }
+        spans.push((from, end));
+    }
+    spans
+}
+
+/// ffprobe 读取的音频信息
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioMetadata {
+    /// 时长（秒）
+    pub duration: f64,
+    /// 第一条音轨的编码，没有音轨时为 None
+    pub codec: Option<String>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+}
+
+impl AudioMetadata {
+    /// 是否有音轨
+    pub fn has_audio(&self) -> bool {
+        self.codec.is_some()
+    }
+}
+
+#[derive(Deserialize)]
+struct ProbeOutput {
+    #[serde(default)]
+    format: Option<ProbeFormat>,
+    #[serde(default)]
+    streams: Vec<ProbeStream>,
+}
+
+#[derive(Deserialize)]
+struct ProbeFormat {
+    duration: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ProbeStream {
+    codec_name: Option<String>,
+    sample_rate: Option<String>,
+    channels: Option<u32>,
+    duration: Option<String>,
+}
+
+/// 解析 ffprobe 的 JSON 输出，容器没有时长时使用音轨时长
+fn parse_probe(json: &str) -> Result<AudioMetadata> {
+    let probe: ProbeOutput = serde_json::from_str(json)
+        .map_err(|e| anyhow!("无法解析ffprobe输出: {}", e))?;
+    let stream = probe.streams.into_iter().next();
+
+    let duration = probe.format.and_then(|f| f.duration)
+        .or_else(|| stream.as_ref().and_then(|s| s.duration.clone()))
+        .and_then(|d| d.parse::<f64>().ok())
+        .unwrap_or(0.0);
+
+    Ok(AudioMetadata {
+        duration,
+        codec: stream.as_ref().and_then(|s| s.codec_name.clone()),
+        sample_rate: stream.as_ref().and_then(|s| s.sample_rate.as_ref()).and_then(|r| r.parse().ok()),
+        channels: stream.and_then(|s| s.channels),
+    })
+}
+
+/// 查找外部程序，找不到时返回 ExternalProgramNotFound
+fn require_program(name: &str) -> Result<PathBuf> {
+    find_executable(name)
+        .ok_or_else(|| AudioProcessorError::ExternalProgramNotFound(name.to_string()).into())
+}
+
+/// 是否可以不经 ffmpeg 直接解码，需要启用 native-decode 特性
+pub fn native_decodable(path: &Path) -> bool {
+    #[cfg(feature = "native-decode")]
+    {
+        decoder::can_decode(path)
+    }
+    #[cfg(not(feature = "native-decode"))]
+    {
+        let _ = path;
+        false
+    }
+}
+
+/// 从 audio_file 截取的片段使用的扩展名，内置解码时写WAV
+pub fn snippet_extension(audio_file: &Path) -> &'static str {
+    if native_decodable(audio_file) { "wav" } else { "mp3" }
+}
+
+/// 读取媒体文件的时长和第一条音轨的信息，可直接解码的音频不需要 ffprobe；
+/// 内置解码器处理不了的编码（如 Opus、ALAC）仍交给 ffprobe
+pub fn probe_audio(path: &Path) -> Result<AudioMetadata> {
+    #[cfg(feature = "native-decode")]
+    if decoder::can_decode(path) {
+        match decoder::probe(path) {
+            Ok(metadata) => return Ok(metadata),
+            Err(e) => debug!("内置解码器无法读取，改用 ffprobe: {}", e),
+        }
+    }
+
+    let ffprobe = require_program("ffprobe")?;
+
+    let output = Command::new(&ffprobe)
+        .args(["-v", "error", "-select_streams", "a:0"])
+        .args(["-show_entries", "format=duration:stream=codec_name,sample_rate,channels,duration"])
+        .args(["-of", "json"])
+        .arg(path)
+        .output()?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(anyhow!("读取媒体信息失败 {}: {}", path.display(), stderr.trim()));
+    }
+
+    parse_probe(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// 解析 ffmpeg -progress 输出的一行，返回已处理的秒数
+fn parse_progress_line(line: &str) -> Option<f64> {
+    let (key, value) = line.trim().split_once('=')?;
+    match key {
+        // 旧版本 ffmpeg 的 out_time_ms 实际单位也是微秒
+        "out_time_us" | "out_time_ms" => value.parse::<f64>().ok().map(|us| us / 1_000_000.0),
+        _ => None,
+    }
+}
+
 /// 音频提取器，负责从媒体文件中提取音频
 pub struct AudioExtractor {
     /// 音频片段输出目录
     segments_dir: PathBuf,
-    
+
     /// 进度回调函数
     progress_callback: Option<Arc<ProgressCallback>>,
+
+    /// 分段设置
+    segment_options: SegmentOptions,
+
+    /// 最近一次解码的音频，截取复核片段时避免重复解码
+    #[cfg(feature = "native-decode")]
+    decoded: Mutex<Option<(PathBuf, Arc<DecodedAudio>)>>,
 }
 
 impl AudioExtractor {
@@ -20,22 +309,667 @@ impl AudioExtractor {
         Self {
             segments_dir: segments_dir.to_path_buf(),
             progress_callback,
+            segment_options: SegmentOptions::default(),
+            #[cfg(feature = "native-decode")]
+            decoded: Mutex::new(None),
+        }
+    }
+
+    /// 设置分段方式
+    pub fn with_segment_options(mut self, segment_options: SegmentOptions) -> Self {
+        self.segment_options = segment_options;
+        self
+    }
+
+    /// 音频片段输出目录
+    pub fn segments_dir(&self) -> &Path {
+        &self.segments_dir
+    }
+
+    /// 运行 ffmpeg，按已处理时长占总时长的百分比报告进度，返回错误输出
+    fn run_ffmpeg(&self, args: &[&std::ffi::OsStr], loglevel: &str, duration: f64, context: &str, label: &str) -> Result<String> {
+        let ffmpeg = require_program("ffmpeg")?;
+
+        let mut child = Command::new(&ffmpeg)
+            .args(["-hide_banner", "-loglevel", loglevel, "-nostats", "-progress", "pipe:1", "-y"])
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => AudioProcessorError::ExternalProgramNotFound("ffmpeg".to_string()).into(),
+                _ => anyhow!("无法启动ffmpeg: {}", e),
+            })?;
+
+        // 单独读取错误输出，避免管道写满后 ffmpeg 阻塞
+        let mut stderr = child.stderr.take().expect("stderr已设置为管道");
+        let stderr_reader = std::thread::spawn(move || {
+            let mut buf = String::new();
+            let _ = stderr.read_to_string(&mut buf);
+            buf
+        });
+
+        let report = |percent: usize| {
+            if let Some(callback) = &self.progress_callback {
+                callback(percent, 100, Some(format!("{} {}%", label, percent)), Some(context.to_string()));
+            }
+        };
+
+        let mut last_percent = 0;
+        if let Some(stdout) = child.stdout.take() {
+            for line in BufReader::new(stdout).lines().map_while(|l| l.ok()) {
+                let done = match parse_progress_line(&line) {
+                    Some(done) if duration > 0.0 => done,
+                    _ => continue,
+                };
+                // 100% 留到进程成功退出后再报告
+                let percent = ((done / duration * 100.0) as usize).min(99);
+                if percent > last_percent {
+                    last_percent = percent;
+                    report(percent);
+                }
+            }
         }
+
+        let status = child.wait()?;
+        let stderr = stderr_reader.join().unwrap_or_default();
+        if !status.success() {
+            return Err(anyhow!("ffmpeg执行失败 ({}): {}", label, stderr.trim()));
+        }
+
+        report(100);
+        Ok(stderr)
     }
-    
-    /// 从媒体文件提取音频
+
+    /// 从媒体文件提取音频，输出为16kHz单声道，格式由扩展名决定
     pub fn extract_audio(&self, media_file: &Path, output_file: &Path) -> Result<()> {
-        // 这里需要实现实际的音频提取逻辑
-        // 通常会使用 ffmpeg 或其他工具调用
-        // 暂时返回 Ok 作为占位符
+        let duration = probe_audio(media_file).map(|m| m.duration).unwrap_or(0.0);
+        let label = format!("提取音频 {}", media_file.file_name().unwrap_or_default().to_string_lossy());
+
+        self.run_ffmpeg(&[
+            "-i".as_ref(), media_file.as_os_str(),
+            "-vn".as_ref(), "-ac".as_ref(), "1".as_ref(), "-ar".as_ref(), SAMPLE_RATE.as_ref(),
+            output_file.as_os_str(),
+        ], "error", duration, "extract", &label)?;
+
+        debug!("已提取音频: {}", output_file.display());
         Ok(())
     }
-    
-    /// 将音频分段
-    pub fn segment_audio(&self, audio_file: &Path, max_part_time: u32) -> Result<Vec<PathBuf>> {
-        // 这里需要实现音频分段逻辑
-        // 返回分段后的音频文件路径列表
-        // 暂时返回空列表作为占位符
-        Ok(Vec::new())
+
+    /// 将音频按 segment_secs 秒分段，写入片段目录下以音频名命名的子目录，返回按顺序排列的片段路径
+    pub fn segment_audio(&self, audio_file: &Path, segment_secs: u32) -> Result<Vec<PathBuf>> {
+        let segment_time = segment_secs.max(1).to_string();
+        self.write_segments(audio_file, &["-segment_time".as_ref(), segment_time.as_ref()])
+    }
+
+    /// 片段目录下以音频名命名的子目录，清掉上次中断留下的片段
+    fn prepare_segments_dir(&self, audio_file: &Path) -> Result<PathBuf> {
+        let stem = audio_file.file_stem().and_then(|s| s.to_str()).unwrap_or("audio");
+        let output_dir = self.segments_dir.join(stem);
+
+        if output_dir.exists() {
+            fs::remove_dir_all(&output_dir)?;
+        }
+        fs::create_dir_all(&output_dir)?;
+        Ok(output_dir)
+    }
+
+    /// 在给定的时间点（秒）切分音频，返回按顺序排列的片段路径
+    pub fn segment_audio_at(&self, audio_file: &Path, cuts: &[f64]) -> Result<Vec<PathBuf>> {
+        if cuts.is_empty() {
+            return self.segment_audio(audio_file, u32::MAX);
+        }
+        let segment_times = cuts.iter().map(|c| format!("{:.3}", c)).collect::<Vec<_>>().join(",");
+        self.write_segments(audio_file, &["-segment_times".as_ref(), segment_times.as_ref()])
     }
-}
\ No newline at end of file
+
+    /// 用 ffmpeg 的 segment 输出分段，split_args 指定切分方式
+    fn write_segments(&self, audio_file: &Path, split_args: &[&std::ffi::OsStr]) -> Result<Vec<PathBuf>> {
+        let output_dir = self.prepare_segments_dir(audio_file)?;
+
+        let duration = probe_audio(audio_file).map(|m| m.duration).unwrap_or(0.0);
+        let pattern = output_dir.join("part_%04d.mp3");
+        let label = format!("分割音频 {}", audio_file.file_name().unwrap_or_default().to_string_lossy());
+
+        let mut args: Vec<&std::ffi::OsStr> = vec![
+            "-i".as_ref(), audio_file.as_os_str(),
+            "-vn".as_ref(), "-ac".as_ref(), "1".as_ref(), "-ar".as_ref(), SAMPLE_RATE.as_ref(),
+            "-f".as_ref(), "segment".as_ref(),
+        ];
+        args.extend_from_slice(split_args);
+        args.extend(["-reset_timestamps".as_ref(), "1".as_ref(), pattern.as_os_str()]);
+        self.run_ffmpeg(&args, "error", duration, "segment", &label)?;
+
+        let mut parts: Vec<PathBuf> = fs::read_dir(&output_dir)?
+            .filter_map(|e| e.ok())
+            .map(|e| e.path())
+            .filter(|p| p.extension().map(|e| e == "mp3").unwrap_or(false))
+            .collect();
+        parts.sort();
+
+        debug!("{} 分割为 {} 个片段", audio_file.display(), parts.len());
+        Ok(parts)
+    }
+
+    /// 从视频文件提取音频到输出目录，返回音频路径和是否为新提取；视频没有音轨时返回 None
+    pub fn extract_audio_from_video(&self, video_path: &Path, output_folder: &Path) -> Result<(Option<PathBuf>, bool)> {
+        let stem = video_path.file_stem().and_then(|s| s.to_str()).unwrap_or("audio");
+        let audio_path = output_folder.join(format!("{}.mp3", stem));
+
+        if fs::metadata(&audio_path).map(|m| m.len() > 0).unwrap_or(false) {
+            debug!("音频已存在，跳过提取: {}", audio_path.display());
+            return Ok((Some(audio_path), false));
+        }
+
+        let metadata = probe_audio(video_path)?;
+        if !metadata.has_audio() {
+            warn!("视频没有音轨: {}", video_path.display());
+            return Ok((None, false));
+        }
+
+        fs::create_dir_all(output_folder)?;
+        // 先写入临时文件，避免中断后留下不完整的音频被当作已提取
+        let partial = output_folder.join(format!("{}.partial.mp3", stem));
+        if let Err(e) = self.extract_audio(video_path, &partial) {
+            let _ = fs::remove_file(&partial);
+            return Err(e);
+        }
+        fs::rename(&partial, &audio_path)?;
+
+        info!("已从视频提取音频: {} ({:.1}秒)", audio_path.display(), metadata.duration);
+        Ok((Some(audio_path), true))
+    }
+
+    /// 用 silencedetect 找出音频中的静音区间
+    pub fn detect_silences(&self, audio_file: &Path, total: f64) -> Result<Vec<(f64, f64)>> {
+        let filter = format!("silencedetect=noise={}dB:d={}", self.segment_options.silence_db, self.segment_options.min_silence);
+        let label = format!("检测静音 {}", audio_file.file_name().unwrap_or_default().to_string_lossy());
+
+        let stderr = self.run_ffmpeg(&[
+            "-i".as_ref(), audio_file.as_os_str(),
+            "-vn".as_ref(), "-af".as_ref(), filter.as_ref(),
+            "-f".as_ref(), "null".as_ref(), "-".as_ref(),
+        ], "info", total, "silence", &label)?;
+
+        Ok(parse_silences(&stderr, total))
+    }
+
+    /// 把音频解码为16kHz单声道PCM送入语音检测，返回语音段
+    pub fn detect_speech(&self, audio_file: &Path, total: f64) -> Result<Vec<SpeechRegion>> {
+        let ffmpeg = require_program("ffmpeg")?;
+        let label = format!("检测语音 {}", audio_file.file_name().unwrap_or_default().to_string_lossy());
+
+        let mut child = Command::new(&ffmpeg)
+            .args(["-hide_banner", "-loglevel", "error", "-nostdin"])
+            .arg("-i").arg(audio_file)
+            .args(["-vn", "-ac", "1", "-ar", &PCM_SAMPLE_RATE.to_string(), "-f", "s16le", "-"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        let mut stderr = child.stderr.take().expect("stderr已设置为管道");
+        let stderr_reader = std::thread::spawn(move || {
+            let mut buf = String::new();
+            let _ = stderr.read_to_string(&mut buf);
+            buf
+        });
+
+        let mut vad = Vad::new(self.segment_options.vad.clone(), PCM_SAMPLE_RATE);
+        let mut stdout = child.stdout.take().expect("stdout已设置为管道");
+        let mut buf = vec![0u8; 64 * 1024];
+        let mut carry: Option<u8> = None;
+        let mut last_percent = 0;
+        loop {
+            let read = stdout.read(&mut buf)?;
+            if read == 0 {
+                break;
+            }
+            let mut bytes: Vec<u8> = carry.take().into_iter().collect();
+            bytes.extend_from_slice(&buf[..read]);
+            if bytes.len() % 2 == 1 {
+                carry = bytes.pop();
+            }
+            let samples: Vec<i16> = bytes.chunks_exact(2).map(|b| i16::from_le_bytes([b[0], b[1]])).collect();
+            vad.feed(&samples);
+
+            if let Some(callback) = &self.progress_callback {
+                let percent = if total > 0.0 { ((vad.duration() / total * 100.0) as usize).min(99) } else { 0 };
+                if percent > last_percent {
+                    last_percent = percent;
+                    callback(percent, 100, Some(format!("{} {}%", label, percent)), Some("vad".to_string()));
+                }
+            }
+        }
+
+        let status = child.wait()?;
+        let stderr = stderr_reader.join().unwrap_or_default();
+        if !status.success() {
+            return Err(anyhow!("ffmpeg执行失败 ({}): {}", label, stderr.trim()));
+        }
+        if let Some(callback) = &self.progress_callback {
+            callback(100, 100, Some(format!("{} 100%", label)), Some("vad".to_string()));
+        }
+
+        Ok(vad.finish())
+    }
+
+    /// 把音频中的各区间分别截取为片段，设置了重叠时片段向前延伸
+    fn cut_spans(&self, audio_file: &Path, spans: &[(f64, f64)], overlap: f64) -> Result<Vec<AudioSegment>> {
+        let overlapped = overlap_spans(spans, overlap);
+        let output_dir = self.prepare_segments_dir(audio_file)?;
+        let label = format!("分割音频 {}", audio_file.file_name().unwrap_or_default().to_string_lossy());
+
+        let mut segments = Vec::new();
+        for (idx, ((start, end), (span_start, _))) in overlapped.iter().zip(spans).enumerate() {
+            let part = output_dir.join(format!("part_{:04}.mp3", idx));
+            self.run_cut(audio_file, *start, *end, &part, SEGMENT_OUTPUT_ARGS)?;
+            segments.push(AudioSegment::new(&part, *start, Some(end - start)).with_overlap(span_start - start));
+
+            if let Some(callback) = &self.progress_callback {
+                callback(idx + 1, spans.len(), Some(format!("{} {}/{}", label, idx + 1, spans.len())), Some("segment".to_string()));
+            }
+        }
+        Ok(segments)
+    }
+
+    /// 按分段设置分割音频，片段的偏移即实际切点；设置了重叠时片段向前延伸 overlap 秒。
+    /// 只有语音检测模式在没有语音时返回空列表
+    pub fn split_audio_file(&self, audio_file: &Path) -> Result<Vec<AudioSegment>> {
+        #[cfg(feature = "native-decode")]
+        if let Some(audio) = self.try_decode(audio_file) {
+            return self.split_decoded(audio_file, &audio);
+        }
+
+        let total = probe_audio(audio_file)?.duration;
+        let options = &self.segment_options;
+        let overlap = options.overlap.max(0.0);
+
+        // 不重叠时用 segment 输出一次切完，重叠的片段只能逐个截取
+        let spans = match options.mode {
+            SegmentMode::Fixed => {
+                let secs = options.target.round().max(1.0);
+                if overlap == 0.0 {
+                    let parts = self.segment_audio(audio_file, secs as u32)?;
+                    if parts.is_empty() {
+                        return Err(anyhow!("ffmpeg没有输出片段: {}", audio_file.display()));
+                    }
+                    return Ok(segments_from_parts(&parts, secs, total));
+                }
+                spans_from_cuts(&fixed_cuts(secs, total), total)
+            }
+            SegmentMode::Silence => {
+                let silences = self.detect_silences(audio_file, total)?;
+                let cuts = choose_cuts(&silences, total, options);
+                debug!("{} 切点: {:?}", audio_file.display(), cuts);
+                if overlap == 0.0 {
+                    let parts = self.segment_audio_at(audio_file, &cuts)?;
+                    if parts.is_empty() {
+                        return Err(anyhow!("ffmpeg没有输出片段: {}", audio_file.display()));
+                    }
+                    if parts.len() != cuts.len() + 1 {
+                        warn!("片段数 {} 与切点数 {} 不符: {}", parts.len(), cuts.len(), audio_file.display());
+                    }
+                    return Ok(segments_at_cuts(&parts, &cuts, total));
+                }
+                spans_from_cuts(&cuts, total)
+            }
+            SegmentMode::Vad => {
+                let regions = self.detect_speech(audio_file, total)?;
+                let spans = plan_speech_spans(&regions, options);
+                let speech: f64 = spans.iter().map(|(start, end)| end - start).sum();
+                info!("{} 检测到 {:.1}/{:.1} 秒语音，分为 {} 个片段", audio_file.display(), speech, total, spans.len());
+                if spans.is_empty() {
+                    warn!("没有检测到语音: {}", audio_file.display());
+                }
+                spans
+            }
+        };
+
+        self.cut_spans(audio_file, &spans, overlap)
+    }
+
+    /// 解码音频，同一文件连续使用时复用上次的结果
+    #[cfg(feature = "native-decode")]
+    fn decode_cached(&self, audio_file: &Path) -> Result<Arc<DecodedAudio>> {
+        let mut cached = self.decoded.lock().unwrap();
+        if let Some((path, audio)) = cached.as_ref() {
+            if path == audio_file {
+                return Ok(audio.clone());
+            }
+        }
+
+        let label = format!("解码音频 {}", audio_file.file_name().unwrap_or_default().to_string_lossy());
+        let audio = Arc::new(decoder::decode(audio_file, PCM_SAMPLE_RATE)?);
+        if let Some(callback) = &self.progress_callback {
+            callback(100, 100, Some(format!("{} 100%", label)), Some("decode".to_string()));
+        }
+        *cached = Some((audio_file.to_path_buf(), audio.clone()));
+        Ok(audio)
+    }
+
+    /// 扩展名可直接解码时解码音频，解码失败（如 Opus、ALAC 编码）返回 None，改用 ffmpeg
+    #[cfg(feature = "native-decode")]
+    fn try_decode(&self, audio_file: &Path) -> Option<Arc<DecodedAudio>> {
+        if !decoder::can_decode(audio_file) {
+            return None;
+        }
+        match self.decode_cached(audio_file) {
+            Ok(audio) => Some(audio),
+            Err(e) => {
+                debug!("内置解码器无法解码，改用 ffmpeg: {}", e);
+                None
+            }
+        }
+    }
+
+    /// 不经 ffmpeg 分割音频：解码一次，在内存中规划切点，片段写成16kHz单声道WAV
+    #[cfg(feature = "native-decode")]
+    fn split_decoded(&self, audio_file: &Path, audio: &DecodedAudio) -> Result<Vec<AudioSegment>> {
+        let total = audio.duration();
+        let options = &self.segment_options;
+
+        let spans = match options.mode {
+            SegmentMode::Fixed => spans_from_cuts(&fixed_cuts(options.target.round().max(1.0), total), total),
+            SegmentMode::Silence => {
+                let silences = decoder::detect_silences(audio, options.silence_db, options.min_silence);
+                let cuts = choose_cuts(&silences, total, options);
+                debug!("{} 切点: {:?}", audio_file.display(), cuts);
+                spans_from_cuts(&cuts, total)
+            }
+            SegmentMode::Vad => {
+                let mut vad = Vad::new(options.vad.clone(), audio.sample_rate);
+                vad.feed(&audio.samples);
+                let spans = plan_speech_spans(&vad.finish(), options);
+                let speech: f64 = spans.iter().map(|(start, end)| end - start).sum();
+                info!("{} 检测到 {:.1}/{:.1} 秒语音，分为 {} 个片段", audio_file.display(), speech, total, spans.len());
+                if spans.is_empty() {
+                    warn!("没有检测到语音: {}", audio_file.display());
+                }
+                spans
+            }
+        };
+        let overlapped = overlap_spans(&spans, options.overlap.max(0.0));
+
+        let output_dir = self.prepare_segments_dir(audio_file)?;
+        let label = format!("分割音频 {}", audio_file.file_name().unwrap_or_default().to_string_lossy());
+
+        let mut segments = Vec::new();
+        for (idx, ((start, end), (span_start, _))) in overlapped.iter().zip(&spans).enumerate() {
+            let part = output_dir.join(format!("part_{:04}.wav", idx));
+            decoder::write_wav(&part, audio.slice(*start, *end), audio.sample_rate)?;
+            segments.push(AudioSegment::new(&part, *start, Some(end - start)).with_overlap(span_start - start));
+
+            if let Some(callback) = &self.progress_callback {
+                callback(idx + 1, spans.len(), Some(format!("{} {}/{}", label, idx + 1, spans.len())), Some("segment".to_string()));
+            }
+        }
+
+        debug!("{} 分割为 {} 个片段", audio_file.display(), segments.len());
+        Ok(segments)
+    }
+
+    /// 截取音频中的一段，输出格式由扩展名决定；可直接解码的音频截取为WAV时不需要 ffmpeg
+    pub fn cut_audio(&self, audio_file: &Path, start: f64, end: f64, output_file: &Path) -> Result<()> {
+        #[cfg(feature = "native-decode")]
+        if output_file.extension().map(|e| e == "wav").unwrap_or(false) {
+            if let Some(audio) = self.try_decode(audio_file) {
+                decoder::write_wav(output_file, audio.slice(start, end.max(start + 0.1)), audio.sample_rate)?;
+                debug!("已截取 {:.1}-{:.1}秒: {}", start, end, output_file.display());
+                return Ok(());
+            }
+        }
+
+        self.run_cut(audio_file, start, end, output_file, &[])
+    }
+
+    /// 用 ffmpeg 截取音频中的一段，output_args 为输出文件前的额外参数
+    fn run_cut(&self, audio_file: &Path, start: f64, end: f64, output_file: &Path, output_args: &[&str]) -> Result<()> {
+        let ffmpeg = require_program("ffmpeg")?;
+
+        let output = Command::new(&ffmpeg)
+            .args(["-hide_banner", "-loglevel", "error", "-y"])
+            .args(cut_args(audio_file, start, end, output_file, output_args))
+            .output()?;
+
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(anyhow!("截取音频失败 {}: {}", audio_file.display(), stderr.trim()));
+        }
+
+        debug!("已截取 {:.1}-{:.1}秒: {}", start, end, output_file.display());
+        Ok(())
+    }
+}
+
+/// 送去识别的片段的输出参数，与 segment 输出一致转为16kHz单声道；复核片段保持原始音质
+const SEGMENT_OUTPUT_ARGS: &[&str] = &["-ac", "1", "-ar", SAMPLE_RATE];
+
+/// 截取 start..end 秒的 ffmpeg 参数，output_args 放在输出文件之前
+fn cut_args(audio_file: &Path, start: f64, end: f64, output_file: &Path, output_args: &[&str]) -> Vec<std::ffi::OsString> {
+    let mut args: Vec<std::ffi::OsString> = vec![
+        "-ss".into(), format!("{:.3}", start.max(0.0)).into(),
+        "-t".into(), format!("{:.3}", (end - start).max(0.1)).into(),
+        "-i".into(), audio_file.into(),
+        "-vn".into(),
+    ];
+    args.extend(output_args.iter().map(|a| a.into()));
+    args.push(output_file.into());
+    args
+}
+
+/// 按固定时长为片段计算偏移，最后一段的时长取剩余部分
+fn segments_from_parts(parts: &[PathBuf], segment_secs: f64, total: f64) -> Vec<AudioSegment> {
+    parts.iter()
+        .enumerate()
+        .map(|(idx, path)| {
+            let offset = idx as f64 * segment_secs;
+            let duration = if total > 0.0 {
+                (total - offset).clamp(0.0, segment_secs)
+            } else {
+                segment_secs
+            };
+            AudioSegment::new(path, offset, Some(duration))
+        })
+        .collect()
+}
+
+/// 固定分段的切点
+fn fixed_cuts(segment_secs: f64, total: f64) -> Vec<f64> {
+    (1..).map(|i| i as f64 * segment_secs).take_while(|cut| *cut < total).collect()
+}
+
+/// 切点两两之间的区间
+fn spans_from_cuts(cuts: &[f64], total: f64) -> Vec<(f64, f64)> {
+    let mut bounds = vec![0.0];
+    bounds.extend_from_slice(cuts);
+    bounds.push(total);
+    bounds.windows(2).map(|w| (w[0], w[1])).collect()
+}
+
+/// 与上一区间相接的区间向前延伸 overlap 秒（不超过上一区间的起点），
+/// 中间跳过了空白的区间不延伸
+fn overlap_spans(spans: &[(f64, f64)], overlap: f64) -> Vec<(f64, f64)> {
+    spans.iter()
+        .enumerate()
+        .map(|(idx, &(start, end))| match idx.checked_sub(1).map(|prev| spans[prev]) {
+            Some((prev_start, prev_end)) if overlap > 0.0 && start - prev_end < 1e-6 => ((start - overlap).max(prev_start), end),
+            _ => (start, end),
+        })
+        .collect()
+}
+
+/// 按切点计算片段的偏移和时长
+fn segments_at_cuts(parts: &[PathBuf], cuts: &[f64], total: f64) -> Vec<AudioSegment> {
+    parts.iter()
+        .enumerate()
+        .map(|(idx, path)| {
+            let offset = if idx == 0 { 0.0 } else { cuts.get(idx - 1).copied().unwrap_or(total) };
+            let end = cuts.get(idx).copied().unwrap_or(total);
+            AudioSegment::new(path, offset, Some((end - offset).max(0.0)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_probe_output() {
+        let json = r#"{
+            "streams": [{ "codec_name": "aac", "sample_rate": "44100", "channels": 2 }],
+            "format": { "duration": "125.480000" }
+        }"#;
+        let metadata = parse_probe(json).unwrap();
+        assert_eq!(metadata.duration, 125.48);
+        assert_eq!(metadata.codec.as_deref(), Some("aac"));
+        assert_eq!(metadata.sample_rate, Some(44100));
+        assert_eq!(metadata.channels, Some(2));
+
+        let silent = parse_probe(r#"{ "streams": [], "format": { "duration": "3.0" } }"#).unwrap();
+        assert!(!silent.has_audio());
+        assert!(parse_probe("not json").is_err());
+    }
+
+    #[test]
+    fn parses_progress_lines() {
+        assert_eq!(parse_progress_line("out_time_us=1500000"), Some(1.5));
+        assert_eq!(parse_progress_line("out_time_ms=2000000\n"), Some(2.0));
+        assert_eq!(parse_progress_line("out_time_us=N/A"), None);
+        assert_eq!(parse_progress_line("progress=continue"), None);
+    }
+
+    #[test]
+    fn parses_silencedetect_output() {
+        let stderr = "\
+[silencedetect @ 0x5581] silence_start: 28.512
+[silencedetect @ 0x5581] silence_end: 29.104 | silence_duration: 0.592
+size=N/A time=00:01:10.00 bitrate=N/A
+[silencedetect @ 0x5581] silence_start: 68.2";
+        assert_eq!(parse_silences(stderr, 70.0), vec![(28.512, 29.104), (68.2, 70.0)]);
+    }
+
+    #[test]
+    fn cuts_at_silence_nearest_the_target() {
+        let options = SegmentOptions { mode: SegmentMode::Silence, ..SegmentOptions::default() };
+        let silences = [(20.0, 21.0), (27.0, 28.0), (33.0, 34.0), (61.0, 61.4), (100.0, 100.4)];
+        // 27.5 离 30 最近；61.2 在 57.5 ± 5 内；91.2 ± 5 内没有静音，取 min..max 内最近的 100.2
+        assert_eq!(choose_cuts(&silences, 120.0, &options), vec![27.5, 61.2, 100.2]);
+        // 短音频不切分
+        assert!(choose_cuts(&silences, 40.0, &options).is_empty());
+    }
+
+    #[test]
+    fn last_segment_is_not_shorter_than_min() {
+        let options = SegmentOptions { mode: SegmentMode::Silence, ..SegmentOptions::default() };
+        // 44 秒处的静音会留下 2 秒的尾巴，只能在 10..36 秒内切
+        assert_eq!(choose_cuts(&[(43.5, 44.5)], 46.0, &options), vec![30.0]);
+        assert_eq!(choose_cuts(&[(34.5, 35.5), (43.5, 44.5)], 46.0, &options), vec![35.0]);
+    }
+
+    #[test]
+    fn cuts_at_target_without_silence() {
+        let options = SegmentOptions { mode: SegmentMode::Silence, ..SegmentOptions::default() };
+        assert_eq!(choose_cuts(&[], 100.0, &options), vec![30.0, 60.0]);
+    }
+
+    #[test]
+    fn speech_spans_skip_long_pauses() {
+        let options = SegmentOptions { mode: SegmentMode::Vad, ..SegmentOptions::default() };
+        let region = |start: f64, end: f64| SpeechRegion { start, end };
+        let regions = [region(1.0, 5.0), region(6.0, 9.0), region(40.0, 42.0)];
+        // 9 到 40 秒的空白被跳过
+        assert_eq!(plan_speech_spans(&regions, &options), vec![(1.0, 9.0), (40.0, 42.0)]);
+        assert!(plan_speech_spans(&[], &options).is_empty());
+    }
+
+    #[test]
+    fn long_speech_spans_are_cut_between_regions() {
+        let options = SegmentOptions { mode: SegmentMode::Vad, ..SegmentOptions::default() };
+        let regions: Vec<SpeechRegion> = (0..10)
+            .map(|i| SpeechRegion { start: i as f64 * 10.0, end: i as f64 * 10.0 + 9.0 })
+            .collect();
+        let spans = plan_speech_spans(&regions, &options);
+        // 间隙中点 29.5、59.5 离 30 的倍数最近
+        assert_eq!(spans, vec![(0.0, 29.5), (29.5, 59.5), (59.5, 99.0)]);
+    }
+
+    #[test]
+    fn fixed_cuts_stop_before_the_end() {
+        assert_eq!(fixed_cuts(30.0, 75.0), vec![30.0, 60.0]);
+        assert_eq!(fixed_cuts(30.0, 60.0), vec![30.0]);
+        assert!(fixed_cuts(30.0, 20.0).is_empty());
+    }
+
+    #[test]
+    fn overlapping_spans_reach_back_into_the_previous_one() {
+        let spans = spans_from_cuts(&[30.0, 60.0], 75.0);
+        assert_eq!(spans, vec![(0.0, 30.0), (30.0, 60.0), (60.0, 75.0)]);
+        assert_eq!(overlap_spans(&spans, 2.0), vec![(0.0, 30.0), (28.0, 60.0), (58.0, 75.0)]);
+        assert_eq!(overlap_spans(&spans, 0.0), spans);
+        // 跳过空白的区间不重叠，延伸不超过上一区间的起点
+        assert_eq!(overlap_spans(&[(0.0, 9.0), (40.0, 42.0), (42.0, 43.0)], 5.0), vec![(0.0, 9.0), (40.0, 42.0), (40.0, 43.0)]);
+    }
+
+    #[cfg(feature = "native-decode")]
+    #[test]
+    fn splits_decodable_audio_without_ffmpeg() {
+        let dir = tempfile::tempdir().unwrap();
+        let audio = dir.path().join("talk.wav");
+        decoder::write_wav(&audio, &vec![4000i16; 16000 * 70], 16000).unwrap();
+
+        let options = SegmentOptions { overlap: 2.0, ..SegmentOptions::default() };
+        let extractor = AudioExtractor::new(&dir.path().join("segments"), None).with_segment_options(options);
+        let segments = extractor.split_audio_file(&audio).unwrap();
+
+        assert_eq!(segments.iter().map(|s| s.offset).collect::<Vec<_>>(), vec![0.0, 28.0, 58.0]);
+        assert_eq!(segments.iter().map(|s| s.content_start()).collect::<Vec<_>>(), vec![0.0, 30.0, 60.0]);
+        assert_eq!(segments[2].duration, Some(12.0));
+        assert!(segments.iter().all(|s| s.path.extension().unwrap() == "wav"));
+        assert_eq!(probe_audio(&segments[1].path).unwrap().duration, 32.0);
+    }
+
+    #[cfg(feature = "native-decode")]
+    #[test]
+    fn undecodable_audio_falls_back_to_ffmpeg() {
+        let dir = tempfile::tempdir().unwrap();
+        // 扩展名可以直接解码，内容却不行，比如 Opus 编码的 ogg
+        let audio = dir.path().join("voice.ogg");
+        std::fs::write(&audio, b"OggS not really an ogg stream").unwrap();
+
+        let extractor = AudioExtractor::new(&dir.path().join("segments"), None);
+        for error in [probe_audio(&audio).unwrap_err(), extractor.split_audio_file(&audio).unwrap_err()] {
+            let message = error.to_string();
+            assert!(message.contains("ffprobe") || message.contains("读取媒体信息失败"), "{}", message);
+        }
+    }
+
+    #[test]
+    fn cut_segments_are_converted_to_mono_16k() {
+        let args = cut_args(Path::new("in.m4a"), 28.0, 60.0, Path::new("part_0001.mp3"), SEGMENT_OUTPUT_ARGS);
+        let args: Vec<&str> = args.iter().map(|a| a.to_str().unwrap()).collect();
+        assert_eq!(args, ["-ss", "28.000", "-t", "32.000", "-i", "in.m4a", "-vn", "-ac", "1", "-ar", "16000", "part_0001.mp3"]);
+
+        // 复核片段保持原始音质
+        let snippet = cut_args(Path::new("in.m4a"), 1.0, 2.0, Path::new("001.mp3"), &[]);
+        assert!(!snippet.iter().any(|a| a == "-ar"));
+    }
+
+    #[test]
+    fn segments_start_at_their_cut() {
+        let parts: Vec<PathBuf> = (0..3).map(|i| PathBuf::from(format!("part_{:04}.mp3", i))).collect();
+        let segments = segments_at_cuts(&parts, &[27.5, 61.2], 80.0);
+        assert_eq!(segments.iter().map(|s| s.offset).collect::<Vec<_>>(), vec![0.0, 27.5, 61.2]);
+        assert_eq!(segments[2].duration, Some(80.0 - 61.2));
+    }
+
+    #[test]
+    fn last_segment_takes_the_remainder() {
+        let parts: Vec<PathBuf> = (0..3).map(|i| PathBuf::from(format!("part_{:04}.mp3", i))).collect();
+        let segments = segments_from_parts(&parts, 30.0, 75.0);
+        assert_eq!(segments.iter().map(|s| s.offset).collect::<Vec<_>>(), vec![0.0, 30.0, 60.0]);
+        assert_eq!(segments.iter().map(|s| s.duration).collect::<Vec<_>>(), vec![Some(30.0), Some(30.0), Some(15.0)]);
+    }
+}
@@ -3,10 +3,27 @@ use std::fs::{self, File};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use anyhow::{Result, Context};
-use log::{info, warn, error};
+use log::{info, warn};
 use serde_json::Value;
 use thiserror::Error;
 
+use crate::core::credentials::mask_secrets;
+
+/// 没有默认值、但会被读取的配置项
+const OPTIONAL_KEYS: &[&str] = &[
+    "asr_plugins",
+    "asr_priorities",
+    "consensus_providers",
+    "consensus_weights",
+    "jianying_device_id",
+    "local_asr_language",
+    "local_asr_model",
+    "local_asr_threads",
+    "openai_api_key",
+    "openai_language",
+    "temp_dir",
+];
+
 /// 配置验证错误
 #[derive(Error, Debug)]
 pub enum ConfigValidationError {
@@ -68,17 +85,82 @@ impl ConfigManager {
         self.config.insert("output_folder".to_string(), Value::String("D:/download/dest/".to_string()));
         self.config.insert("max_retries".to_string(), Value::Number(3.into()));
         self.config.insert("max_workers".to_string(), Value::Number(4.into()));
-        self.config.insert("use_jianying_first".to_string(), Value::Bool(false));
-        self.config.insert("use_kuaishou".to_string(), Value::Bool(false));
-        self.config.insert("use_bcut".to_string(), Value::Bool(true));
+        self.config.insert("jianying_base_url".to_string(), Value::String(crate::asr::jianying::DEFAULT_BASE_URL.to_string()));
+        self.config.insert("jianying_vod_url".to_string(), Value::String(crate::asr::jianying::DEFAULT_VOD_URL.to_string()));
+        self.config.insert("kuaishou_endpoint".to_string(), Value::String(crate::asr::kuaishou::DEFAULT_ENDPOINT.to_string()));
+        self.config.insert("openai_base_url".to_string(), Value::String(crate::asr::openai::DEFAULT_BASE_URL.to_string()));
+        self.config.insert("openai_model".to_string(), Value::String(crate::asr::openai::DEFAULT_MODEL.to_string()));
+        self.config.insert("asr_chain".to_string(), serde_json::json!(crate::asr::manager::DEFAULT_CHAIN));
+        self.config.insert("asr_strategy".to_string(), Value::String("fallback".to_string()));
+        self.config.insert("bcut_base_url".to_string(), Value::String(crate::asr::bcut::DEFAULT_BASE_URL.to_string()));
+        self.config.insert("local_asr_engine".to_string(), Value::String("whisper_cpp".to_string()));
+        self.config.insert("local_asr_binary".to_string(), Value::String("whisper-cli".to_string()));
+        // 按片段限流，一个片段内的多次HTTP请求不单独计数
+        self.config.insert("asr_segment_limits".to_string(), serde_json::json!({
+            "bcut": { "segments_per_second": 0.5, "burst": 2, "max_concurrency": 2 }
+        }));
+        self.config.insert("asr_cache".to_string(), Value::Bool(true));
+        self.config.insert("asr_cache_dir".to_string(), Value::String(String::new()));
+        self.config.insert("asr_cache_max_mb".to_string(), Value::Number(500.into()));
+        self.config.insert("asr_cache_max_age_days".to_string(), Value::Number(30.into()));
+        self.config.insert("credentials".to_string(), serde_json::json!({}));
+        self.config.insert("credentials_file".to_string(), Value::String(String::new()));
+        self.config.insert("output_formats".to_string(), serde_json::json!(["txt"]));
+        self.config.insert("review_threshold".to_string(), serde_json::json!(0.0));
+        self.config.insert("review_dir".to_string(), Value::String(String::new()));
+        self.config.insert("review_padding".to_string(), serde_json::json!(0.5));
+        self.config.insert("review_snippets".to_string(), Value::Bool(true));
+        self.config.insert("language".to_string(), Value::String(String::new()));
+        self.config.insert("file_languages".to_string(), serde_json::json!({}));
+        self.config.insert("language_sample_segments".to_string(), serde_json::json!(3));
+        self.config.insert("hotwords".to_string(), Value::Array(Vec::new()));
+        self.config.insert("hotwords_file".to_string(), Value::String(String::new()));
+        self.config.insert("hotword_max_distance".to_string(), serde_json::json!(0.34));
+        self.config.insert("dry_run_asr".to_string(), Value::Bool(false));
+        self.config.insert("mock_asr".to_string(), serde_json::json!({
+            "failure_rate": 0.0,
+            "empty_rate": 0.0,
+            "latency_ms": 0
+        }));
+        // proxy 不设置时使用 HTTP_PROXY 等环境变量，"none" 表示直连
+        self.config.insert("http".to_string(), serde_json::json!({
+            "proxy": null,
+            "no_proxy": [],
+            "connect_timeout": crate::asr::http::DEFAULT_CONNECT_TIMEOUT,
+            "timeout": crate::asr::http::DEFAULT_TIMEOUT,
+            "read_timeout": crate::asr::http::DEFAULT_READ_TIMEOUT,
+            "user_agent": null,
+            "ca_certs": [],
+            "providers": {}
+        }));
+        self.config.insert("http_proxy".to_string(), Value::String(String::new()));
+        self.config.insert("http_fixtures_mode".to_string(), Value::String("off".to_string()));
+        self.config.insert("http_fixtures_dir".to_string(), Value::String(String::new()));
+        self.config.insert("circuit_failure_threshold".to_string(), Value::Number(3.into()));
+        self.config.insert("circuit_cooldown".to_string(), Value::Number(60.into()));
         self.config.insert("format_text".to_string(), Value::Bool(true));
         self.config.insert("include_timestamps".to_string(), Value::Bool(true));
         self.config.insert("show_progress".to_string(), Value::Bool(true));
         self.config.insert("process_video".to_string(), Value::Bool(true));
         self.config.insert("extract_audio_only".to_string(), Value::Bool(false));
         self.config.insert("watch_mode".to_string(), Value::Bool(false));
+        // mode 为 silence 时在 target ± tolerance 内的静音处切分，
+        // 为 vad 时用内置语音检测切分并跳过超过 skip_silence 秒的非语音
+        self.config.insert("segmentation".to_string(), serde_json::json!({
+            "mode": "fixed",
+            "target": 30,
+            "tolerance": 5,
+            "min": 10,
+            "max": 45,
+            "silence_db": -30,
+            "min_silence": 0.3,
+            "skip_silence": 3.0,
+            "overlap": 0
+        }));
+        self.config.insert("segment_mode".to_string(), Value::String(String::new()));
+        self.config.insert("segment_overlap".to_string(), Value::Null);
         self.config.insert("max_part_time".to_string(), Value::Number(30.into()));
-        self.config.insert("retry_delay".to_string(), Value::Number(1.5.into()));
+        self.config.insert("retry_delay".to_string(), serde_json::json!(1.5));
     }
     
     /// 从文件加载配置
@@ -89,9 +171,17 @@ impl ConfigManager {
         
         let loaded_config: HashMap<String, Value> = serde_json::from_str(&contents)?;
         
-        // 更新现有配置
+        // 更新现有配置，对象逐项合并，文件中没写的子项（如内置的 bcut 限流）保留默认值
         for (key, value) in loaded_config {
-            self.config.insert(key, value);
+            if !self.is_known_key(&key) {
+                warn!("未知的配置项 {}，不会生效（请检查拼写）: {}", key, path.display());
+            }
+            match self.config.get_mut(&key) {
+                Some(current) => merge_value(current, value),
+                None => {
+                    self.config.insert(key, value);
+                }
+            }
         }
         
         info!("从文件加载了配置: {}", path.display());
@@ -99,6 +189,11 @@ impl ConfigManager {
         Ok(())
     }
     
+    /// 是否为程序会读取的配置项
+    fn is_known_key(&self, key: &str) -> bool {
+        self.config.contains_key(key) || OPTIONAL_KEYS.contains(&key)
+    }
+    
     /// 保存配置到文件
     pub fn save_config(&self, path: &Path) -> Result<()> {
         let config_json = serde_json::to_string_pretty(&self.config)?;
@@ -140,11 +235,13 @@ impl ConfigManager {
         self.config.clone()
     }
     
-    /// 打印当前配置
+    /// 打印当前配置，密钥、cookie 等敏感值以 ****** 代替
     pub fn print_config(&self) {
         info!("当前配置:");
-        for (key, value) in &self.config {
-            info!("  {}: {}", key, value);
+        let mut keys: Vec<&String> = self.config.keys().collect();
+        keys.sort();
+        for key in keys {
+            info!("  {}: {}", key, mask_secrets(key, &self.config[key]));
         }
     }
     
@@ -179,4 +276,57 @@ impl ConfigManager {
         
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// 把 update 合并到 base：两边都是对象时逐键递归合并，否则用 update 替换
+fn merge_value(base: &mut Value, update: Value) {
+    match (base, update) {
+        (Value::Object(base), Value::Object(update)) => {
+            for (key, value) in update {
+                match base.get_mut(&key) {
+                    Some(current) => merge_value(current, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, update) => *base = update,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn config_file_objects_are_merged_into_defaults() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.json");
+        fs::write(&path, json!({
+            "asr_segment_limits": { "openai": { "segments_per_second": 1 } },
+            "http": { "proxy": "none", "timeout": 60 },
+            "output_formats": ["srt"],
+        }).to_string()).unwrap();
+
+        let config = ConfigManager::new(Some(&path)).unwrap();
+        let limits = config.get("asr_segment_limits").unwrap();
+        assert_eq!(limits["openai"]["segments_per_second"], 1);
+        assert_eq!(limits["bcut"]["burst"], 2);
+        let http = config.get("http").unwrap();
+        assert_eq!(http["proxy"], "none");
+        assert_eq!(http["timeout"], 60);
+        assert_eq!(http["read_timeout"], crate::asr::http::DEFAULT_READ_TIMEOUT);
+        // 数组整个替换
+        assert_eq!(config.get("output_formats").unwrap(), &json!(["srt"]));
+    }
+
+    #[test]
+    fn recognizes_known_config_keys() {
+        let config = ConfigManager::new(None).unwrap();
+        assert!(config.is_known_key("asr_segment_limits"));
+        assert!(config.is_known_key("asr_plugins"));
+        assert!(!config.is_known_key("asr_rate_limits"));
+    }
+}
@@ -0,0 +1,288 @@
+//! 纯Rust音频解码，启用 native-decode 特性时使用
+//!
+//! 用 symphonia 解码 mp3、aac/m4a、flac、wav、ogg，混合为单声道并重采样，
+//! 片段用 hound 写成16位WAV。整个文件解码后保存在内存中，每小时约 115MB（16kHz）。
+//! 视频等其他容器以及解码失败的音频（如 Opus、ALAC 编码）仍交给 ffmpeg。
+
+use std::fs::File;
+use std::path::Path;
+use anyhow::{Result, anyhow};
+use log::debug;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{DecoderOptions, CODEC_TYPE_NULL};
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::{FormatOptions, FormatReader, Track};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+
+use crate::core::audio_extractor::AudioMetadata;
+
+/// 可以直接解码的扩展名
+const EXTENSIONS: &[&str] = &["mp3", "aac", "m4a", "flac", "wav", "ogg", "oga"];
+
+/// 解码后的单声道PCM
+#[derive(Debug, Clone)]
+pub struct DecodedAudio {
+    pub samples: Vec<i16>,
+    pub sample_rate: u32,
+}
+
+impl DecodedAudio {
+    /// 时长（秒）
+    pub fn duration(&self) -> f64 {
+        self.samples.len() as f64 / self.sample_rate as f64
+    }
+
+    /// start..end 秒之间的采样，超出范围的部分被截掉
+    pub fn slice(&self, start: f64, end: f64) -> &[i16] {
+        let index = |secs: f64| ((secs.max(0.0) * self.sample_rate as f64).round() as usize).min(self.samples.len());
+        let (from, to) = (index(start), index(end));
+        &self.samples[from..to.max(from)]
+    }
+}
+
+/// 文件扩展名是否属于可直接解码的音频格式
+pub fn can_decode(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// 打开文件，返回容器读取器和第一条音轨
+fn open(path: &Path) -> Result<(Box<dyn FormatReader>, Track)> {
+    let file = File::open(path)
+        .map_err(|e| anyhow!("无法打开音频 {}: {}", path.display(), e))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(ext);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+        .map_err(|e| anyhow!("无法识别音频格式 {}: {}", path.display(), e))?;
+    let track = probed.format.tracks().iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        .cloned()
+        .ok_or_else(|| anyhow!("没有音轨: {}", path.display()))?;
+
+    Ok((probed.format, track))
+}
+
+/// 读取时长和音轨信息，容器没有记录总帧数时解码一遍计算时长
+pub fn probe(path: &Path) -> Result<AudioMetadata> {
+    let (_, track) = open(path)?;
+    let params = &track.codec_params;
+
+    let duration = match (params.n_frames, params.sample_rate) {
+        (Some(frames), Some(rate)) if rate > 0 => frames as f64 / rate as f64,
+        _ => decode(path, 16000)?.duration(),
+    };
+
+    Ok(AudioMetadata {
+        duration,
+        codec: symphonia::default::get_codecs()
+            .get_codec(params.codec)
+            .map(|d| d.short_name.to_string()),
+        sample_rate: params.sample_rate,
+        channels: params.channels.map(|c| c.count() as u32),
+    })
+}
+
+/// 解码整个文件，混合为单声道并重采样到 sample_rate
+pub fn decode(path: &Path, sample_rate: u32) -> Result<DecodedAudio> {
+    let (mut format, track) = open(path)?;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .map_err(|e| anyhow!("不支持的音频编码 {}: {}", path.display(), e))?;
+
+    let mut mono: Vec<f32> = Vec::new();
+    let mut source_rate = track.codec_params.sample_rate.unwrap_or(sample_rate);
+    let mut buffer: Option<SampleBuffer<f32>> = None;
+
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(anyhow!("读取音频失败 {}: {}", path.display(), e)),
+        };
+        if packet.track_id() != track.id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // 个别损坏的帧跳过即可
+            Err(SymphoniaError::DecodeError(e)) => {
+                debug!("跳过无法解码的帧 {}: {}", path.display(), e);
+                continue;
+            }
+            Err(e) => return Err(anyhow!("解码音频失败 {}: {}", path.display(), e)),
+        };
+
+        let spec = *decoded.spec();
+        source_rate = spec.rate;
+        let channels = spec.channels.count().max(1);
+        let buffer = match &mut buffer {
+            Some(buffer) if buffer.capacity() >= decoded.capacity() * channels => buffer,
+            _ => buffer.insert(SampleBuffer::new(decoded.capacity() as u64, spec)),
+        };
+        buffer.copy_interleaved_ref(decoded);
+        mono.extend(buffer.samples().chunks(channels).map(|frame| frame.iter().sum::<f32>() / channels as f32));
+    }
+
+    let samples = resample(&mono, source_rate, sample_rate)
+        .into_iter()
+        .map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32) as i16)
+        .collect();
+
+    Ok(DecodedAudio { samples, sample_rate })
+}
+
+/// 重采样；降采样时取输出采样点覆盖范围内的平均值，兼作简单的抗混叠滤波，升采样时线性插值
+fn resample(input: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to || input.is_empty() || from == 0 || to == 0 {
+        return input.to_vec();
+    }
+
+    let ratio = from as f64 / to as f64;
+    let len = (input.len() as f64 / ratio).floor() as usize;
+    (0..len)
+        .map(|i| {
+            let center = i as f64 * ratio;
+            if ratio > 1.0 {
+                let start = (center - ratio / 2.0).max(0.0).round() as usize;
+                let end = ((center + ratio / 2.0).round() as usize).clamp(start + 1, input.len());
+                input[start..end].iter().sum::<f32>() / (end - start) as f32
+            } else {
+                let idx = center.floor() as usize;
+                let frac = (center - idx as f64) as f32;
+                let next = input.get(idx + 1).copied().unwrap_or(input[idx]);
+                input[idx] + (next - input[idx]) * frac
+            }
+        })
+        .collect()
+}
+
+/// 静音检测的窗口长度（秒）
+const SILENCE_WINDOW: f64 = 0.01;
+
+/// 找出音量持续低于 noise_db 至少 min_duration 秒的区间，对应 ffmpeg 的 silencedetect；
+/// 末尾的静音延续到音频结尾
+pub fn detect_silences(audio: &DecodedAudio, noise_db: f64, min_duration: f64) -> Vec<(f64, f64)> {
+    let window = ((audio.sample_rate as f64 * SILENCE_WINDOW) as usize).max(1);
+    let window_secs = window as f64 / audio.sample_rate as f64;
+    let limit = 32768.0 * 10f64.powf(noise_db / 20.0);
+
+    let mut silences = Vec::new();
+    let mut start: Option<f64> = None;
+    for (idx, chunk) in audio.samples.chunks(window).enumerate() {
+        let rms = (chunk.iter().map(|&s| (s as f64) * (s as f64)).sum::<f64>() / chunk.len() as f64).sqrt();
+        let at = idx as f64 * window_secs;
+        if rms < limit {
+            start.get_or_insert(at);
+        } else if let Some(from) = start.take() {
+            if at - from >= min_duration {
+                silences.push((from, at));
+            }
+        }
+    }
+    if let Some(from) = start {
+        let total = audio.duration();
+        if total - from >= min_duration {
+            silences.push((from, total));
+        }
+    }
+    silences
+}
+
+/// 写入16位单声道WAV
+pub fn write_wav(path: &Path, samples: &[i16], sample_rate: u32) -> Result<()> {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(path, spec)
+        .map_err(|e| anyhow!("无法写入 {}: {}", path.display(), e))?;
+    for sample in samples {
+        writer.write_sample(*sample)?;
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_decodable_extensions() {
+        assert!(can_decode(Path::new("/tmp/a.MP3")));
+        assert!(can_decode(Path::new("talk.m4a")));
+        assert!(!can_decode(Path::new("movie.mp4")));
+        assert!(!can_decode(Path::new("noext")));
+    }
+
+    #[test]
+    fn resampling_keeps_duration_and_level() {
+        let input = vec![0.5f32; 44100];
+        let down = resample(&input, 44100, 16000);
+        assert_eq!(down.len(), 16000);
+        assert!(down.iter().all(|s| (s - 0.5).abs() < 1e-6));
+
+        let up = resample(&[0.0, 1.0], 8000, 16000);
+        assert_eq!(up, vec![0.0, 0.5, 1.0, 1.0]);
+    }
+
+    #[test]
+    fn decodes_stereo_wav_to_mono_16k() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stereo.wav");
+        let spec = hound::WavSpec { channels: 2, sample_rate: 32000, bits_per_sample: 16, sample_format: hound::SampleFormat::Int };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for _ in 0..32000 {
+            writer.write_sample(8000i16).unwrap();
+            writer.write_sample(0i16).unwrap();
+        }
+        writer.finalize().unwrap();
+
+        let metadata = probe(&path).unwrap();
+        assert_eq!(metadata.duration, 1.0);
+        assert_eq!(metadata.channels, Some(2));
+        assert_eq!(metadata.sample_rate, Some(32000));
+
+        let audio = decode(&path, 16000).unwrap();
+        assert_eq!(audio.samples.len(), 16000);
+        assert!(audio.samples.iter().all(|s| (*s - 4000).abs() <= 1));
+        assert_eq!(audio.slice(0.25, 0.5).len(), 4000);
+        assert!(audio.slice(0.9, 5.0).len() == 1600);
+
+        let part = dir.path().join("part.wav");
+        write_wav(&part, audio.slice(0.0, 0.5), 16000).unwrap();
+        assert_eq!(decode(&part, 16000).unwrap().samples.len(), 8000);
+    }
+
+    #[test]
+    fn finds_silences_longer_than_the_minimum() {
+        let mut samples = vec![6000i16; 16000];
+        samples.extend(vec![10i16; 8000]);
+        samples.extend(vec![6000i16; 16000]);
+        samples.extend(vec![0i16; 1600]);
+        samples.extend(vec![6000i16; 8000]);
+        samples.extend(vec![0i16; 16000]);
+        let audio = DecodedAudio { samples, sample_rate: 16000 };
+
+        // 0.1 秒的停顿短于 min_duration，末尾的静音延续到结尾
+        let silences = detect_silences(&audio, -30.0, 0.3);
+        let expected = [(1.0, 1.5), (3.1, 4.1)];
+        assert_eq!(silences.len(), expected.len(), "{:?}", silences);
+        for ((start, end), (want_start, want_end)) in silences.iter().zip(expected.iter()) {
+            assert!((start - want_start).abs() < 1e-6 && (end - want_end).abs() < 1e-6, "{:?}", silences);
+        }
+    }
+}
@@ -101,7 +101,6 @@ impl ErrorHandler {
         F: Fn() -> Result<T>,
     {
         let mut retry_count = 0;
-        let mut last_error = None;
         
         loop {
             match f() {
@@ -114,7 +113,7 @@ impl ErrorHandler {
                     return Ok(result);
                 }
                 Err(e) => {
-                    last_error = Some(e.to_string());
+                    let last_error = e.to_string();
                     
                     // 更新错误计数
                     {
@@ -129,7 +128,7 @@ impl ErrorHandler {
                         let delay = Duration::from_secs_f64(self.retry_delay * (retry_count as f64));
                         warn!("{} - 重试 {}/{}, 延迟 {:.1}秒: {}", 
                             error_context, retry_count, self.max_retries, delay.as_secs_f64(), 
-                            last_error.as_ref().unwrap());
+                            last_error);
                         
                         thread::sleep(delay);
                         continue;
@@ -142,8 +141,8 @@ impl ErrorHandler {
                         
                         error!("{} - 重试 {}/{} 次后失败: {}", 
                             error_context, retry_count - 1, self.max_retries, 
-                            last_error.as_ref().unwrap());
-                        return Err(anyhow::anyhow!("{}: {}", error_context, last_error.unwrap()));
+                            last_error);
+                        return Err(anyhow::anyhow!("{}: {}", error_context, last_error));
                     }
                 }
             }
@@ -1,13 +1,61 @@
-use std::time::Duration;    
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use anyhow::{Result, Context};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
 
 pub fn get_file_extension(path: &str) -> Option<&str> {
-    // File utility functions
-    None
+    Path::new(path).extension().and_then(|ext| ext.to_str())
 }
+
+/// 查找可执行文件，带路径分隔符时直接检查该路径，否则在 PATH 中搜索
+pub fn find_executable(name: &str) -> Option<PathBuf> {
+    let path = Path::new(name);
+    if path.components().count() > 1 {
+        return path.is_file().then(|| path.to_path_buf());
+    }
+
+    let paths = std::env::var_os("PATH")?;
+    std::env::split_paths(&paths).find_map(|dir| {
+        let candidate = dir.join(name);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        if cfg!(windows) {
+            let exe = dir.join(format!("{}.exe", name));
+            if exe.is_file() {
+                return Some(exe);
+            }
+        }
+        None
+    })
+}
+
+/// ffmpeg 和 ffprobe 是否都在 PATH 中
 pub fn check_ffmpeg_available() -> bool {
-    // Check if FFmpeg is available in the system PATH
-    false
+    find_executable("ffmpeg").is_some() && find_executable("ffprobe").is_some()
 }
+/// 从JSON文件加载数据
+pub fn load_json_file<T: DeserializeOwned>(path: &Path) -> Result<T> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("读取JSON文件失败: {}", path.display()))?;
+    let data = serde_json::from_str(&contents)
+        .with_context(|| format!("解析JSON文件失败: {}", path.display()))?;
+    Ok(data)
+}
+
+/// 保存数据到JSON文件
+pub fn save_json_file<T: Serialize + ?Sized>(path: &Path, data: &T) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let contents = serde_json::to_string_pretty(data)?;
+    fs::write(path, contents)
+        .with_context(|| format!("写入JSON文件失败: {}", path.display()))?;
+    Ok(())
+}
+
 /// 格式化时间间隔为友好字符串
 pub fn format_time_duration(duration: &Duration) -> String {
     // 获取总秒数 - 使用 std::time::Duration 的 as_secs 方法
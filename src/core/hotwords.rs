@@ -0,0 +1,245 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+use anyhow::{Result, Context};
+use log::{info, debug};
+use serde_json::Value;
+
+/// 媒体目录下默认的热词文件名
+pub const SIDECAR_FILE: &str = "hotwords.txt";
+
+/// 默认允许的编辑距离占热词长度的比例
+const DEFAULT_MAX_DISTANCE_RATIO: f64 = 0.34;
+
+/// 英文热词达到该长度才做模糊匹配
+const MIN_FUZZY_LATIN_LEN: usize = 5;
+
+/// 中文等非拉丁热词达到该长度才做模糊匹配，三字词允许一处差异时任意两字都能匹配上
+const MIN_FUZZY_CJK_LEN: usize = 4;
+
+/// 读取热词：配置中的 hotwords 列表，加上 hotwords_file 指定的文件，
+/// 未指定文件时使用媒体目录下的 hotwords.txt。文件每行一个词，# 开头为注释。
+pub fn load_hotwords(config: &HashMap<String, Value>) -> Result<Vec<String>> {
+    let mut hotwords: Vec<String> = config.get("hotwords")
+        .and_then(|v| v.as_array())
+        .map(|list| list.iter().filter_map(|v| v.as_str()).map(str::to_string).collect())
+        .unwrap_or_default();
+
+    let file = match config.get("hotwords_file").and_then(|v| v.as_str()).filter(|s| !s.is_empty()) {
+        Some(file) => Some(PathBuf::from(file)),
+        None => config.get("media_folder")
+            .and_then(|v| v.as_str())
+            .map(|dir| PathBuf::from(dir).join(SIDECAR_FILE))
+            .filter(|path| path.is_file()),
+    };
+
+    if let Some(file) = file {
+        let contents = fs::read_to_string(&file)
+            .with_context(|| format!("无法读取热词文件: {}", file.display()))?;
+        let before = hotwords.len();
+        hotwords.extend(contents.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(str::to_string));
+        info!("从 {} 读取 {} 个热词", file.display(), hotwords.len() - before);
+    }
+
+    let mut seen = HashSet::new();
+    hotwords.retain(|w| !w.trim().is_empty() && seen.insert(w.trim().to_string()));
+    for word in hotwords.iter_mut() {
+        *word = word.trim().to_string();
+    }
+
+    Ok(hotwords)
+}
+
+/// 热词纠正：把与热词相近的片段替换为热词，用于不支持热词的服务
+#[derive(Debug, Clone)]
+pub struct HotwordCorrector {
+    hotwords: Vec<Vec<char>>,
+    native_providers: HashSet<String>,
+    max_distance_ratio: f64,
+}
+
+impl HotwordCorrector {
+    /// 创建纠正器，native_providers 中的服务已在识别时使用热词，不再纠正
+    pub fn new(hotwords: &[String], native_providers: HashSet<String>) -> Self {
+        let mut hotwords: Vec<Vec<char>> = hotwords.iter().map(|w| w.chars().collect()).collect();
+        // 长词优先，避免短词先替换掉长词的一部分
+        hotwords.sort_by_key(|w| std::cmp::Reverse(w.len()));
+
+        Self {
+            hotwords,
+            native_providers,
+            max_distance_ratio: DEFAULT_MAX_DISTANCE_RATIO,
+        }
+    }
+
+    /// 设置允许的编辑距离占热词长度的比例
+    pub fn with_max_distance_ratio(mut self, ratio: f64) -> Self {
+        self.max_distance_ratio = ratio.clamp(0.0, 0.5);
+        self
+    }
+
+    /// 该服务的结果是否需要纠正
+    pub fn applies_to(&self, provider: &str) -> bool {
+        !self.hotwords.is_empty() && !self.native_providers.contains(provider)
+    }
+
+    /// 纠正一段文本
+    pub fn correct(&self, text: &str) -> String {
+        let mut chars: Vec<char> = text.chars().collect();
+        for hotword in &self.hotwords {
+            chars = self.correct_word(&chars, hotword);
+        }
+        chars.into_iter().collect()
+    }
+
+    /// 在文本中查找与热词相近的片段并替换，比较时忽略大小写和空白
+    fn correct_word(&self, text: &[char], hotword: &[char]) -> Vec<char> {
+        let target: Vec<char> = normalize(hotword);
+        if target.is_empty() {
+            return text.to_vec();
+        }
+        // 较短的词模糊匹配容易误伤普通词语，只做精确匹配和大小写纠正
+        let latin = target.iter().all(|c| c.is_ascii());
+        let min_fuzzy_len = if latin { MIN_FUZZY_LATIN_LEN } else { MIN_FUZZY_CJK_LEN };
+        let allowed = if target.len() < min_fuzzy_len {
+            0
+        } else {
+            (target.len() as f64 * self.max_distance_ratio).floor() as usize
+        };
+        // 中文识别错误是逐字替换成同音字，允许的编辑只用于替换，只比较与热词等长的片段
+        let (min_len, max_len) = if latin {
+            (target.len().saturating_sub(allowed).max(1), target.len() + allowed)
+        } else {
+            (target.len(), target.len())
+        };
+
+        // 规范化后的字符及其在原文中的位置
+        let (normalized, positions): (Vec<char>, Vec<usize>) = text.iter()
+            .enumerate()
+            .filter(|(_, c)| !c.is_whitespace())
+            .map(|(i, c)| (lower(*c), i))
+            .unzip();
+
+        // 拉丁字母的热词不能匹配在更长的单词中间
+        let inside_word = |a: Option<&char>, b: Option<&char>| matches!((a, b), (Some(a), Some(b)) if a.is_ascii_alphanumeric() && b.is_ascii_alphanumeric());
+
+        // 所有候选：(编辑距离, 与热词的长度差, 起点, 长度)
+        let mut candidates = Vec::new();
+        for i in 0..normalized.len() {
+            // 不把首尾的标点算进匹配
+            if !normalized[i].is_alphanumeric() {
+                continue;
+            }
+            for len in min_len..=max_len.min(normalized.len() - i) {
+                if !normalized[i + len - 1].is_alphanumeric() {
+                    continue;
+                }
+                let distance = if latin {
+                    levenshtein(&normalized[i..i + len], &target)
+                } else {
+                    normalized[i..i + len].iter().zip(&target).filter(|(a, b)| a != b).count()
+                };
+                if distance > allowed {
+                    continue;
+                }
+                let (start, end) = (positions[i], positions[i + len - 1] + 1);
+                if inside_word(start.checked_sub(1).and_then(|p| text.get(p)), text.get(start))
+                    || inside_word(text.get(end), text.get(end - 1))
+                {
+                    continue;
+                }
+                candidates.push((distance, len.abs_diff(target.len()), start, end));
+            }
+        }
+
+        // 距离最小、长度最接近的优先，重叠的候选只取一个
+        candidates.sort();
+        let mut chosen: Vec<(usize, usize)> = Vec::new();
+        for (_, _, start, end) in candidates {
+            if chosen.iter().all(|&(s, e)| end <= s || start >= e) {
+                chosen.push((start, end));
+            }
+        }
+        chosen.sort();
+
+        let mut result = Vec::with_capacity(text.len());
+        let mut copied = 0;
+        for (start, end) in chosen {
+            if text[start..end] != *hotword {
+                debug!("热词纠正: {} -> {}", text[start..end].iter().collect::<String>(), hotword.iter().collect::<String>());
+            }
+            result.extend_from_slice(&text[copied..start]);
+            result.extend_from_slice(hotword);
+            copied = end;
+        }
+
+        result.extend_from_slice(&text[copied..]);
+        result
+    }
+}
+
+fn lower(c: char) -> char {
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+fn normalize(word: &[char]) -> Vec<char> {
+    word.iter().filter(|c| !c.is_whitespace()).map(|c| lower(*c)).collect()
+}
+
+/// 编辑距离，字符或词均可
+pub(crate) fn levenshtein<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            current[j + 1] = (previous[j] + cost).min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn correct(hotwords: &[&str], text: &str) -> String {
+        let hotwords: Vec<String> = hotwords.iter().map(|w| w.to_string()).collect();
+        HotwordCorrector::new(&hotwords, HashSet::new()).correct(text)
+    }
+
+    #[test]
+    fn short_cjk_hotwords_require_an_exact_match() {
+        assert_eq!(correct(&["大模型"], "这个模型很好，大模形也不错"), "这个模型很好，大模形也不错");
+        assert_eq!(correct(&["大模型"], "大模型来了"), "大模型来了");
+    }
+
+    #[test]
+    fn long_cjk_hotwords_fix_substituted_characters() {
+        assert_eq!(correct(&["机器学习"], "我们用机器雪习做分类"), "我们用机器学习做分类");
+    }
+
+    #[test]
+    fn cjk_candidates_shorter_than_the_hotword_are_rejected() {
+        assert_eq!(correct(&["机器学习"], "用机器学。"), "用机器学。");
+        assert_eq!(correct(&["自然语言处理"], "我会自然语言理。"), "我会自然语言理。");
+    }
+
+    #[test]
+    fn short_latin_hotwords_only_fix_case() {
+        assert_eq!(correct(&["Rust"], "rust and rest"), "Rust and rest");
+        assert_eq!(correct(&["Rust"], "trust rust"), "trust Rust");
+    }
+
+    #[test]
+    fn long_latin_hotwords_match_fuzzily() {
+        assert_eq!(correct(&["Kubernetes"], "deploy to kubernets today"), "deploy to Kubernetes today");
+    }
+}
@@ -1,4 +1,9 @@
 pub mod audio_extractor;
 pub mod error;
 pub mod file_utils;
-pub mod config_manager;
\ No newline at end of file
+pub mod config_manager;
+pub mod hotwords;
+pub mod credentials;
+pub mod vad;
+#[cfg(feature = "native-decode")]
+pub mod decoder;
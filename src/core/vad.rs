@@ -0,0 +1,245 @@
+//! 基于帧能量和过零率的语音活动检测
+//!
+//! 输入为16位单声道PCM，可分多次送入。每帧计算能量（dBFS）和过零率，
+//! 全部送完后以能量的低分位数估计底噪，高出底噪 margin_db 的帧视为语音；
+//! 略低于阈值但过零率高的帧（清辅音）也算作语音。语音帧之后的 hangover
+//! 时长内仍视为语音，过短的语音段被丢弃，最后向两侧扩展 padding。
+
+use serde::{Serialize, Deserialize};
+
+/// 估计底噪使用的能量分位数
+const NOISE_PERCENTILE: f64 = 0.1;
+
+/// 清辅音允许低于阈值的幅度（dB）
+const WEAK_MARGIN_DB: f64 = 6.0;
+
+/// 语音检测设置
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VadOptions {
+    /// 帧长（毫秒）
+    pub frame_ms: u32,
+    /// 高出底噪多少 dB 视为语音
+    pub margin_db: f64,
+    /// 语音阈值的下限（dBFS），避免静音录音中的底噪被当作语音
+    pub min_energy_db: f64,
+    /// 略低于阈值的帧过零率达到该值时视为清辅音
+    pub fricative_zcr: f64,
+    /// 语音结束后继续保持的时长（毫秒）
+    pub hangover_ms: u32,
+    /// 短于该时长的语音段被丢弃（毫秒）
+    pub min_speech_ms: u32,
+    /// 语音段两侧扩展的时长（毫秒）
+    pub padding_ms: u32,
+}
+
+impl Default for VadOptions {
+    fn default() -> Self {
+        Self {
+            frame_ms: 30,
+            margin_db: 12.0,
+            min_energy_db: -55.0,
+            fricative_zcr: 0.25,
+            hangover_ms: 300,
+            min_speech_ms: 200,
+            padding_ms: 150,
+        }
+    }
+}
+
+/// 语音段（秒）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeechRegion {
+    pub start: f64,
+    pub end: f64,
+}
+
+/// 一帧的特征
+#[derive(Debug, Clone, Copy)]
+struct Frame {
+    energy_db: f64,
+    zcr: f64,
+}
+
+/// 语音活动检测器
+pub struct Vad {
+    options: VadOptions,
+    sample_rate: u32,
+    frame_len: usize,
+    pending: Vec<i16>,
+    frames: Vec<Frame>,
+}
+
+impl Vad {
+    /// 创建检测器，sample_rate 为输入PCM的采样率
+    pub fn new(options: VadOptions, sample_rate: u32) -> Self {
+        let frame_len = (sample_rate as usize * options.frame_ms.max(1) as usize / 1000).max(1);
+        Self {
+            options,
+            sample_rate,
+            frame_len,
+            pending: Vec::with_capacity(frame_len),
+            frames: Vec::new(),
+        }
+    }
+
+    /// 送入一段PCM
+    pub fn feed(&mut self, samples: &[i16]) {
+        let mut rest = samples;
+        while !rest.is_empty() {
+            let take = (self.frame_len - self.pending.len()).min(rest.len());
+            self.pending.extend_from_slice(&rest[..take]);
+            rest = &rest[take..];
+            if self.pending.len() == self.frame_len {
+                self.frames.push(frame_features(&self.pending));
+                self.pending.clear();
+            }
+        }
+    }
+
+    /// 已送入的时长（秒）
+    pub fn duration(&self) -> f64 {
+        (self.frames.len() * self.frame_len + self.pending.len()) as f64 / self.sample_rate as f64
+    }
+
+    /// 结束输入，返回按时间排列的语音段
+    pub fn finish(mut self) -> Vec<SpeechRegion> {
+        let total = self.duration();
+        if !self.pending.is_empty() {
+            self.frames.push(frame_features(&self.pending));
+        }
+        let frame_secs = self.frame_len as f64 / self.sample_rate as f64;
+        let frames_for = |ms: u32| ((ms as f64 / 1000.0) / frame_secs).ceil() as usize;
+
+        let threshold = speech_threshold(&self.frames, &self.options);
+        let raw: Vec<bool> = self.frames.iter()
+            .map(|f| is_speech(f, threshold, &self.options))
+            .collect();
+
+        // 按 hangover 延长后划分语音段，同时记录段内真正的语音帧数
+        let hangover = frames_for(self.options.hangover_ms);
+        let mut regions: Vec<(usize, usize, usize)> = Vec::new();
+        let mut current: Option<(usize, usize, usize)> = None;
+        let mut since_speech = usize::MAX;
+        for (idx, &speech) in raw.iter().enumerate() {
+            since_speech = if speech { 0 } else { since_speech.saturating_add(1) };
+            if since_speech <= hangover {
+                let region = current.get_or_insert((idx, idx, 0));
+                region.1 = idx + 1;
+                region.2 += speech as usize;
+            } else if let Some(region) = current.take() {
+                regions.push(region);
+            }
+        }
+        regions.extend(current);
+
+        let min_speech = frames_for(self.options.min_speech_ms);
+        let padding = self.options.padding_ms as f64 / 1000.0;
+        let mut merged: Vec<SpeechRegion> = Vec::new();
+        for (start, end, speech_frames) in regions {
+            if speech_frames < min_speech {
+                continue;
+            }
+            let region = SpeechRegion {
+                start: (start as f64 * frame_secs - padding).max(0.0),
+                end: (end as f64 * frame_secs + padding).min(total),
+            };
+            match merged.last_mut() {
+                Some(last) if region.start <= last.end => last.end = last.end.max(region.end),
+                _ => merged.push(region),
+            }
+        }
+        merged
+    }
+}
+
+/// 计算一帧的能量（dBFS）和过零率
+fn frame_features(samples: &[i16]) -> Frame {
+    let sum_sq: f64 = samples.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    let rms = (sum_sq / samples.len() as f64).sqrt();
+    let energy_db = if rms > 0.0 { 20.0 * (rms / 32768.0).log10() } else { -100.0 };
+
+    let crossings = samples.windows(2)
+        .filter(|w| (w[0] >= 0) != (w[1] >= 0))
+        .count();
+    let zcr = crossings as f64 / samples.len().max(2).saturating_sub(1) as f64;
+
+    Frame { energy_db, zcr }
+}
+
+/// 能量达到阈值，或略低于阈值但过零率高（清辅音）的帧视为语音
+fn is_speech(frame: &Frame, threshold: f64, options: &VadOptions) -> bool {
+    frame.energy_db >= threshold
+        || (frame.energy_db >= threshold - WEAK_MARGIN_DB && frame.zcr >= options.fricative_zcr)
+}
+
+/// 以能量的低分位数作为底噪，加上 margin_db 作为语音阈值
+fn speech_threshold(frames: &[Frame], options: &VadOptions) -> f64 {
+    let mut energies: Vec<f64> = frames.iter().map(|f| f.energy_db).collect();
+    energies.sort_by(|a, b| a.total_cmp(b));
+    let noise_floor = energies.get((energies.len() as f64 * NOISE_PERCENTILE) as usize)
+        .copied()
+        .unwrap_or(-100.0);
+
+    (noise_floor + options.margin_db).max(options.min_energy_db)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 16000;
+
+    fn tone(secs: f64, amplitude: f64) -> Vec<i16> {
+        (0..(secs * RATE as f64) as usize)
+            .map(|i| (amplitude * (i as f64 * 440.0 * 2.0 * std::f64::consts::PI / RATE as f64).sin()) as i16)
+            .collect()
+    }
+
+    fn quiet(secs: f64) -> Vec<i16> {
+        // 很小的噪声，交替符号
+        (0..(secs * RATE as f64) as usize).map(|i| if i % 2 == 0 { 3 } else { -3 }).collect()
+    }
+
+    fn detect(parts: &[Vec<i16>], chunk: usize) -> Vec<SpeechRegion> {
+        let samples: Vec<i16> = parts.concat();
+        let mut vad = Vad::new(VadOptions::default(), RATE);
+        for piece in samples.chunks(chunk) {
+            vad.feed(piece);
+        }
+        vad.finish()
+    }
+
+    #[test]
+    fn finds_speech_between_silences() {
+        let regions = detect(&[quiet(1.0), tone(1.0, 8000.0), quiet(2.0), tone(0.5, 8000.0), quiet(1.0)], 4096);
+        assert_eq!(regions.len(), 2);
+        assert!((regions[0].start - 0.85).abs() < 0.05, "{:?}", regions);
+        // 结束处包含 hangover 和 padding
+        assert!(regions[0].end > 2.3 && regions[0].end < 2.5, "{:?}", regions);
+        assert!((regions[1].start - 3.85).abs() < 0.05, "{:?}", regions);
+    }
+
+    #[test]
+    fn result_does_not_depend_on_chunking() {
+        let parts = [quiet(0.5), tone(0.7, 6000.0), quiet(1.5), tone(0.4, 6000.0)];
+        assert_eq!(detect(&parts, 17), detect(&parts, 16000));
+    }
+
+    #[test]
+    fn drops_short_clicks_and_silent_input() {
+        assert!(detect(&[quiet(1.0), tone(0.05, 20000.0), quiet(1.0)], 4096).is_empty());
+        assert!(detect(&[vec![0; RATE as usize * 2]], 4096).is_empty());
+    }
+
+    #[test]
+    fn noisy_fricatives_count_as_speech() {
+        let options = VadOptions::default();
+        let frames = vec![Frame { energy_db: -70.0, zcr: 0.02 }; 20];
+        let threshold = speech_threshold(&frames, &options);
+        assert_eq!(threshold, options.min_energy_db);
+        assert!(is_speech(&Frame { energy_db: threshold - 3.0, zcr: 0.4 }, threshold, &options));
+        assert!(!is_speech(&Frame { energy_db: threshold - 3.0, zcr: 0.05 }, threshold, &options));
+        assert!(!is_speech(&Frame { energy_db: threshold - 10.0, zcr: 0.4 }, threshold, &options));
+    }
+}
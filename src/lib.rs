@@ -0,0 +1,9 @@
+pub mod cli;
+pub mod config;
+pub mod core;
+pub mod asr;
+pub mod processing;
+pub mod ui;
+pub mod controller;
+pub mod logging;
+pub mod error;
\ No newline at end of file
@@ -1,58 +1,86 @@
-mod cli;
-mod config;
-mod core;
-mod asr;
-mod processing;
-mod ui;
-mod controller;
-mod logging;
-mod error;
-
 use anyhow::Context;
 use tokio::signal;
-use std::{collections::HashMap, path::PathBuf};
-use controller::ProcessorController;
-use crate::cli::parse_args;
+use std::collections::HashMap;
+use std::sync::Arc;
+use audio_processor::logging;
+use audio_processor::controller::ProcessorController;
+use audio_processor::cli::parse_args;
+use clap::parser::ValueSource;
 
-fn main() -> anyhow::Result<()> {
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
     // 解析命令行参数
-    let cli =parse_args();
+    let (cli, matches) = parse_args();
     
     // 设置日志
     logging::setup_logging(cli.log_file.as_deref())
         .context("无法设置日志系统")?;
     
     
-    // 设置代理(如果需要)
-    // Rust中设置环境变量
-    std::env::set_var("HTTP_PROXY", "http://127.0.0.1:7890");
-    std::env::set_var("HTTPS_PROXY", "http://127.0.0.1:7890");
-    
-    // 为了让reqwest库使用这些代理，还需要确保它的配置使用系统代理
-    
       // 构建配置参数字典
       let mut config_params = HashMap::new();
     
-    // 直接添加已有的值，不需要使用 if let Some 模式
-        config_params.insert("media_folder".to_string(), serde_json::to_value(&cli.media_folder)?);
-        config_params.insert("output_folder".to_string(), serde_json::to_value(&cli.output_folder)?);
-        config_params.insert("max_retries".to_string(), serde_json::to_value(cli.max_retries)?);
-        config_params.insert("max_workers".to_string(), serde_json::to_value(cli.max_workers)?);
-        config_params.insert("use_jianying_first".to_string(), serde_json::to_value(cli.use_jianying_first)?);
-        config_params.insert("use_kuaishou".to_string(), serde_json::to_value(cli.use_kuaishou)?);
-        config_params.insert("use_bcut".to_string(), serde_json::to_value(cli.use_bcut)?);
-        config_params.insert("format_text".to_string(), serde_json::to_value(cli.format_text)?);
-        config_params.insert("include_timestamps".to_string(), serde_json::to_value(cli.include_timestamps)?);
-        config_params.insert("show_progress".to_string(), serde_json::to_value(cli.show_progress)?);
-        config_params.insert("process_video".to_string(), serde_json::to_value(cli.process_video)?);
-        config_params.insert("extract_audio_only".to_string(), serde_json::to_value(cli.extract_audio_only)?);
-        config_params.insert("watch_mode".to_string(), serde_json::to_value(cli.watch_mode)?);
+        // 指定了配置文件时，只有命令行上明确给出的参数覆盖配置文件中的值
+        if let Some(path) = &cli.config {
+            anyhow::ensure!(path.is_file(), "配置文件不存在: {}", path.display());
+        }
+        let given = |id: &str| cli.config.is_none() || matches.value_source(id) == Some(ValueSource::CommandLine);
+        for (key, id, value) in [
+            ("media_folder", "media_folder", serde_json::to_value(&cli.media_folder)?),
+            ("output_folder", "output_folder", serde_json::to_value(&cli.output_folder)?),
+            ("max_retries", "max_retries", serde_json::to_value(cli.max_retries)?),
+            ("max_workers", "max_workers", serde_json::to_value(cli.max_workers)?),
+            ("asr_chain", "asr_chain", serde_json::to_value(&cli.asr_chain)?),
+            ("asr_strategy", "asr_strategy", serde_json::to_value(&cli.asr_strategy)?),
+            ("dry_run_asr", "dry_run_asr", serde_json::to_value(cli.dry_run_asr)?),
+            ("asr_cache", "no_cache", serde_json::to_value(!cli.no_cache)?),
+            ("format_text", "format_text", serde_json::to_value(cli.format_text)?),
+            ("include_timestamps", "include_timestamps", serde_json::to_value(cli.include_timestamps)?),
+            ("show_progress", "show_progress", serde_json::to_value(cli.show_progress)?),
+            ("process_video", "process_video", serde_json::to_value(cli.process_video)?),
+            ("extract_audio_only", "extract_audio_only", serde_json::to_value(cli.extract_audio_only)?),
+            ("watch_mode", "watch_mode", serde_json::to_value(cli.watch_mode)?),
+        ] {
+            if given(id) {
+                config_params.insert(key.to_string(), value);
+            }
+        }
+        if let Some(formats) = &cli.output_formats {
+            config_params.insert("output_formats".to_string(), serde_json::to_value(formats)?);
+        }
+        if let Some(threshold) = cli.review_threshold {
+            config_params.insert("review_threshold".to_string(), serde_json::to_value(threshold)?);
+        }
+        if let Some(language) = &cli.language {
+            config_params.insert("language".to_string(), serde_json::to_value(language)?);
+        }
+        if let Some(file) = &cli.credentials_file {
+            config_params.insert("credentials_file".to_string(), serde_json::to_value(file)?);
+        }
+        if let Some(file) = &cli.hotwords_file {
+            config_params.insert("hotwords_file".to_string(), serde_json::to_value(file)?);
+        }
+        if let Some(mode) = &cli.segment_mode {
+            config_params.insert("segment_mode".to_string(), serde_json::to_value(mode)?);
+        }
+        if let Some(overlap) = cli.segment_overlap {
+            config_params.insert("segment_overlap".to_string(), serde_json::to_value(overlap)?);
+        }
+        if let Some(proxy) = &cli.proxy {
+            config_params.insert("http_proxy".to_string(), serde_json::to_value(proxy)?);
+        }
+        if let Some(mode) = &cli.http_fixtures {
+            config_params.insert("http_fixtures_mode".to_string(), serde_json::to_value(mode)?);
+        }
+        if let Some(dir) = &cli.fixtures_dir {
+            config_params.insert("http_fixtures_dir".to_string(), serde_json::to_value(dir)?);
+        }
         
   
-        let controller = ProcessorController::new(
-            None,  // 没有配置文件
+        let controller = Arc::new(ProcessorController::new(
+            cli.config.as_deref(),
             Some(config_params),
-        )?;
+        )?);
 
        // 创建中断处理任务
        let controller_clone = controller.clone();
@@ -63,7 +91,9 @@ fn main() -> anyhow::Result<()> {
            }
        });
           // 启动处理
-    let processing = controller.start_processing();
+    let processing = controller.start_processing().await;
+    interrupt_handler.abort();
+    processing?;
     
       log::info!("\n程序执行完毕。");
       Ok(())
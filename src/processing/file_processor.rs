@@ -13,12 +13,15 @@ use tokio::task;
 use tokio::time;
 use anyhow::{Result, anyhow, Context};
 
-use crate::core::audio_extractor::AudioExtractor;
+use crate::core::audio_extractor::{AudioExtractor, native_decodable};
 use crate::core::file_utils::{load_json_file, save_json_file};
-use crate::core::error::AudioToolsError;
-use crate::processing::text_processor::TextProcessor;
+use crate::core::hotwords::HotwordCorrector;
+use crate::processing::text_processor::{OutputFormat, SpeakerLabels, TextProcessor, merge_overlaps, merge_overlaps_after, summarize_providers};
 use crate::processing::transcription_processor::TranscriptionProcessor;
 use crate::processing::part_manager::PartManager;
+use crate::processing::review::ReviewExporter;
+use crate::asr::base::{AudioSegment, Transcript};
+use crate::asr::language::{LanguageConfig, LanguageSetting};
 use crate::asr::utils::get_audio_duration;
 
 // 进度回调函数类型
@@ -26,12 +29,12 @@ type ProgressCallback = Arc<dyn Fn(usize, usize, Option<String>, Option<String>)
 
 /// 已处理文件记录
 #[derive(Debug, Clone, Serialize, Deserialize)]
-struct FileRecord {
-    last_processed_time: String,
-    processed_parts: Vec<usize>,
-    total_parts: usize,
-    part_stats: HashMap<String, Value>,
-    completed: bool,
+pub struct FileRecord {
+    pub last_processed_time: String,
+    pub processed_parts: Vec<usize>,
+    pub total_parts: usize,
+    pub part_stats: HashMap<String, Value>,
+    pub completed: bool,
 }
 
 impl Default for FileRecord {
@@ -73,10 +76,13 @@ pub struct FileProcessor {
     
     // 支持的文件类型
     video_extensions: Vec<String>,
+    languages: LanguageConfig,
+    review: Option<Arc<ReviewExporter>>,
 }
 
 impl FileProcessor {
     /// 创建新的文件处理器
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         media_folder: PathBuf,
         output_folder: PathBuf,
@@ -90,6 +96,9 @@ impl FileProcessor {
         include_timestamps: bool,
         max_part_time: u32,
         max_retries: u32,
+        hotwords: Option<HotwordCorrector>,
+        languages: LanguageConfig,
+        output_formats: Vec<OutputFormat>,
     ) -> Result<Self> {
         // 创建输出目录
         fs::create_dir_all(&output_folder)?;
@@ -109,12 +118,17 @@ impl FileProcessor {
         };
         
         // 创建文本处理器
-        let text_processor = Arc::new(TextProcessor::new(
+        let mut text_processor = TextProcessor::new(
             output_folder.clone(),
             format_text,
             include_timestamps,
             progress_callback.clone(),
-        ));
+        );
+        if let Some(hotwords) = hotwords {
+            text_processor = text_processor.with_hotwords(hotwords);
+        }
+        text_processor = text_processor.with_output_formats(output_formats);
+        let text_processor = Arc::new(text_processor);
         
         Ok(Self {
             media_folder,
@@ -134,8 +148,64 @@ impl FileProcessor {
             processed_record_file,
             interrupt_flag: Arc::new(Mutex::new(false)),
             video_extensions,
+            languages,
+            review: None,
         })
     }
+
+    /// 启用低置信度复核导出
+    pub fn with_review(mut self, review: ReviewExporter) -> Self {
+        self.review = Some(Arc::new(review));
+        self
+    }
+
+    /// 导出复核清单，失败只记录警告
+    fn export_review(&self, audio_path: &Path, name: &str, segments: &[AudioSegment], results: &HashMap<usize, Transcript>) {
+        if let Some(review) = &self.review {
+            if let Err(e) = review.export(audio_path, name, segments, results) {
+                warn!("导出复核清单失败: {}", e);
+            }
+        }
+    }
+
+    /// 确定文件的识别语言并写入各片段
+    ///
+    /// 返回写入元数据的语言说明，以及自动检测时可复用的采样结果
+    /// （只保留支持检测出的语言的服务给出的结果）。
+    fn resolve_language(&self, audio_path: &Path, segments: &mut [AudioSegment]) -> (String, HashMap<PathBuf, Transcript>) {
+        let (language, samples, label) = match self.languages.for_file(audio_path) {
+            LanguageSetting::Unspecified => (None, HashMap::new(), "未指定".to_string()),
+            LanguageSetting::Fixed(language) => (Some(language.clone()), HashMap::new(), language.clone()),
+            LanguageSetting::Auto => {
+                let (detected, samples) = self.transcription_processor
+                    .detect_language(segments, self.languages.sample_segments);
+                match detected {
+                    Some(language) => {
+                        info!("检测到语言: {} ({})", language, audio_path.display());
+                        let samples = samples.into_iter()
+                            .filter(|(_, t)| self.transcription_processor.supports_language(&t.provider, Some(&language)))
+                            .map(|(path, mut t)| {
+                                t.language = Some(language.clone());
+                                (path, t)
+                            })
+                            .collect();
+                        let label = format!("{}（自动检测）", language);
+                        (Some(language), samples, label)
+                    }
+                    None => {
+                        warn!("未能检测语言，按未指定处理: {}", audio_path.display());
+                        (None, samples, "未知（自动检测失败）".to_string())
+                    }
+                }
+            }
+        };
+
+        for segment in segments.iter_mut() {
+            segment.language = language.clone();
+        }
+
+        (label, samples)
+    }
     
     /// 设置中断标志
     pub fn set_interrupt_flag(&self, value: bool) {
@@ -193,15 +263,15 @@ impl FileProcessor {
         
         // 处理视频文件
         if self.video_extensions.iter().any(|ext| ext.trim_start_matches('.') == file_extension) {
-            return self.process_video_file(filepath);
+            self.process_video_file(filepath)
         }
-        // 处理音频文件
-        else if file_extension == "mp3" {
-            return self.process_audio_file(filepath);
+        // 处理音频文件，其他音频格式需要内置解码
+        else if file_extension == "mp3" || native_decodable(filepath) {
+            self.process_audio_file(filepath)
         }
         else {
             warn!("不支持的文件类型: {}", filename);
-            return Ok(false);
+            Ok(false)
         }
     }
 
@@ -265,14 +335,16 @@ impl FileProcessor {
         }
         
         // 处理正常大小的音频文件
-        let segment_files = self.audio_extractor.split_audio_file(audio_path)?;
+        let mut segment_files = self.audio_extractor.split_audio_file(audio_path)?;
         if segment_files.is_empty() {
-            error!("分割音频失败: {}", filename);
-            return Ok(false);
+            return self.finish_without_speech(audio_path, audio_duration);
         }
         
+        // 确定识别语言
+        let (language, samples) = self.resolve_language(audio_path, &mut segment_files);
+        
         // 处理音频片段
-        let segment_results = self.transcription_processor.process_audio_segments(&segment_files)?;
+        let segment_results = self.transcription_processor.process_audio_segments_with(&segment_files, &samples)?;
         
         // 重试失败的片段
         let segment_results = if !segment_results.is_empty() {
@@ -281,6 +353,9 @@ impl FileProcessor {
             HashMap::new()
         };
         
+        // 去掉重叠片段之间的重复内容
+        let segment_results = merge_overlaps(&segment_files, segment_results);
+        
         // 处理转写结果，生成文本文件
         if let Some(callback) = &self.progress_callback {
             callback(0, 1, Some("准备生成文本文件...".to_string()), None);
@@ -290,15 +365,19 @@ impl FileProcessor {
         let current_time = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
         let metadata = HashMap::from([
             ("原始文件".to_string(), Value::String(filename.to_string())),
-            ("处理时间".to_string(), Value::String(current_time)),
+            ("处理时间".to_string(), Value::String(current_time.clone())),
             ("识别成功率".to_string(), Value::String(format!("{}/{} 片段", segment_results.len(), segment_files.len()))),
-            ("音频长度".to_string(), Value::String(format!("{}秒", segment_files.len() * 30))),
+            ("音频长度".to_string(), Value::String(format!("{:.0}秒", audio_duration))),
+            ("识别服务".to_string(), Value::String(summarize_providers(&segment_results))),
+            ("语言".to_string(), Value::String(language)),
         ]);
         
         // 准备文本内容
+        let speakers = SpeakerLabels::collect(&segment_files, &segment_results);
         let result_text = self.text_processor.prepare_result_text(
             &segment_files,
             &segment_results,
+            &speakers,
             Some(&metadata)
         )?;
         
@@ -325,6 +404,15 @@ impl FileProcessor {
         
         info!("转写结果已保存到: {}", output_file.display());
         
+        // 保存字幕和JSON
+        for file in self.text_processor.save_extra_outputs(&segment_files, &segment_results, &speakers, Some(&metadata), filename, None)? {
+            info!("已保存: {}", file.display());
+        }
+        
+        // 导出低置信度复核清单，需在删除音频前截取
+        let stem = audio_path.file_stem().and_then(|s| s.to_str()).unwrap_or(filename);
+        self.export_review(audio_path, stem, &segment_files, &segment_results);
+        
         // 更新处理记录
         {
             let mut processed_audio = self.processed_audio.lock().unwrap();
@@ -352,6 +440,69 @@ impl FileProcessor {
     }
 
 
+    /// 语音检测没有找到语音时，只输出带元数据的文本并记为已处理，避免每次运行都重新检测
+    fn finish_without_speech(&self, audio_path: &Path, audio_duration: f64) -> Result<bool> {
+        let filename = audio_path.file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("未知文件");
+        warn!("未检测到语音: {}", filename);
+
+        let current_time = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        let metadata = HashMap::from([
+            ("原始文件".to_string(), Value::String(filename.to_string())),
+            ("处理时间".to_string(), Value::String(current_time.clone())),
+            ("识别成功率".to_string(), Value::String("0/0 片段".to_string())),
+            ("音频长度".to_string(), Value::String(format!("{:.0}秒", audio_duration))),
+        ]);
+        let text = self.text_processor.prepare_empty_text(&metadata, "（未检测到语音）");
+        let output_file = self.text_processor.save_result_text(&text, filename, None)?;
+        info!("转写结果已保存到: {}", output_file.display());
+
+        {
+            let mut processed_audio = self.processed_audio.lock().unwrap();
+            let record = processed_audio.entry(audio_path.to_string_lossy().to_string()).or_default();
+            record.last_processed_time = current_time;
+            record.completed = true;
+        }
+        self.save_processed_records()?;
+
+        if audio_path.exists() {
+            fs::remove_file(audio_path)?;
+            info!("删除音频文件: {}", audio_path.display());
+        }
+
+        Ok(true)
+    }
+
+    /// 紧挨在 first 之前并与它重叠的片段及其识别结果
+    ///
+    /// carried 是上一个处理的part留下的；不是这个片段时（上一个part在之前的运行中已完成）
+    /// 重新识别它，启用识别缓存时通常直接命中。
+    fn preceding_result(
+        &self,
+        segment_files: &[AudioSegment],
+        first: &AudioSegment,
+        carried: Option<(AudioSegment, Transcript)>,
+        samples: &HashMap<PathBuf, Transcript>,
+    ) -> Option<(AudioSegment, Transcript)> {
+        let position = segment_files.iter().position(|s| s.path == first.path)?;
+        let prev = &segment_files[position.checked_sub(1)?];
+        if prev.offset + prev.duration.unwrap_or(0.0) <= first.offset {
+            return None;
+        }
+        if let Some((segment, transcript)) = carried {
+            if segment.path == prev.path {
+                return Some((segment, transcript));
+            }
+        }
+
+        debug!("重新识别上一个part的最后一个片段 {}，用于去掉重叠内容", prev.file_name());
+        let mut results = self.transcription_processor
+            .process_audio_segments_with(std::slice::from_ref(prev), samples)
+            .ok()?;
+        Some((prev.clone(), results.remove(&0)?))
+    }
+
     /// 处理大音频文件
     fn process_large_audio_file(&self, audio_path: &Path, audio_duration: f64) -> Result<bool> {
         let filename = audio_path.file_name()
@@ -362,7 +513,7 @@ impl FileProcessor {
               filename, audio_duration / 60.0);
               
         // 创建Part管理器
-        let part_manager = PartManager::new(&self.output_folder);
+        let part_manager = PartManager::new(&self.output_folder, self.max_part_time);
         
         // 获取part信息和待处理part
         let (file_record, pending_parts) = {
@@ -370,7 +521,7 @@ impl FileProcessor {
             part_manager.get_parts_for_audio(
                 audio_path, 
                 audio_duration, 
-                &mut *processed_audio
+                &mut processed_audio
             )
         };
         
@@ -381,7 +532,7 @@ impl FileProcessor {
             // 创建索引文件
             {
                 let processed_audio = self.processed_audio.lock().unwrap();
-                let index_file = part_manager.create_index_file(audio_path, &*processed_audio)?;
+                let index_file = part_manager.create_index_file(audio_path, &processed_audio)?;
                 info!("创建索引文件: {}", index_file.display());
             }
             
@@ -390,12 +541,36 @@ impl FileProcessor {
         }
         
         // 分割音频为片段
-        let segment_files = self.audio_extractor.split_audio_file(audio_path)?;
+        let mut segment_files = self.audio_extractor.split_audio_file(audio_path)?;
         if segment_files.is_empty() {
-            error!("分割音频失败: {}", filename);
-            return Ok(false);
+            return self.finish_without_speech(audio_path, audio_duration);
         }
         
+        // 确定识别语言，整个文件只检测一次
+        let (language, samples) = self.resolve_language(audio_path, &mut segment_files);
+        
+        // 说话人编号在各part之间保持一致
+        let mut speakers = SpeakerLabels::default();
+
+        // 上一个part的最后一个片段及其结果，用来去掉part边界处重叠的内容
+        let mut previous: Option<(AudioSegment, Transcript)> = None;
+
+        // part文本的元数据
+        let metadata_for = |part_idx: usize, providers: String| {
+            let (start_time, end_time) = part_manager.get_part_time_range(part_idx);
+            let current_time = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+            HashMap::from([
+                ("原始文件".to_string(), Value::String(filename.to_string())),
+                ("Part编号".to_string(), Value::String(format!("{}/{}", part_idx + 1, file_record.total_parts))),
+                ("时间范围".to_string(), Value::String(format!("{:.1}-{:.1}分钟", 
+                                            start_time / 60.0, 
+                                            (end_time.min(audio_duration)) / 60.0))),
+                ("处理时间".to_string(), Value::String(current_time)),
+                ("识别服务".to_string(), Value::String(providers)),
+                ("语言".to_string(), Value::String(language.clone())),
+            ])
+        };
+
         // 依次处理每个pending的part
         let total_pending = pending_parts.len();
         for (i, part_idx) in pending_parts.iter().enumerate() {
@@ -425,9 +600,22 @@ impl FileProcessor {
                     None
                 );
             }
+
+            // VAD 模式下整个part可能都没有语音，同样保存并记为已处理，以后不必重新切分整个文件
+            if part_segments.is_empty() {
+                let text = self.text_processor.prepare_empty_text(&metadata_for(*part_idx, String::new()), "（未检测到语音）");
+                let output_file = {
+                    let mut processed_audio = self.processed_audio.lock().unwrap();
+                    part_manager.save_part_text(audio_path, *part_idx, &text, &mut processed_audio)?
+                };
+                info!("Part {} 未检测到语音，已保存: {}", part_idx + 1, output_file.display());
+                self.save_processed_records()?;
+                previous = None;
+                continue;
+            }
             
             // 处理这个part的所有片段
-            let segment_results = self.transcription_processor.process_audio_segments(&part_segments)?;
+            let segment_results = self.transcription_processor.process_audio_segments_with(&part_segments, &samples)?;
             
             // 重试失败的片段
             let segment_results = if !segment_results.is_empty() {
@@ -435,22 +623,24 @@ impl FileProcessor {
             } else {
                 HashMap::new()
             };
+            let previous_result = part_segments.first()
+                .and_then(|first| self.preceding_result(&segment_files, first, previous.take(), &samples));
+            let segment_results = merge_overlaps_after(
+                previous_result.as_ref().map(|(segment, transcript)| (segment, transcript)),
+                &part_segments,
+                segment_results,
+            );
+            previous = part_segments.len().checked_sub(1)
+                .and_then(|last| Some((part_segments[last].clone(), segment_results.get(&last)?.clone())));
             
             // 准备part的文本内容
-            let (start_time, end_time) = part_manager.get_part_time_range(*part_idx);
-            let current_time = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-            let part_metadata = HashMap::from([
-                ("原始文件".to_string(), Value::String(filename.to_string())),
-                ("Part编号".to_string(), Value::String(format!("{}/{}", part_idx + 1, file_record.total_parts))),
-                ("时间范围".to_string(), Value::String(format!("{:.1}-{:.1}分钟", 
-                                            start_time / 60.0, 
-                                            (end_time.min(audio_duration)) / 60.0))),
-                ("处理时间".to_string(), Value::String(current_time)),
-            ]);
+            let part_metadata = metadata_for(*part_idx, summarize_providers(&segment_results));
             
+            speakers.extend(&part_segments, &segment_results);
             let part_text = self.text_processor.prepare_result_text(
                 &part_segments,
                 &segment_results,
+                &speakers,
                 Some(&part_metadata)
             )?;
             
@@ -462,12 +652,20 @@ impl FileProcessor {
                         audio_path, 
                         *part_idx, 
                         &part_text, 
-                        &mut *processed_audio
+                        &mut processed_audio
                     )?
                 };
                 
                 info!("Part {} 转写结果已保存: {}", part_idx + 1, output_file.display());
                 
+                let suffix = format!("_part{:02}", part_idx + 1);
+                for file in self.text_processor.save_extra_outputs(&part_segments, &segment_results, &speakers, Some(&part_metadata), filename, Some(&suffix))? {
+                    info!("已保存: {}", file.display());
+                }
+                
+                let stem = audio_path.file_stem().and_then(|s| s.to_str()).unwrap_or(filename);
+                self.export_review(audio_path, &format!("{}{}", stem, suffix), &part_segments, &segment_results);
+                
                 // 保存进度
                 self.save_processed_records()?;
             } else {
@@ -489,7 +687,7 @@ impl FileProcessor {
             // 创建索引文件
             {
                 let processed_audio = self.processed_audio.lock().unwrap();
-                let index_file = part_manager.create_index_file(audio_path, &*processed_audio)?;
+                let index_file = part_manager.create_index_file(audio_path, &processed_audio)?;
                 info!("所有Part处理完成，创建索引文件: {}", index_file.display());
             }
         }
@@ -702,8 +900,8 @@ impl FileWatcher {
         // 保存监控器实例
         self.watcher = Some(watcher);
         
-        // 克隆自身引用用于异步任务
-        let self_ref = Arc::new(self);
+        // 克隆共享状态用于异步任务
+        let self_ref = Arc::new(self.event_handler());
         
         // 异步任务监听事件
         task::spawn(async move {
@@ -715,8 +913,22 @@ impl FileWatcher {
         Ok(())
     }
     
+    // 创建共享状态的事件处理副本（不持有监控器）
+    fn event_handler(&self) -> Self {
+        Self {
+            processor: Arc::clone(&self.processor),
+            media_folder: self.media_folder.clone(),
+            audio_extensions: self.audio_extensions.clone(),
+            processed_files: Arc::clone(&self.processed_files),
+            pending_files: Arc::clone(&self.pending_files),
+            debounce_seconds: self.debounce_seconds,
+            watcher: None,
+            watcher_running: Arc::clone(&self.watcher_running),
+        }
+    }
+    
     // 停止文件监控
-    fn stop(&mut self) -> Result<()> {
+    pub fn stop(&mut self) -> Result<()> {
         if let Some(watcher) = self.watcher.take() {
             drop(watcher);
             
@@ -739,9 +951,11 @@ impl FileProcessor {
         watcher.start().await?;
         Ok(watcher)
     }
-    
-    // 为了支持克隆
-    pub fn clone(&self) -> Self {
+}
+
+// 为了支持克隆
+impl Clone for FileProcessor {
+    fn clone(&self) -> Self {
         Self {
             media_folder: self.media_folder.clone(),
             output_folder: self.output_folder.clone(),
@@ -760,6 +974,8 @@ impl FileProcessor {
             processed_record_file: self.processed_record_file.clone(),
             interrupt_flag: Arc::clone(&self.interrupt_flag),
             video_extensions: self.video_extensions.clone(),
+            languages: self.languages.clone(),
+            review: self.review.clone(),
         }
     }
-}
\ No newline at end of file
+}
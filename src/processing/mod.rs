@@ -2,4 +2,5 @@ pub mod file_processor;
 pub mod text_processor;
 pub mod transcription_processor;
 pub mod part_manager;
+pub mod review;
 pub mod progress_manager;
\ No newline at end of file
@@ -1,3 +1,211 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use chrono::Local;
+use log::{info, debug};
+
+use crate::asr::base::AudioSegment;
+use crate::processing::file_processor::FileRecord;
+
+/// Part管理器，负责把大音频文件拆成多个part分批处理
 pub struct PartManager {
-    // Audio part management functionality
-}
\ No newline at end of file
+    output_folder: PathBuf,
+
+    /// 每个part的时长（秒）
+    part_duration: f64,
+}
+
+impl PartManager {
+    /// 创建新的Part管理器，max_part_time 单位为分钟
+    pub fn new(output_folder: &Path, max_part_time: u32) -> Self {
+        Self {
+            output_folder: output_folder.to_path_buf(),
+            part_duration: max_part_time.max(1) as f64 * 60.0,
+        }
+    }
+
+    /// 获取音频的处理记录和待处理的part列表
+    pub fn get_parts_for_audio(
+        &self,
+        audio_path: &Path,
+        audio_duration: f64,
+        records: &mut HashMap<String, FileRecord>,
+    ) -> (FileRecord, Vec<usize>) {
+        let key = audio_path.to_string_lossy().to_string();
+        let total_parts = (audio_duration / self.part_duration).ceil().max(1.0) as usize;
+
+        let record = records.entry(key).or_default();
+        if record.total_parts != total_parts {
+            debug!("更新part数量: {} -> {}", record.total_parts, total_parts);
+            record.total_parts = total_parts;
+            record.processed_parts.retain(|idx| *idx < total_parts);
+        }
+
+        let pending = (0..total_parts)
+            .filter(|idx| !record.processed_parts.contains(idx))
+            .collect();
+
+        (record.clone(), pending)
+    }
+
+    /// 获取part的时间范围（秒）
+    pub fn get_part_time_range(&self, part_idx: usize) -> (f64, f64) {
+        let start = part_idx as f64 * self.part_duration;
+        (start, start + self.part_duration)
+    }
+
+    /// 获取属于指定part的片段，按片段自身内容的起点划分，向前重叠的部分不算
+    pub fn get_segments_for_part(&self, part_idx: usize, segments: &[AudioSegment]) -> Vec<AudioSegment> {
+        let (start, end) = self.get_part_time_range(part_idx);
+
+        segments.iter()
+            .filter(|s| s.content_start() >= start && s.content_start() < end)
+            .cloned()
+            .collect()
+    }
+
+    /// part文本文件路径
+    fn part_text_path(&self, audio_path: &Path, part_idx: usize) -> PathBuf {
+        let stem = audio_path.file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("audio");
+
+        self.output_folder.join(format!("{}_part{:02}.txt", stem, part_idx + 1))
+    }
+
+    /// 保存part文本并更新处理记录
+    pub fn save_part_text(
+        &self,
+        audio_path: &Path,
+        part_idx: usize,
+        text: &str,
+        records: &mut HashMap<String, FileRecord>,
+    ) -> Result<PathBuf> {
+        let output_file = self.part_text_path(audio_path, part_idx);
+
+        fs::create_dir_all(&self.output_folder)?;
+        fs::write(&output_file, text)
+            .with_context(|| format!("写入part文本失败: {}", output_file.display()))?;
+
+        let key = audio_path.to_string_lossy().to_string();
+        let record = records.entry(key).or_default();
+        if !record.processed_parts.contains(&part_idx) {
+            record.processed_parts.push(part_idx);
+            record.processed_parts.sort_unstable();
+        }
+        record.last_processed_time = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+        record.completed = record.total_parts > 0 && record.processed_parts.len() >= record.total_parts;
+
+        Ok(output_file)
+    }
+
+    /// 创建索引文件，列出所有part文本
+    pub fn create_index_file(&self, audio_path: &Path, records: &HashMap<String, FileRecord>) -> Result<PathBuf> {
+        let key = audio_path.to_string_lossy().to_string();
+        let record = records.get(&key).cloned().unwrap_or_default();
+
+        let stem = audio_path.file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("audio");
+        let index_file = self.output_folder.join(format!("{}_index.txt", stem));
+
+        let mut content = format!("原始文件: {}\n总part数: {}\n\n", stem, record.total_parts);
+        for part_idx in 0..record.total_parts {
+            let (start, end) = self.get_part_time_range(part_idx);
+            let status = if record.processed_parts.contains(&part_idx) { "已完成" } else { "未完成" };
+            let part_file = self.part_text_path(audio_path, part_idx);
+
+            content.push_str(&format!(
+                "Part {:02} [{:.1}-{:.1}分钟] {}: {}\n",
+                part_idx + 1,
+                start / 60.0,
+                end / 60.0,
+                status,
+                part_file.file_name().unwrap_or_default().to_string_lossy(),
+            ));
+        }
+
+        fs::write(&index_file, content)
+            .with_context(|| format!("写入索引文件失败: {}", index_file.display()))?;
+
+        info!("索引文件已更新: {}", index_file.display());
+
+        Ok(index_file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segments(offsets: &[f64]) -> Vec<AudioSegment> {
+        offsets.iter()
+            .enumerate()
+            .map(|(idx, offset)| AudioSegment::new(Path::new(&format!("part_{:04}.mp3", idx)), *offset, Some(30.0)))
+            .collect()
+    }
+
+    #[test]
+    fn parts_cover_the_whole_audio_and_skip_processed_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = PartManager::new(dir.path(), 30);
+        let audio = Path::new("/media/talk.mp3");
+        let mut records = HashMap::new();
+
+        let (record, pending) = manager.get_parts_for_audio(audio, 65.0 * 60.0, &mut records);
+        assert_eq!(record.total_parts, 3);
+        assert_eq!(pending, vec![0, 1, 2]);
+
+        records.get_mut("/media/talk.mp3").unwrap().processed_parts = vec![0, 2];
+        let (_, pending) = manager.get_parts_for_audio(audio, 65.0 * 60.0, &mut records);
+        assert_eq!(pending, vec![1]);
+
+        // 时长变短后超出范围的part记录被丢掉
+        let (record, pending) = manager.get_parts_for_audio(audio, 50.0 * 60.0, &mut records);
+        assert_eq!(record.total_parts, 2);
+        assert_eq!(record.processed_parts, vec![0]);
+        assert_eq!(pending, vec![1]);
+    }
+
+    #[test]
+    fn segments_belong_to_the_part_containing_their_start() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = PartManager::new(dir.path(), 1);
+        let all = segments(&[0.0, 30.0, 60.0, 90.0, 120.0]);
+
+        let offsets = |part_idx| manager.get_segments_for_part(part_idx, &all).iter().map(|s| s.offset).collect::<Vec<_>>();
+        assert_eq!(offsets(0), vec![0.0, 30.0]);
+        assert_eq!(offsets(1), vec![60.0, 90.0]);
+        assert_eq!(offsets(2), vec![120.0]);
+        assert!(offsets(3).is_empty());
+
+        // 从part边界开始、向前重叠 2 秒的片段属于后一个part
+        let overlapped = vec![
+            AudioSegment::new(Path::new("part_0000.mp3"), 0.0, Some(60.0)),
+            AudioSegment::new(Path::new("part_0001.mp3"), 58.0, Some(32.0)).with_overlap(2.0),
+        ];
+        assert_eq!(manager.get_segments_for_part(0, &overlapped).len(), 1);
+        assert_eq!(manager.get_segments_for_part(1, &overlapped)[0].offset, 58.0);
+    }
+
+    #[test]
+    fn saving_the_last_part_completes_the_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = PartManager::new(dir.path(), 30);
+        let audio = Path::new("/media/talk.mp3");
+        let mut records = HashMap::new();
+        manager.get_parts_for_audio(audio, 45.0 * 60.0, &mut records);
+
+        let file = manager.save_part_text(audio, 1, "第二部分", &mut records).unwrap();
+        assert_eq!(file, dir.path().join("talk_part02.txt"));
+        assert_eq!(fs::read_to_string(&file).unwrap(), "第二部分");
+        assert!(!records["/media/talk.mp3"].completed);
+
+        manager.save_part_text(audio, 0, "第一部分", &mut records).unwrap();
+        manager.save_part_text(audio, 0, "第一部分", &mut records).unwrap();
+        let record = &records["/media/talk.mp3"];
+        assert_eq!(record.processed_parts, vec![0, 1]);
+        assert!(record.completed);
+    }
+}
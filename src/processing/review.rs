@@ -0,0 +1,273 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use anyhow::{Result, Context};
+use log::{info, warn};
+
+use crate::asr::base::{AudioSegment, Transcript, Utterance};
+use crate::core::audio_extractor::{AudioExtractor, snippet_extension};
+use crate::processing::text_processor::format_subtitle_time;
+
+/// 相邻的低置信度词间隔小于该值（秒）时合并为一段
+const MERGE_GAP_SECS: f64 = 1.0;
+
+/// 待人工复核的一段，时间相对于原始音频（秒）
+#[derive(Debug, Clone)]
+pub struct ReviewSpan {
+    pub start: f64,
+    pub end: f64,
+    pub confidence: f32,
+    pub text: String,
+}
+
+/// 复核导出配置
+#[derive(Debug, Clone)]
+pub struct ReviewOptions {
+    /// 置信度低于该值的句子或词需要复核
+    pub threshold: f32,
+    /// 复核清单和音频的输出目录
+    pub dir: PathBuf,
+    /// 截取音频时前后多留的时长（秒）
+    pub padding: f64,
+    /// 是否截取音频
+    pub snippets: bool,
+}
+
+/// 低置信度复核导出
+///
+/// 整句置信度低于阈值时整句列出；整句置信度合格或未知时，
+/// 把连续的低置信度词合并为一段列出。服务没有给出置信度的内容不列出。
+pub struct ReviewExporter {
+    options: ReviewOptions,
+    audio_extractor: Arc<AudioExtractor>,
+}
+
+impl ReviewExporter {
+    /// 创建复核导出器
+    pub fn new(options: ReviewOptions, audio_extractor: Arc<AudioExtractor>) -> Self {
+        Self { options, audio_extractor }
+    }
+
+    /// 找出所有低置信度的片段，按置信度从低到高排列
+    pub fn collect_spans(&self, segments: &[AudioSegment], results: &HashMap<usize, Transcript>) -> Vec<ReviewSpan> {
+        let mut spans = Vec::new();
+
+        for (idx, segment) in segments.iter().enumerate() {
+            let transcript = match results.get(&idx) {
+                Some(transcript) => transcript,
+                None => continue,
+            };
+            for utterance in &transcript.utterances {
+                spans.extend(self.utterance_spans(segment.offset, utterance));
+            }
+        }
+
+        spans.sort_by(|a, b| a.confidence.partial_cmp(&b.confidence)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then(a.start.partial_cmp(&b.start).unwrap_or(std::cmp::Ordering::Equal)));
+        spans
+    }
+
+    fn utterance_spans(&self, offset: f64, utterance: &Utterance) -> Vec<ReviewSpan> {
+        if let Some(confidence) = utterance.confidence.filter(|c| *c < self.options.threshold) {
+            return vec![ReviewSpan {
+                start: offset + utterance.start,
+                end: offset + utterance.end,
+                confidence,
+                text: utterance.text.clone(),
+            }];
+        }
+
+        // 合并后的片段连同第一个词的下标，文本包括夹在中间的正常词，与截取的音频一致
+        let mut spans: Vec<(usize, ReviewSpan)> = Vec::new();
+        for (idx, word) in utterance.words.iter().enumerate() {
+            let confidence = match word.confidence.filter(|c| *c < self.options.threshold) {
+                Some(confidence) => confidence,
+                None => continue,
+            };
+            let (start, end) = (offset + word.start, offset + word.end);
+
+            match spans.last_mut() {
+                Some((first, last)) if start - last.end < MERGE_GAP_SECS => {
+                    last.end = end;
+                    last.confidence = last.confidence.min(confidence);
+                    last.text.clear();
+                    for word in &utterance.words[*first..=idx] {
+                        join_word(&mut last.text, &word.text);
+                    }
+                }
+                _ => spans.push((idx, ReviewSpan { start, end, confidence, text: word.text.clone() })),
+            }
+        }
+        spans.into_iter().map(|(_, span)| span).collect()
+    }
+
+    /// 导出一个文件的复核清单，audio_file 为截取音频用的原始音频；
+    /// 没有低置信度内容时不写文件，返回 None
+    pub fn export(
+        &self,
+        audio_file: &Path,
+        name: &str,
+        segments: &[AudioSegment],
+        results: &HashMap<usize, Transcript>,
+    ) -> Result<Option<PathBuf>> {
+        let spans = self.collect_spans(segments, results);
+        if spans.is_empty() {
+            return Ok(None);
+        }
+
+        fs::create_dir_all(&self.options.dir)?;
+        let snippet_dir = self.options.dir.join(name);
+        let mut snippets_enabled = self.options.snippets;
+
+        let mut lines = vec!["文件\t开始\t结束\t置信度\t文本\t音频".to_string()];
+        for (rank, span) in spans.iter().enumerate() {
+            let mut snippet = String::new();
+
+            if snippets_enabled {
+                let file_name = format!(
+                    "{:03}_{}.{}",
+                    rank + 1,
+                    format_subtitle_time(span.start, true).replace([':', '.'], "-"),
+                    snippet_extension(audio_file),
+                );
+                let output = snippet_dir.join(&file_name);
+                let result = fs::create_dir_all(&snippet_dir).map_err(anyhow::Error::from).and_then(|_| {
+                    self.audio_extractor.cut_audio(
+                        audio_file,
+                        span.start - self.options.padding,
+                        span.end + self.options.padding,
+                        &output,
+                    )
+                });
+                match result {
+                    Ok(()) => snippet = format!("{}/{}", name, file_name),
+                    Err(e) => {
+                        // 通常是缺少 ffmpeg，后面的片段也不会成功
+                        warn!("截取复核音频失败，只导出清单: {}", e);
+                        snippets_enabled = false;
+                    }
+                }
+            }
+
+            lines.push(format!("{}\t{}\t{}\t{:.2}\t{}\t{}",
+                audio_file.file_name().map(|n| n.to_string_lossy()).unwrap_or_default(),
+                format_subtitle_time(span.start, true),
+                format_subtitle_time(span.end, true),
+                span.confidence,
+                span.text.replace(['\t', '\n'], " "),
+                snippet));
+        }
+
+        let list_file = self.options.dir.join(format!("{}.tsv", name));
+        fs::write(&list_file, lines.join("\n") + "\n")
+            .with_context(|| format!("写入复核清单失败: {}", list_file.display()))?;
+
+        info!("{} 处低置信度内容待复核: {}", spans.len(), list_file.display());
+        Ok(Some(list_file))
+    }
+}
+
+/// 拼接词，拉丁字母的词之间加空格
+fn join_word(text: &mut String, word: &str) {
+    let needs_space = text.chars().last().map(|c| c.is_ascii_alphanumeric()).unwrap_or(false)
+        && word.chars().next().map(|c| c.is_ascii_alphanumeric()).unwrap_or(false);
+    if needs_space {
+        text.push(' ');
+    }
+    text.push_str(word);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asr::base::Word;
+
+    fn exporter(dir: &Path) -> ReviewExporter {
+        let options = ReviewOptions { threshold: 0.6, dir: dir.to_path_buf(), padding: 0.5, snippets: false };
+        ReviewExporter::new(options, Arc::new(AudioExtractor::new(dir, None)))
+    }
+
+    fn word(start: f64, end: f64, text: &str, confidence: Option<f32>) -> Word {
+        Word { start, end, text: text.to_string(), confidence }
+    }
+
+    fn utterance(start: f64, end: f64, text: &str, confidence: Option<f32>, words: Vec<Word>) -> Utterance {
+        let mut utterance = Utterance::new(start, end, text);
+        utterance.confidence = confidence;
+        utterance.words = words;
+        utterance
+    }
+
+    fn results(utterances: Vec<Utterance>) -> (Vec<AudioSegment>, HashMap<usize, Transcript>) {
+        let segments = vec![AudioSegment::new(Path::new("part_0000.mp3"), 30.0, Some(30.0))];
+        let mut results = HashMap::new();
+        results.insert(0, Transcript::new("test", utterances));
+        (segments, results)
+    }
+
+    #[test]
+    fn low_confidence_utterance_is_listed_whole() {
+        let dir = tempfile::tempdir().unwrap();
+        let (segments, results) = results(vec![
+            utterance(1.0, 3.0, "听不清的一句", Some(0.4), vec![word(1.0, 2.0, "听不清", Some(0.9))]),
+            utterance(3.0, 4.0, "清楚的一句", Some(0.95), Vec::new()),
+        ]);
+
+        let spans = exporter(dir.path()).collect_spans(&segments, &results);
+        assert_eq!(spans.len(), 1);
+        assert_eq!((spans[0].start, spans[0].end), (31.0, 33.0));
+        assert_eq!(spans[0].text, "听不清的一句");
+        assert_eq!(spans[0].confidence, 0.4);
+    }
+
+    #[test]
+    fn nearby_low_confidence_words_are_merged() {
+        let dir = tempfile::tempdir().unwrap();
+        let (segments, results) = results(vec![utterance(0.0, 5.0, "hello there world 说得清楚，这里模糊没有", Some(0.9), vec![
+            word(0.0, 0.4, "hello", Some(0.5)),
+            word(0.4, 0.7, "there", Some(0.95)),
+            word(0.8, 1.2, "world", Some(0.3)),
+            word(1.2, 1.6, "说得", Some(0.9)),
+            word(1.6, 2.0, "清楚", Some(0.9)),
+            word(3.5, 4.0, "这里", Some(0.9)),
+            word(4.0, 4.5, "模糊", Some(0.2)),
+            word(4.5, 5.0, "没有", None),
+        ])]);
+
+        let spans = exporter(dir.path()).collect_spans(&segments, &results);
+        assert_eq!(spans.len(), 2);
+        assert_eq!((spans[0].start, spans[0].end, spans[0].text.as_str()), (34.0, 34.5, "模糊"));
+        // 中间置信度正常的 there 也在文本中
+        assert_eq!((spans[1].start, spans[1].end, spans[1].text.as_str()), (30.0, 31.2, "hello there world"));
+        assert_eq!(spans[1].confidence, 0.3);
+    }
+
+    #[test]
+    fn content_without_confidence_is_not_listed() {
+        let dir = tempfile::tempdir().unwrap();
+        let (segments, results) = results(vec![
+            utterance(0.0, 1.0, "没有置信度", None, vec![word(0.0, 1.0, "没有置信度", None)]),
+        ]);
+
+        assert!(exporter(dir.path()).collect_spans(&segments, &results).is_empty());
+        assert_eq!(exporter(dir.path()).export(Path::new("audio.mp3"), "audio", &segments, &results).unwrap(), None);
+    }
+
+    #[test]
+    fn exports_list_sorted_by_confidence() {
+        let dir = tempfile::tempdir().unwrap();
+        let (segments, results) = results(vec![
+            utterance(0.0, 1.0, "第一句", Some(0.5), Vec::new()),
+            utterance(1.0, 2.0, "第二句", Some(0.1), Vec::new()),
+        ]);
+
+        let list = exporter(dir.path()).export(Path::new("audio.mp3"), "audio", &segments, &results).unwrap().unwrap();
+        let contents = fs::read_to_string(list).unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1].starts_with("audio.mp3\t") && lines[1].contains("\t0.10\t第二句\t"));
+        assert!(lines[2].contains("\t0.50\t第一句\t"));
+    }
+}
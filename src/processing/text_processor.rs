@@ -1,3 +1,924 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use anyhow::{Result, Context};
+use log::debug;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::asr::base::{is_cjk, join_segment_texts, mean_confidence, AudioSegment, Transcript};
+use crate::core::hotwords::{levenshtein, HotwordCorrector};
+
+// 进度回调函数类型
+type ProgressCallback = Arc<dyn Fn(usize, usize, Option<String>, Option<String>) + Send + Sync>;
+
+/// 每个段落包含的句子数
+const SENTENCES_PER_PARAGRAPH: usize = 5;
+
+/// 对齐重叠文本时，前后片段各取的最多词数
+const MAX_OVERLAP_TOKENS: usize = 80;
+
+/// 至少这么多个词相同才认为是重复，避免偶然相同的单字被删掉
+const MIN_OVERLAP_TOKENS: usize = 2;
+
+/// 重复文本允许的差异比例
+const MAX_OVERLAP_DIFF: f64 = 0.25;
+
+/// 对齐重叠文本时，按句子时间筛选候选内容的余量（秒）
+const OVERLAP_SLACK: f64 = 1.0;
+
+/// 文本之外的输出格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// SRT字幕，说话人写成 [Speaker 1] 前缀
+    Srt,
+    /// WebVTT字幕，说话人写成 <v Speaker 1> 标签
+    Vtt,
+    /// 逐句的JSON
+    Json,
+}
+
+impl OutputFormat {
+    /// 从配置字符串解析格式
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "srt" => Some(OutputFormat::Srt),
+            "vtt" | "webvtt" => Some(OutputFormat::Vtt),
+            "json" => Some(OutputFormat::Json),
+            _ => None,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Srt => "srt",
+            OutputFormat::Vtt => "vtt",
+            OutputFormat::Json => "json",
+        }
+    }
+}
+
+/// 文件内统一的说话人编号
+///
+/// 按服务返回的说话人标识在文件中首次出现的顺序编号为 Speaker 1、Speaker 2……，
+/// 同一标识在整个文件（分part输出时包括所有part）中使用同一编号。
+/// 各片段分别识别，服务的标识不保证跨片段指同一个人，这里按标识原样合并。
+#[derive(Debug, Default)]
+pub struct SpeakerLabels {
+    ids: HashMap<String, usize>,
+}
+
+impl SpeakerLabels {
+    /// 为一组识别结果编号
+    pub fn collect(segments: &[AudioSegment], results: &HashMap<usize, Transcript>) -> Self {
+        let mut labels = Self::default();
+        labels.extend(segments, results);
+        labels
+    }
+
+    /// 追加新出现的说话人，已有的编号不变
+    pub fn extend(&mut self, segments: &[AudioSegment], results: &HashMap<usize, Transcript>) {
+        for idx in 0..segments.len() {
+            let transcript = match results.get(&idx) {
+                Some(transcript) => transcript,
+                None => continue,
+            };
+            for speaker in transcript.utterances.iter().filter_map(|u| u.speaker.as_ref()) {
+                let next = self.ids.len() + 1;
+                self.ids.entry(speaker.clone()).or_insert(next);
+            }
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    fn label(&self, speaker: Option<&String>) -> Option<String> {
+        speaker.and_then(|s| self.ids.get(s)).map(|n| format!("Speaker {}", n))
+    }
+
+    /// 按编号排列的所有标签
+    fn labels(&self) -> Vec<String> {
+        (1..=self.ids.len()).map(|n| format!("Speaker {}", n)).collect()
+    }
+}
+
+/// 输出JSON中的一句，时间相对于原始音频（秒）
+#[derive(Debug, Serialize)]
+struct JsonUtterance {
+    start: f64,
+    end: f64,
+    text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    speaker: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    confidence: Option<f32>,
+    provider: String,
+}
+
+/// 输出JSON
+#[derive(Debug, Serialize)]
+struct JsonTranscript<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<&'a HashMap<String, Value>>,
+    speakers: Vec<String>,
+    utterances: Vec<JsonUtterance>,
+    /// 识别失败的片段起点（秒）
+    failed_segments: Vec<f64>,
+}
+
+/// 文本处理器，负责把识别结果整理成文本文件
 pub struct TextProcessor {
-    // Text processing functionality
-}
\ No newline at end of file
+    output_folder: PathBuf,
+    format_text: bool,
+    include_timestamps: bool,
+    hotwords: Option<HotwordCorrector>,
+    output_formats: Vec<OutputFormat>,
+    progress_callback: Option<ProgressCallback>,
+}
+
+impl TextProcessor {
+    /// 创建新的文本处理器
+    pub fn new(
+        output_folder: PathBuf,
+        format_text: bool,
+        include_timestamps: bool,
+        progress_callback: Option<ProgressCallback>,
+    ) -> Self {
+        Self {
+            output_folder,
+            format_text,
+            include_timestamps,
+            hotwords: None,
+            output_formats: Vec::new(),
+            progress_callback,
+        }
+    }
+
+    /// 设置文本之外还要输出的格式
+    pub fn with_output_formats(mut self, formats: Vec<OutputFormat>) -> Self {
+        self.output_formats = formats;
+        self
+    }
+
+    /// 启用热词纠正
+    pub fn with_hotwords(mut self, hotwords: HotwordCorrector) -> Self {
+        self.hotwords = Some(hotwords);
+        self
+    }
+
+    /// 对不支持热词的服务结果做热词纠正
+    fn correct_hotwords(&self, provider: &str, text: &str) -> String {
+        match &self.hotwords {
+            Some(hotwords) if hotwords.applies_to(provider) => hotwords.correct(text),
+            _ => text.to_string(),
+        }
+    }
+
+    /// 热词纠正并清理一句文本
+    fn utterance_text(&self, provider: &str, text: &str) -> String {
+        self.clean_text(&self.correct_hotwords(provider, text))
+    }
+
+    /// 根据片段识别结果生成文本内容，没有任何结果时返回空字符串
+    pub fn prepare_result_text(
+        &self,
+        segments: &[AudioSegment],
+        results: &HashMap<usize, Transcript>,
+        speakers: &SpeakerLabels,
+        metadata: Option<&HashMap<String, Value>>,
+    ) -> Result<String> {
+        if results.is_empty() {
+            return Ok(String::new());
+        }
+
+        let mut lines = Vec::new();
+        // 不带时间戳时按说话人轮次分行：(说话人, 文本)
+        let mut turns: Vec<(Option<String>, String)> = Vec::new();
+
+        for (idx, segment) in segments.iter().enumerate() {
+            let transcript = match results.get(&idx) {
+                Some(transcript) => transcript,
+                None => {
+                    if self.include_timestamps {
+                        lines.push(format!("[{}] [识别失败]", format_timestamp(segment.offset)));
+                    }
+                    continue;
+                }
+            };
+
+            if self.include_timestamps {
+                for utterance in &transcript.utterances {
+                    let text = self.utterance_text(&transcript.provider, &utterance.text);
+                    if !text.is_empty() {
+                        let speaker = speakers.label(utterance.speaker.as_ref())
+                            .map(|label| format!("[{}] ", label))
+                            .unwrap_or_default();
+                        lines.push(format!("[{}] {}{}", format_timestamp(segment.offset + utterance.start), speaker, text));
+                    }
+                }
+            } else if !speakers.is_empty() {
+                for utterance in &transcript.utterances {
+                    let text = self.utterance_text(&transcript.provider, &utterance.text);
+                    if text.is_empty() {
+                        continue;
+                    }
+                    let speaker = speakers.label(utterance.speaker.as_ref());
+                    match turns.last_mut() {
+                        Some((last, current)) if *last == speaker => {
+                            if !self.format_text {
+                                current.push(' ');
+                            }
+                            current.push_str(&text);
+                        }
+                        _ => turns.push((speaker, text)),
+                    }
+                }
+            } else {
+                let text = self.utterance_text(&transcript.provider, &transcript.text());
+                if !text.is_empty() {
+                    lines.push(text);
+                }
+            }
+
+            if let Some(callback) = &self.progress_callback {
+                callback(idx + 1, segments.len(), Some("整理文本".to_string()), Some("文本".to_string()));
+            }
+        }
+
+        let body = if !turns.is_empty() {
+            let separator = if self.format_text { "\n\n" } else { "\n" };
+            turns.iter()
+                .map(|(speaker, text)| match speaker {
+                    Some(label) => format!("[{}] {}", label, text),
+                    None => text.clone(),
+                })
+                .collect::<Vec<_>>()
+                .join(separator)
+        } else if self.include_timestamps || !self.format_text {
+            lines.join("\n")
+        } else {
+            split_paragraphs(&join_segment_texts(&lines))
+        };
+
+        let mut content = metadata.map(format_metadata).unwrap_or_default();
+        content.push_str(&body);
+        content.push('\n');
+
+        let disagreements = format_disagreements(segments, results);
+        if !disagreements.is_empty() {
+            content.push_str("\n---\n\n识别分歧:\n");
+            content.push_str(&disagreements);
+            content.push('\n');
+        }
+
+        Ok(content)
+    }
+
+    /// 没有识别内容时的文本，只含元数据和说明
+    pub fn prepare_empty_text(&self, metadata: &HashMap<String, Value>, note: &str) -> String {
+        format!("{}{}\n", format_metadata(metadata), note)
+    }
+
+    /// 生成字幕内容
+    pub fn prepare_subtitles(
+        &self,
+        segments: &[AudioSegment],
+        results: &HashMap<usize, Transcript>,
+        speakers: &SpeakerLabels,
+        format: OutputFormat,
+    ) -> String {
+        let vtt = format == OutputFormat::Vtt;
+        let mut cues = Vec::new();
+
+        for (idx, segment) in segments.iter().enumerate() {
+            let transcript = match results.get(&idx) {
+                Some(transcript) => transcript,
+                None => continue,
+            };
+
+            for utterance in &transcript.utterances {
+                let text = self.utterance_text(&transcript.provider, &utterance.text);
+                if text.is_empty() {
+                    continue;
+                }
+                let start = format_subtitle_time(segment.offset + utterance.start, vtt);
+                let end = format_subtitle_time(segment.offset + utterance.end.max(utterance.start), vtt);
+                let text = match (speakers.label(utterance.speaker.as_ref()), vtt) {
+                    (Some(label), true) => format!("<v {}>{}", label, text),
+                    (Some(label), false) => format!("[{}] {}", label, text),
+                    (None, _) => text,
+                };
+
+                if vtt {
+                    cues.push(format!("{} --> {}\n{}", start, end, text));
+                } else {
+                    cues.push(format!("{}\n{} --> {}\n{}", cues.len() + 1, start, end, text));
+                }
+            }
+        }
+
+        let mut content = if vtt { "WEBVTT\n\n".to_string() } else { String::new() };
+        content.push_str(&cues.join("\n\n"));
+        content.push('\n');
+        content
+    }
+
+    /// 生成逐句的JSON内容
+    pub fn prepare_json(
+        &self,
+        segments: &[AudioSegment],
+        results: &HashMap<usize, Transcript>,
+        speakers: &SpeakerLabels,
+        metadata: Option<&HashMap<String, Value>>,
+    ) -> Result<String> {
+        let mut utterances = Vec::new();
+        let mut failed_segments = Vec::new();
+
+        for (idx, segment) in segments.iter().enumerate() {
+            let transcript = match results.get(&idx) {
+                Some(transcript) => transcript,
+                None => {
+                    failed_segments.push(segment.offset);
+                    continue;
+                }
+            };
+
+            for utterance in &transcript.utterances {
+                let text = self.utterance_text(&transcript.provider, &utterance.text);
+                if text.is_empty() {
+                    continue;
+                }
+                utterances.push(JsonUtterance {
+                    start: segment.offset + utterance.start,
+                    end: segment.offset + utterance.end,
+                    text,
+                    speaker: speakers.label(utterance.speaker.as_ref()),
+                    confidence: utterance.confidence,
+                    provider: transcript.provider.clone(),
+                });
+            }
+        }
+
+        Ok(serde_json::to_string_pretty(&JsonTranscript {
+            metadata,
+            speakers: speakers.labels(),
+            utterances,
+            failed_segments,
+        })?)
+    }
+
+    /// 保存文本到输出目录，返回文件路径
+    pub fn save_result_text(&self, text: &str, filename: &str, suffix: Option<&str>) -> Result<PathBuf> {
+        self.save_output(text, filename, suffix, "txt")
+    }
+
+    /// 按配置的格式保存字幕和JSON，返回保存的文件
+    pub fn save_extra_outputs(
+        &self,
+        segments: &[AudioSegment],
+        results: &HashMap<usize, Transcript>,
+        speakers: &SpeakerLabels,
+        metadata: Option<&HashMap<String, Value>>,
+        filename: &str,
+        suffix: Option<&str>,
+    ) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        if results.is_empty() {
+            return Ok(files);
+        }
+
+        for format in &self.output_formats {
+            let content = match format {
+                OutputFormat::Srt | OutputFormat::Vtt => self.prepare_subtitles(segments, results, speakers, *format),
+                OutputFormat::Json => self.prepare_json(segments, results, speakers, metadata)?,
+            };
+            files.push(self.save_output(&content, filename, suffix, format.extension())?);
+        }
+
+        Ok(files)
+    }
+
+    /// 保存到输出目录下的 文件名+后缀.扩展名
+    fn save_output(&self, content: &str, filename: &str, suffix: Option<&str>, extension: &str) -> Result<PathBuf> {
+        let stem = Path::new(filename)
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or(filename);
+
+        let output_file = self.output_folder.join(format!("{}{}.{}", stem, suffix.unwrap_or(""), extension));
+
+        fs::create_dir_all(&self.output_folder)?;
+        fs::write(&output_file, content)
+            .with_context(|| format!("写入文件失败: {}", output_file.display()))?;
+
+        debug!("已保存: {}", output_file.display());
+
+        Ok(output_file)
+    }
+
+    /// 清理识别文本
+    fn clean_text(&self, text: &str) -> String {
+        let text = text.trim();
+        if !self.format_text {
+            return text.to_string();
+        }
+
+        // 去掉中文字符之间多余的空格
+        let chars: Vec<char> = text.chars().collect();
+        let mut cleaned = String::with_capacity(text.len());
+        for (i, c) in chars.iter().enumerate() {
+            if c.is_whitespace() {
+                let prev_cjk = i > 0 && is_cjk(chars[i - 1]);
+                let next_cjk = chars.get(i + 1).map(|n| is_cjk(*n)).unwrap_or(false);
+                if prev_cjk || next_cjk || cleaned.ends_with(' ') {
+                    continue;
+                }
+            }
+            cleaned.push(*c);
+        }
+
+        cleaned
+    }
+}
+
+/// 格式化时间戳为 HH:MM:SS
+pub fn format_timestamp(seconds: f64) -> String {
+    let total = seconds.max(0.0) as u64;
+    format!("{:02}:{:02}:{:02}", total / 3600, (total % 3600) / 60, total % 60)
+}
+
+/// 格式化字幕时间，SRT 为 HH:MM:SS,mmm，WebVTT 为 HH:MM:SS.mmm
+pub fn format_subtitle_time(seconds: f64, vtt: bool) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!("{:02}:{:02}:{:02}{}{:03}",
+        millis / 3_600_000,
+        (millis % 3_600_000) / 60_000,
+        (millis % 60_000) / 1000,
+        if vtt { '.' } else { ',' },
+        millis % 1000)
+}
+
+/// 按键排序的元数据头，以分隔线结尾
+fn format_metadata(metadata: &HashMap<String, Value>) -> String {
+    let mut keys: Vec<&String> = metadata.keys().collect();
+    keys.sort();
+
+    let mut content = String::new();
+    for key in keys {
+        let value = match &metadata[key] {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        content.push_str(&format!("{}: {}\n", key, value));
+    }
+    content.push_str("\n---\n\n");
+    content
+}
+
+/// 列出投票合并时各服务的分歧，每处一行
+fn format_disagreements(segments: &[AudioSegment], results: &HashMap<usize, Transcript>) -> String {
+    let mut lines = Vec::new();
+
+    for (idx, segment) in segments.iter().enumerate() {
+        let transcript = match results.get(&idx) {
+            Some(transcript) => transcript,
+            None => continue,
+        };
+
+        for disagreement in &transcript.disagreements {
+            let alternatives = disagreement.alternatives.iter()
+                .map(|(provider, text)| format!("{}「{}」", provider, text))
+                .collect::<Vec<_>>()
+                .join(" ");
+            lines.push(format!(
+                "[{}] 选用「{}」 {}",
+                format_timestamp(segment.offset + disagreement.start),
+                disagreement.chosen,
+                alternatives
+            ));
+        }
+    }
+
+    lines.join("\n")
+}
+
+/// 去掉相邻片段重叠部分的重复内容
+///
+/// 片段的时间范围与下一片段重叠时，两个片段都识别了重叠的音频。
+/// 两边都有逐词时间戳时以重叠区间的中点为界，前一片段保留中点之前开始的词，
+/// 后一片段保留中点及之后开始的词；否则把前一片段末尾和后一片段开头的文本
+/// 按词模糊对齐，去掉后一片段开头与前一片段重复的部分。
+pub fn merge_overlaps(segments: &[AudioSegment], mut results: HashMap<usize, Transcript>) -> HashMap<usize, Transcript> {
+    for idx in 1..segments.len() {
+        let mut pair = (results.remove(&(idx - 1)), results.remove(&idx));
+        if let (Some(prev_result), Some(next_result)) = (&mut pair.0, &mut pair.1) {
+            merge_pair(&segments[idx - 1], &segments[idx], prev_result, next_result, true);
+        }
+        if let Some(prev_result) = pair.0 {
+            results.insert(idx - 1, prev_result);
+        }
+        if let Some(next_result) = pair.1 {
+            results.insert(idx, next_result);
+        }
+    }
+    results
+}
+
+/// 与 merge_overlaps 相同，另外去掉第一个片段开头与 previous 重复的内容
+///
+/// previous 是上一个part的最后一个片段及其识别结果。上一个part已经保存，
+/// 它的结果不再改动，重叠部分都归它。
+pub fn merge_overlaps_after(
+    previous: Option<(&AudioSegment, &Transcript)>,
+    segments: &[AudioSegment],
+    mut results: HashMap<usize, Transcript>,
+) -> HashMap<usize, Transcript> {
+    if let (Some((prev, prev_result)), Some(first)) = (previous, segments.first()) {
+        if let Some(first_result) = results.get_mut(&0) {
+            let mut prev_result = prev_result.clone();
+            merge_pair(prev, first, &mut prev_result, first_result, false);
+        }
+    }
+    merge_overlaps(segments, results)
+}
+
+/// 去掉一对相邻片段重叠部分的重复内容，trim_prev 为 false 时不改动前一片段
+fn merge_pair(prev: &AudioSegment, next: &AudioSegment, prev_result: &mut Transcript, next_result: &mut Transcript, trim_prev: bool) {
+    let prev_end = prev.offset + prev.duration.unwrap_or(0.0);
+    if prev_end <= next.offset {
+        return;
+    }
+
+    if has_word_timestamps(prev_result) && has_word_timestamps(next_result) {
+        let cut = if trim_prev { (next.offset + prev_end) / 2.0 } else { prev_end };
+        if trim_prev {
+            retain_words(prev_result, |start| prev.offset + start < cut);
+        }
+        retain_words(next_result, |start| next.offset + start >= cut);
+    } else {
+        let tail = overlap_tokens(prev_result, |start, end| end <= start || prev.offset + end > next.offset - OVERLAP_SLACK);
+        let tail: Vec<String> = tail.into_iter().rev().take(MAX_OVERLAP_TOKENS).rev().map(|t| t.text).collect();
+        let head = overlap_tokens(next_result, |start, end| end <= start || next.offset + start < prev_end + OVERLAP_SLACK);
+        let head: Vec<OverlapToken> = head.into_iter().take(MAX_OVERLAP_TOKENS).collect();
+
+        let texts: Vec<String> = head.iter().map(|t| t.text.clone()).collect();
+        let duplicated = duplicated_head(&tail, &texts);
+        if duplicated > 0 {
+            debug!("片段 {} 开头 {} 个词与上一片段重复，已去掉", next.file_name(), duplicated);
+            drop_head(next_result, &head[duplicated - 1]);
+        }
+    }
+}
+
+/// 所有非空语句都带逐词时间戳
+fn has_word_timestamps(transcript: &Transcript) -> bool {
+    let mut utterances = transcript.utterances.iter().filter(|u| !u.text.trim().is_empty()).peekable();
+    utterances.peek().is_some() && utterances.all(|u| !u.words.is_empty())
+}
+
+/// 按开始时间（相对片段）保留词，语句的文本、时间和置信度随之更新，空语句被删掉
+fn retain_words(transcript: &mut Transcript, keep: impl Fn(f64) -> bool) {
+    for utterance in &mut transcript.utterances {
+        if utterance.words.is_empty() {
+            if !keep(utterance.start) {
+                utterance.text.clear();
+            }
+            continue;
+        }
+        let before = utterance.words.len();
+        utterance.words.retain(|w| keep(w.start));
+        if utterance.words.len() == before {
+            continue;
+        }
+        let texts: Vec<String> = utterance.words.iter().map(|w| w.text.trim().to_string()).collect();
+        utterance.text = join_segment_texts(&texts);
+        if let (Some(first), Some(last)) = (utterance.words.first(), utterance.words.last()) {
+            utterance.start = first.start;
+            utterance.end = last.end;
+        }
+        if utterance.confidence.is_some() {
+            utterance.confidence = mean_confidence(&utterance.words).or(utterance.confidence);
+        }
+    }
+    transcript.utterances.retain(|u| !u.text.trim().is_empty());
+}
+
+/// 对齐用的词：中文按字，其他按字母数字连写的串，标点和空白不计
+#[derive(Debug, Clone)]
+struct OverlapToken {
+    text: String,
+    utterance: usize,
+    /// 词在语句文本中的结束位置（字节）
+    end: usize,
+}
+
+/// 按顺序列出符合条件的语句中的词，条件参数为语句的 (start, end)
+fn overlap_tokens(transcript: &Transcript, include: impl Fn(f64, f64) -> bool) -> Vec<OverlapToken> {
+    let mut tokens = Vec::new();
+    for (utterance_idx, utterance) in transcript.utterances.iter().enumerate() {
+        if !include(utterance.start, utterance.end) {
+            continue;
+        }
+        let mut word: Option<(usize, String)> = None;
+        for (pos, c) in utterance.text.char_indices() {
+            if c.is_alphanumeric() && !is_cjk(c) {
+                word.get_or_insert_with(|| (pos, String::new())).1.extend(c.to_lowercase());
+                continue;
+            }
+            if let Some((_, text)) = word.take() {
+                tokens.push(OverlapToken { text, utterance: utterance_idx, end: pos });
+            }
+            if is_cjk(c) && c.is_alphanumeric() {
+                tokens.push(OverlapToken { text: c.to_string(), utterance: utterance_idx, end: pos + c.len_utf8() });
+            }
+        }
+        if let Some((_, text)) = word {
+            tokens.push(OverlapToken { text, utterance: utterance_idx, end: utterance.text.len() });
+        }
+    }
+    tokens
+}
+
+/// 后一片段开头与前一片段末尾重复的词数，对不上时为 0
+///
+/// 比较前一片段的后 k 个词和后一片段的前 len 个词，得分为词数减去两倍编辑距离，
+/// 取得分最高的；得分相同时少删，边界处被切断的词宁可重复也不丢。
+fn duplicated_head(tail: &[String], head: &[String]) -> usize {
+    let mut best = (0.0, 0);
+    for k in MIN_OVERLAP_TOKENS..=tail.len() {
+        let suffix = &tail[tail.len() - k..];
+        // 边界处的词常被切坏，允许两边差一个词
+        for len in k.saturating_sub(1).max(MIN_OVERLAP_TOKENS)..=(k + 1).min(head.len()) {
+            let distance = levenshtein(suffix, &head[..len]) as f64;
+            let size = k.max(len) as f64;
+            if distance > size * MAX_OVERLAP_DIFF {
+                continue;
+            }
+            let score = size - 2.0 * distance;
+            if score > best.0 || (score == best.0 && len < best.1) {
+                best = (score, len);
+            }
+        }
+    }
+    best.1
+}
+
+/// 删掉 last 及其之前的内容，被截断的语句按删掉的字符比例推后开始时间
+fn drop_head(transcript: &mut Transcript, last: &OverlapToken) {
+    transcript.utterances.drain(..last.utterance);
+    let utterance = &mut transcript.utterances[0];
+
+    let total_chars = utterance.text.chars().count().max(1);
+    let rest = utterance.text[last.end..]
+        .trim_start_matches(|c: char| !c.is_alphanumeric())
+        .to_string();
+    let removed = total_chars - rest.chars().count();
+    if utterance.end > utterance.start {
+        utterance.start += (utterance.end - utterance.start) * removed as f64 / total_chars as f64;
+    }
+    utterance.text = rest;
+    utterance.words.clear();
+
+    transcript.utterances.retain(|u| !u.text.trim().is_empty());
+}
+
+/// 统计各服务识别的片段数，例如 "bcut 12, jianying 3"
+pub fn summarize_providers(segment_results: &HashMap<usize, Transcript>) -> String {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    let mut indices: Vec<&usize> = segment_results.keys().collect();
+    indices.sort();
+
+    for idx in indices {
+        let provider = segment_results[idx].provider.as_str();
+        match counts.iter_mut().find(|(name, _)| *name == provider) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((provider, 1)),
+        }
+    }
+
+    counts.iter()
+        .map(|(name, count)| format!("{} {}", name, count))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// 按句末标点把文本分成段落
+fn split_paragraphs(text: &str) -> String {
+    let mut paragraphs = Vec::new();
+    let mut current = String::new();
+    let mut sentences = 0;
+
+    for c in text.chars() {
+        current.push(c);
+        if matches!(c, '。' | '！' | '？' | '.' | '!' | '?') {
+            sentences += 1;
+            if sentences >= SENTENCES_PER_PARAGRAPH {
+                paragraphs.push(current.trim().to_string());
+                current.clear();
+                sentences = 0;
+            }
+        }
+    }
+
+    if !current.trim().is_empty() {
+        paragraphs.push(current.trim().to_string());
+    }
+
+    paragraphs.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::asr::base::{Utterance, Word};
+
+    fn segments(count: usize) -> Vec<AudioSegment> {
+        (0..count)
+            .map(|i| AudioSegment::new(Path::new(&format!("part_{}.mp3", i)), i as f64 * 30.0, Some(30.0)))
+            .collect()
+    }
+
+    /// 每个片段的 (起点, 说话人, 文本)
+    fn results(segments: &[&[(f64, Option<&str>, &str)]]) -> HashMap<usize, Transcript> {
+        segments.iter().enumerate()
+            .map(|(idx, utterances)| {
+                let utterances = utterances.iter()
+                    .map(|(start, speaker, text)| {
+                        let mut utterance = Utterance::new(*start, start + 2.0, text);
+                        utterance.speaker = speaker.map(str::to_string);
+                        utterance
+                    })
+                    .collect();
+                (idx, Transcript::new("mock", utterances))
+            })
+            .collect()
+    }
+
+    fn processor(format_text: bool, include_timestamps: bool) -> TextProcessor {
+        TextProcessor::new(PathBuf::from("out"), format_text, include_timestamps, None)
+    }
+
+    #[test]
+    fn empty_text_keeps_the_metadata_header() {
+        let metadata = HashMap::from([
+            ("音频长度".to_string(), Value::String("600秒".to_string())),
+            ("原始文件".to_string(), Value::String("silence.mp3".to_string())),
+        ]);
+        assert_eq!(
+            processor(true, false).prepare_empty_text(&metadata, "（未检测到语音）"),
+            "原始文件: silence.mp3\n音频长度: 600秒\n\n---\n\n（未检测到语音）\n"
+        );
+    }
+
+    #[test]
+    fn speaker_turns_are_prefixed_with_labels() {
+        let segments = segments(2);
+        let results = results(&[
+            &[(0.0, Some("spk_b"), "你好。"), (2.0, Some("spk_b"), "我是主持人。")],
+            &[(0.0, Some("spk_a"), "大家好。"), (3.0, Some("spk_b"), "欢迎。")],
+        ]);
+        let speakers = SpeakerLabels::collect(&segments, &results);
+
+        let text = processor(false, false).prepare_result_text(&segments, &results, &speakers, None).unwrap();
+        assert_eq!(text, "[Speaker 1] 你好。 我是主持人。\n[Speaker 2] 大家好。\n[Speaker 1] 欢迎。\n");
+
+        let text = processor(false, true).prepare_result_text(&segments, &results, &speakers, None).unwrap();
+        assert_eq!(text.lines().nth(2), Some("[00:00:30] [Speaker 2] 大家好。"));
+    }
+
+    #[test]
+    fn subtitles_tag_speakers() {
+        let segments = segments(2);
+        let results = results(&[&[(0.5, Some("1"), "第一句")], &[(1.0, None, "第二句")]]);
+        let speakers = SpeakerLabels::collect(&segments, &results);
+        let processor = processor(false, false);
+
+        assert_eq!(
+            processor.prepare_subtitles(&segments, &results, &speakers, OutputFormat::Srt),
+            "1\n00:00:00,500 --> 00:00:02,500\n[Speaker 1] 第一句\n\n2\n00:00:31,000 --> 00:00:33,000\n第二句\n"
+        );
+        assert_eq!(
+            processor.prepare_subtitles(&segments, &results, &speakers, OutputFormat::Vtt),
+            "WEBVTT\n\n00:00:00.500 --> 00:00:02.500\n<v Speaker 1>第一句\n\n00:00:31.000 --> 00:00:33.000\n第二句\n"
+        );
+    }
+
+    #[test]
+    fn json_lists_speakers_per_utterance() {
+        let segments = segments(3);
+        let mut results = results(&[&[(0.0, Some("x"), "一")], &[(0.0, Some("y"), "二")], &[(0.0, None, "三")]]);
+        results.remove(&1);
+        let speakers = SpeakerLabels::collect(&segments, &results);
+
+        let json: Value = serde_json::from_str(
+            &processor(false, false).prepare_json(&segments, &results, &speakers, None).unwrap()
+        ).unwrap();
+
+        assert_eq!(json["speakers"], serde_json::json!(["Speaker 1"]));
+        assert_eq!(json["utterances"][0]["speaker"], "Speaker 1");
+        assert_eq!(json["utterances"][1]["start"], 60.0);
+        assert!(json["utterances"][1].get("speaker").is_none());
+        assert_eq!(json["failed_segments"], serde_json::json!([30.0]));
+    }
+
+    #[test]
+    fn extended_labels_keep_numbers_across_parts() {
+        let segments = segments(1);
+        let first_part = results(&[&[(0.0, Some("b"), "一")]]);
+        let second_part = results(&[&[(0.0, Some("a"), "二"), (2.0, Some("b"), "三")]]);
+
+        let mut speakers = SpeakerLabels::default();
+        speakers.extend(&segments, &first_part);
+        speakers.extend(&segments, &second_part);
+
+        let text = processor(false, false).prepare_result_text(&segments, &second_part, &speakers, None).unwrap();
+        assert_eq!(text, "[Speaker 2] 二\n[Speaker 1] 三\n");
+        assert_eq!(speakers.labels(), vec!["Speaker 1", "Speaker 2"]);
+    }
+
+    /// 两个各 35 秒、重叠 5 秒的片段
+    fn overlapping_segments() -> Vec<AudioSegment> {
+        vec![
+            AudioSegment::new(Path::new("part_0.mp3"), 0.0, Some(35.0)),
+            AudioSegment::new(Path::new("part_1.mp3"), 30.0, Some(35.0)),
+        ]
+    }
+
+    #[test]
+    fn overlap_is_cut_at_the_midpoint_with_word_timestamps() {
+        let word = |start: f64, text: &str| Word { start, end: start + 0.4, text: text.to_string(), confidence: Some(0.9) };
+        let mut first = Utterance::new(31.0, 34.5, "see you at the");
+        first.words = vec![word(31.0, "see"), word(31.8, "you"), word(32.9, "at"), word(34.0, "the")];
+        let mut second = Utterance::new(0.5, 5.0, "you at the station");
+        second.words = vec![word(1.8, "you"), word(2.9, "at"), word(4.0, "the"), word(4.6, "station")];
+        let results = HashMap::from([
+            (0, Transcript::new("mock", vec![first])),
+            (1, Transcript::new("mock", vec![second])),
+        ]);
+
+        let merged = merge_overlaps(&overlapping_segments(), results);
+        assert_eq!(merged[&0].text(), "see you");
+        assert_eq!(merged[&1].text(), "at the station");
+        assert_eq!(merged[&1].utterances[0].start, 2.9);
+    }
+
+    #[test]
+    fn overlap_is_aligned_by_text_without_word_timestamps() {
+        let results = results(&[
+            &[(28.0, None, "今天我们来聊一聊天气预报")],
+            &[(0.0, None, "聊一聊天气预报，明天会下雨。")],
+        ]);
+        let merged = merge_overlaps(&overlapping_segments(), results);
+        assert_eq!(merged[&0].text(), "今天我们来聊一聊天气预报");
+        assert_eq!(merged[&1].text(), "明天会下雨。");
+        assert!(merged[&1].utterances[0].start > 0.0);
+    }
+
+    #[test]
+    fn fuzzy_alignment_tolerates_a_garbled_boundary_word() {
+        let tail: Vec<String> = "we will meet at the stat".split(' ').map(str::to_string).collect();
+        let head: Vec<String> = "meet at the station tomorrow".split(' ').map(str::to_string).collect();
+        assert_eq!(duplicated_head(&tail, &head), 3);
+        let unrelated: Vec<String> = "something else entirely".split(' ').map(str::to_string).collect();
+        assert_eq!(duplicated_head(&tail, &unrelated), 0);
+    }
+
+    #[test]
+    fn overlap_with_the_previous_part_only_trims_the_current_part() {
+        let word = |start: f64, text: &str| Word { start, end: start + 0.4, text: text.to_string(), confidence: Some(0.9) };
+        let segments = overlapping_segments();
+        let mut first = Utterance::new(31.0, 34.5, "see you at the");
+        first.words = vec![word(31.0, "see"), word(31.8, "you"), word(32.9, "at"), word(34.0, "the")];
+        let previous = Transcript::new("mock", vec![first]);
+        let mut second = Utterance::new(0.5, 5.0, "you at the station");
+        second.words = vec![word(1.8, "you"), word(2.9, "at"), word(4.0, "the"), word(5.2, "station")];
+        let current = HashMap::from([(0, Transcript::new("mock", vec![second]))]);
+
+        let merged = merge_overlaps_after(Some((&segments[0], &previous)), &segments[1..], current);
+        assert_eq!(merged[&0].text(), "station");
+
+        let current = results(&[&[(0.0, None, "聊一聊天气预报，明天会下雨。")]]);
+        let previous = Transcript::new("mock", vec![Utterance::new(28.0, 35.0, "今天我们来聊一聊天气预报")]);
+        let merged = merge_overlaps_after(Some((&segments[0], &previous)), &segments[1..], current);
+        assert_eq!(merged[&0].text(), "明天会下雨。");
+    }
+
+    #[test]
+    fn segments_without_overlap_are_untouched() {
+        let original = results(&[&[(0.0, None, "一二三")], &[(0.0, None, "一二三")]]);
+        assert_eq!(merge_overlaps(&segments(2), original.clone()), original);
+    }
+
+    #[test]
+    fn summarize_providers_counts_in_segment_order() {
+        let results: HashMap<usize, Transcript> = [(2, "bcut"), (0, "jianying"), (1, "bcut"), (3, "jianying"), (4, "bcut")]
+            .iter()
+            .map(|(idx, provider)| (*idx, Transcript::new(provider, vec![])))
+            .collect();
+        assert_eq!(summarize_providers(&results), "jianying 2, bcut 3");
+        assert_eq!(summarize_providers(&HashMap::new()), "");
+    }
+}
@@ -1,3 +1,448 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use anyhow::Result;
+use log::{info, warn, debug};
+use tokio::runtime::RuntimeFlavor;
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+
+use crate::asr::base::{AsrError, AudioSegment, Transcript};
+use crate::asr::cache::AsrCache;
+use crate::asr::language;
+use crate::asr::manager::{AsrManager, AsrStrategy};
+use crate::core::audio_extractor::ProgressCallback;
+
+/// 转写处理器，负责并发识别音频片段
 pub struct TranscriptionProcessor {
-    // Transcription processing functionality
+    asr_manager: Arc<AsrManager>,
+    strategy: AsrStrategy,
+    cache: Option<Arc<AsrCache>>,
+    temp_segments_dir: PathBuf,
+    max_workers: usize,
+    max_retries: u32,
+    progress_callback: Option<Arc<ProgressCallback>>,
+    interrupt_flag: Arc<Mutex<bool>>,
+}
+
+impl TranscriptionProcessor {
+    /// 创建新的转写处理器
+    pub fn new(
+        asr_manager: Arc<AsrManager>,
+        temp_segments_dir: &Path,
+        max_workers: usize,
+        max_retries: u32,
+        progress_callback: Option<Arc<ProgressCallback>>,
+        interrupt_flag: Arc<Mutex<bool>>,
+    ) -> Self {
+        Self {
+            asr_manager,
+            strategy: AsrStrategy::default(),
+            cache: None,
+            temp_segments_dir: temp_segments_dir.to_path_buf(),
+            max_workers: max_workers.max(1),
+            max_retries,
+            progress_callback,
+            interrupt_flag,
+        }
+    }
+
+    /// 设置片段识别策略
+    pub fn with_strategy(mut self, strategy: AsrStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// 启用识别结果缓存
+    pub fn with_cache(mut self, cache: Arc<AsrCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// 设置中断标志
+    pub fn set_interrupt_flag(&self, value: bool) {
+        let mut flag = self.interrupt_flag.lock().unwrap();
+        *flag = value;
+    }
+
+    /// 是否已被中断
+    fn is_interrupted(&self) -> bool {
+        *self.interrupt_flag.lock().unwrap()
+    }
+
+    /// 片段临时目录
+    pub fn temp_segments_dir(&self) -> &Path {
+        &self.temp_segments_dir
+    }
+
+    /// 并发识别所有片段，返回成功片段的索引和结果
+    pub fn process_audio_segments(&self, segments: &[AudioSegment]) -> Result<HashMap<usize, Transcript>> {
+        self.process_audio_segments_with(segments, &HashMap::new())
+    }
+
+    /// 并发识别所有片段，known 中已有结果的片段（按路径）不再识别
+    pub fn process_audio_segments_with(
+        &self,
+        segments: &[AudioSegment],
+        known: &HashMap<PathBuf, Transcript>,
+    ) -> Result<HashMap<usize, Transcript>> {
+        let mut results: HashMap<usize, Transcript> = segments.iter()
+            .enumerate()
+            .filter_map(|(idx, segment)| known.get(&segment.path).map(|t| (idx, t.clone())))
+            .collect();
+        if !results.is_empty() {
+            debug!("复用 {} 个已识别的片段", results.len());
+        }
+
+        let indices: Vec<usize> = (0..segments.len())
+            .filter(|idx| !results.contains_key(idx))
+            .collect();
+        results.extend(block_on(self.recognize_segments(segments, &indices, "转写")));
+
+        info!("片段识别完成: 成功 {}/{}", results.len(), segments.len());
+        Ok(results)
+    }
+
+    /// 识别几个均匀分布的片段，按结果判断主要语言，返回语言和可复用的采样结果
+    ///
+    /// 服务返回了语言时规范化后采用，否则按文字的书写系统判断，按文本长度加权投票。
+    pub fn detect_language(
+        &self,
+        segments: &[AudioSegment],
+        sample_count: usize,
+    ) -> (Option<String>, HashMap<PathBuf, Transcript>) {
+        let sample_count = sample_count.min(segments.len());
+        if sample_count == 0 {
+            return (None, HashMap::new());
+        }
+
+        let mut indices: Vec<usize> = (0..sample_count)
+            .map(|k| (2 * k + 1) * segments.len() / (2 * sample_count))
+            .collect();
+        indices.dedup();
+
+        let samples: Vec<AudioSegment> = indices.iter()
+            .map(|&idx| segments[idx].clone().with_language(None))
+            .collect();
+        let sample_indices: Vec<usize> = (0..samples.len()).collect();
+        let results = block_on(self.recognize_segments(&samples, &sample_indices, "语言检测"));
+
+        let votes = results.values().filter_map(|transcript| {
+            let text = transcript.text();
+            let detected = transcript.language.as_deref()
+                .and_then(language::normalize)
+                .or_else(|| language::detect(&text).map(str::to_string))?;
+            Some((detected, text.chars().filter(|c| !c.is_whitespace()).count()))
+        });
+        let detected = language::dominant(votes);
+
+        let samples = results.into_iter()
+            .map(|(idx, transcript)| (samples[idx].path.clone(), transcript))
+            .collect();
+
+        (detected, samples)
+    }
+
+    /// 服务是否支持该语言
+    pub fn supports_language(&self, provider: &str, language: Option<&str>) -> bool {
+        self.asr_manager.supports_language(provider, language)
+    }
+
+    /// 重试识别失败的片段，返回合并后的结果
+    pub fn retry_failed_segments(
+        &self,
+        segments: &[AudioSegment],
+        mut results: HashMap<usize, Transcript>,
+    ) -> Result<HashMap<usize, Transcript>> {
+        for attempt in 1..=self.max_retries {
+            let failed: Vec<usize> = (0..segments.len())
+                .filter(|idx| !results.contains_key(idx))
+                .collect();
+
+            if failed.is_empty() || self.is_interrupted() {
+                break;
+            }
+
+            warn!("重试 {} 个失败片段 (第 {}/{} 次)", failed.len(), attempt, self.max_retries);
+
+            let delay = Duration::from_secs(attempt as u64);
+            let retried = block_on(async {
+                tokio::time::sleep(delay).await;
+                self.recognize_segments(segments, &failed, "重试").await
+            });
+
+            results.extend(retried);
+        }
+
+        let failed_count = segments.len().saturating_sub(results.len());
+        if failed_count > 0 {
+            warn!("仍有 {} 个片段识别失败", failed_count);
+        }
+
+        Ok(results)
+    }
+
+    /// 按最大并发数识别指定索引的片段
+    async fn recognize_segments(
+        &self,
+        segments: &[AudioSegment],
+        indices: &[usize],
+        context: &str,
+    ) -> HashMap<usize, Transcript> {
+        let semaphore = Arc::new(Semaphore::new(self.max_workers));
+        let mut tasks = JoinSet::new();
+
+        for &idx in indices {
+            if self.is_interrupted() {
+                warn!("转写被中断，停止提交新的片段");
+                break;
+            }
+
+            let permit = match Arc::clone(&semaphore).acquire_owned().await {
+                Ok(permit) => permit,
+                Err(_) => break,
+            };
+
+            let asr_manager = Arc::clone(&self.asr_manager);
+            let segment = segments[idx].clone();
+            let strategy = self.strategy;
+            let cache = self.cache.clone();
+
+            tasks.spawn(async move {
+                let result = recognize_cached(&asr_manager, cache.as_deref(), &segment, strategy).await;
+                drop(permit);
+                (idx, segment, result)
+            });
+        }
+
+        let total = tasks.len();
+        let mut completed = 0;
+        let mut results = HashMap::new();
+
+        while let Some(joined) = tasks.join_next().await {
+            completed += 1;
+
+            match joined {
+                Ok((idx, _, Ok(transcript))) if !transcript.is_empty() => {
+                    debug!("片段 {} 识别成功 [{}]", idx, transcript.provider);
+                    results.insert(idx, transcript);
+                }
+                Ok((idx, segment, Ok(_))) => {
+                    warn!("片段 {} 识别结果为空: {}", idx, segment.file_name());
+                }
+                Ok((idx, segment, Err(e))) => {
+                    warn!("片段 {} 识别失败: {} ({})", idx, segment.file_name(), e);
+                }
+                Err(e) => {
+                    warn!("识别任务异常退出: {}", e);
+                }
+            }
+
+            if let Some(callback) = &self.progress_callback {
+                callback(
+                    completed,
+                    total,
+                    Some(format!("{}片段 {}/{}", context, completed, total)),
+                    Some(context.to_string()),
+                );
+            }
+        }
+
+        results
+    }
+}
+
+/// 先查缓存，未命中时调用服务并保存有效结果
+async fn recognize_cached(
+    asr_manager: &AsrManager,
+    cache: Option<&AsrCache>,
+    segment: &AudioSegment,
+    strategy: AsrStrategy,
+) -> std::result::Result<Transcript, AsrError> {
+    let cache = cache.and_then(|cache| match AsrCache::hash_file(&segment.path) {
+        Ok(hash) => Some((cache, hash)),
+        Err(e) => {
+            debug!("无法计算片段哈希，跳过缓存: {} ({})", segment.file_name(), e);
+            None
+        }
+    });
+
+    // 指定语言时识别结果可能不同，语言也作为缓存键的一部分
+    let with_language = |profile: String| match &segment.language {
+        Some(language) => format!("{}|lang={}", profile, language),
+        None => profile,
+    };
+
+    if let Some((cache, hash)) = &cache {
+        for profile in asr_manager.cache_profiles(strategy).into_iter().map(with_language) {
+            if let Some(transcript) = cache.get(hash, &profile) {
+                cache.record_lookup(true);
+                return Ok(transcript);
+            }
+        }
+        cache.record_lookup(false);
+    }
+
+    let result = asr_manager.recognize_with(segment, strategy).await;
+
+    if let (Some((cache, hash)), Ok(transcript)) = (&cache, &result) {
+        if !transcript.is_empty() {
+            if let Some(profile) = asr_manager.cache_profile_for(strategy, transcript).map(with_language) {
+                if let Err(e) = cache.put(hash, &profile, transcript) {
+                    warn!("写入ASR缓存失败: {}", e);
+                }
+            }
+        }
+    }
+
+    result
+}
+
+/// 在同步代码中等待异步任务完成
+///
+/// 多线程运行时中用 block_in_place 就地等待；单线程运行时（如 #[tokio::test]）中
+/// block_in_place 会 panic，改在独立线程上用新的运行时执行
+fn block_on<F>(future: F) -> F::Output
+where
+    F: Future + Send,
+    F::Output: Send,
+{
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(|| handle.block_on(future))
+        }
+        Ok(_) => std::thread::scope(|scope| {
+            scope.spawn(|| run_to_completion(future))
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        }),
+        Err(_) => run_to_completion(future),
+    }
+}
+
+/// 在新建的单线程运行时中执行完 future
+fn run_to_completion<F: Future>(future: F) -> F::Output {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("无法创建异步运行时")
+        .block_on(future)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use crate::asr::base::{AsrCapabilities, AsrProvider, Utterance};
+    use crate::asr::selector::AsrSelector;
+
+    /// 按片段文件名返回预设文本和语言，并记录收到的请求
+    struct ScriptedProvider {
+        results: HashMap<&'static str, (&'static str, Option<&'static str>)>,
+        seen: Mutex<Vec<(String, Option<String>)>>,
+    }
+
+    #[async_trait]
+    impl AsrProvider for ScriptedProvider {
+        fn name(&self) -> &str {
+            "scripted"
+        }
+
+        fn capabilities(&self) -> AsrCapabilities {
+            AsrCapabilities::default()
+        }
+
+        async fn is_available(&self) -> bool {
+            true
+        }
+
+        async fn recognize(&self, segment: &AudioSegment) -> std::result::Result<Transcript, AsrError> {
+            let stem = segment.path.file_stem().unwrap().to_string_lossy().to_string();
+            self.seen.lock().unwrap().push((stem.clone(), segment.language.clone()));
+
+            let (text, language) = self.results.get(stem.as_str()).copied().unwrap_or(("", None));
+            let mut transcript = Transcript::new(self.name(), vec![Utterance::new(0.0, 1.0, text)]);
+            transcript.language = language.map(str::to_string);
+            Ok(transcript)
+        }
+    }
+
+    fn processor(provider: Arc<ScriptedProvider>) -> TranscriptionProcessor {
+        let manager = AsrManager::with_providers(vec![provider], AsrSelector::new(3, Duration::from_secs(60)));
+        TranscriptionProcessor::new(Arc::new(manager), Path::new("segments"), 2, 0, None, Arc::new(Mutex::new(false)))
+    }
+
+    fn segments(count: usize) -> Vec<AudioSegment> {
+        (0..count)
+            .map(|i| AudioSegment::new(Path::new(&format!("segments/part_{}.mp3", i)), i as f64 * 30.0, Some(30.0))
+                .with_language(Some("en".to_string())))
+            .collect()
+    }
+
+    #[test]
+    fn detect_language_samples_evenly_and_weights_by_length() {
+        let provider = Arc::new(ScriptedProvider {
+            results: HashMap::from([
+                ("part_1", ("this sentence is a long english sentence", None)),
+                ("part_5", ("你好啊", None)),
+                ("part_8", ("谢谢你", None)),
+            ]),
+            seen: Mutex::new(Vec::new()),
+        });
+
+        let (detected, samples) = processor(Arc::clone(&provider)).detect_language(&segments(10), 3);
+
+        assert_eq!(detected.as_deref(), Some("en"));
+        let mut seen = provider.seen.lock().unwrap().clone();
+        seen.sort();
+        // 采样时不带语言，让服务自行判断
+        assert_eq!(seen, vec![
+            ("part_1".to_string(), None),
+            ("part_5".to_string(), None),
+            ("part_8".to_string(), None),
+        ]);
+        assert_eq!(samples.len(), 3);
+        assert_eq!(samples[Path::new("segments/part_5.mp3")].text(), "你好啊");
+    }
+
+    #[test]
+    fn detect_language_normalizes_reported_languages() {
+        let provider = Arc::new(ScriptedProvider {
+            results: HashMap::from([
+                ("part_0", ("this sentence is a long english sentence", None)),
+                ("part_1", ("你好啊", Some("zh-CN"))),
+                ("part_2", ("nihao zhe shi yong pinyin xie de zhongwen ne", Some("Mandarin"))),
+            ]),
+            seen: Mutex::new(Vec::new()),
+        });
+
+        let (detected, _) = processor(provider).detect_language(&segments(3), 5);
+        assert_eq!(detected.as_deref(), Some("zh"));
+    }
+
+    #[test]
+    fn detect_language_without_segments_detects_nothing() {
+        let provider = Arc::new(ScriptedProvider { results: HashMap::new(), seen: Mutex::new(Vec::new()) });
+        let (detected, samples) = processor(provider).detect_language(&[], 3);
+
+        assert_eq!(detected, None);
+        assert!(samples.is_empty());
+    }
+
+    #[tokio::test]
+    async fn block_on_works_inside_current_thread_runtime() {
+        assert_eq!(block_on(async { 1 + 1 }), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn block_on_works_inside_multi_thread_runtime() {
+        assert_eq!(block_on(async { 2 + 2 }), 4);
+    }
+
+    #[test]
+    fn block_on_works_without_runtime() {
+        assert_eq!(block_on(async { 3 }), 3);
+    }
 }
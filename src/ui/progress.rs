@@ -1,5 +1,3 @@
-use indicatif::{ProgressBar, ProgressStyle};
-
 pub struct Progress {
     // Progress bar functionality
 }
\ No newline at end of file
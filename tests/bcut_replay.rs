@@ -0,0 +1,86 @@
+//! 用录制的夹具回放必剪的上传、提交、建任务和轮询流程
+
+mod common;
+
+use std::time::Duration;
+use serde_json::json;
+
+use audio_processor::asr::base::{AsrError, AsrProvider};
+use audio_processor::asr::bcut::{BcutProvider, DEFAULT_BASE_URL};
+use common::{fixture_dir, replay_client, segment_file, write_fixtures};
+
+fn provider(dir: &std::path::Path) -> BcutProvider {
+    BcutProvider::new()
+        .with_client(replay_client(dir))
+        .with_polling(Duration::ZERO, 5)
+}
+
+#[tokio::test]
+async fn recognizes_a_segment_from_recorded_fixtures() {
+    let dir = tempfile::tempdir().unwrap();
+    let segment = segment_file(dir.path(), 16, Some(3.0));
+
+    let transcript = provider(&fixture_dir("bcut")).recognize(&segment).await.unwrap();
+
+    assert_eq!(transcript.provider, "bcut");
+    assert_eq!(transcript.utterances.len(), 2);
+
+    let first = &transcript.utterances[0];
+    assert_eq!(first.text, "大家好");
+    assert_eq!((first.start, first.end), (0.12, 0.98));
+    assert_eq!(first.confidence, Some(0.9));
+    assert_eq!(first.words.iter().map(|w| w.text.as_str()).collect::<Vec<_>>(), ["大家", "好"]);
+
+    // 没有整句置信度时取各词的平均值
+    let second = &transcript.utterances[1];
+    assert_eq!(second.text, "今天讲解码器");
+    assert!((second.confidence.unwrap() - 0.5).abs() < 1e-6);
+}
+
+#[tokio::test]
+async fn rejects_upload_slots_that_do_not_cover_the_file() {
+    let fixtures = tempfile::tempdir().unwrap();
+    write_fixtures(fixtures.path(), &[
+        ("POST", &format!("{}/resource/create", DEFAULT_BASE_URL), 200, json!({
+            "code": 0,
+            "data": {
+                "in_boss_key": "k", "resource_id": "r", "upload_id": "u",
+                "upload_urls": ["https://upos.example.com/res-1?partNumber=1"],
+                "per_size": 8,
+            },
+        })),
+    ]);
+    // 16 字节的片段只分到一个 8 字节的块
+    let segment = segment_file(fixtures.path(), 16, Some(3.0));
+
+    match provider(fixtures.path()).recognize(&segment).await {
+        Err(AsrError::InvalidResponse(message)) => assert!(message.contains("上传地址不足"), "{}", message),
+        other => panic!("应为 InvalidResponse: {:?}", other.map(|t| t.text())),
+    }
+}
+
+#[tokio::test]
+async fn reports_failed_tasks() {
+    let fixtures = tempfile::tempdir().unwrap();
+    let upload_url = "https://upos.example.com/res-1?partNumber=1";
+    write_fixtures(fixtures.path(), &[
+        ("POST", &format!("{}/resource/create", DEFAULT_BASE_URL), 200, json!({
+            "code": 0,
+            "data": { "in_boss_key": "k", "resource_id": "r", "upload_id": "u", "upload_urls": [upload_url], "per_size": 1024 },
+        })),
+        ("PUT", upload_url, 200, json!("")),
+        ("POST", &format!("{}/resource/create/complete", DEFAULT_BASE_URL), 200, json!({
+            "code": 0, "data": { "download_url": "https://boss.example.com/r.mp3" },
+        })),
+        ("POST", &format!("{}/task", DEFAULT_BASE_URL), 200, json!({ "code": 0, "data": { "task_id": "t" } })),
+        ("GET", &format!("{}/task/result?task_id=t&model_id=7", DEFAULT_BASE_URL), 200, json!({
+            "code": 0, "data": { "state": 3, "remark": "音频无法识别" },
+        })),
+    ]);
+    let segment = segment_file(fixtures.path(), 16, Some(3.0));
+
+    match provider(fixtures.path()).recognize(&segment).await {
+        Err(AsrError::TaskFailed(remark)) => assert_eq!(remark, "音频无法识别"),
+        other => panic!("应为 TaskFailed: {:?}", other.map(|t| t.text())),
+    }
+}
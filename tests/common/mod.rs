@@ -0,0 +1,59 @@
+//! 集成测试共用的辅助函数
+
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde_json::{json, Value};
+
+use audio_processor::asr::base::{AsrError, AudioSegment};
+use audio_processor::asr::http::HttpClient;
+
+/// 仓库中录制好的夹具目录
+pub fn fixture_dir(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fixtures").join(name)
+}
+
+/// 从夹具目录回放的HTTP客户端
+pub fn replay_client(dir: &Path) -> HttpClient {
+    let config = HashMap::from([
+        ("http_fixtures_mode".to_string(), json!("replay")),
+        ("http_fixtures_dir".to_string(), json!(dir.to_string_lossy())),
+    ]);
+    HttpClient::from_config(&config).expect("回放客户端")
+}
+
+/// 把 (方法, URL, 状态码, 响应体) 依次写为夹具文件，响应体为字符串时原样保存
+pub fn write_fixtures(dir: &Path, interactions: &[(&str, &str, u16, Value)]) {
+    for (idx, (method, url, status, body)) in interactions.iter().enumerate() {
+        let body = match body {
+            Value::String(text) => text.clone(),
+            other => other.to_string(),
+        };
+        let interaction = json!({ "method": method, "url": url, "status": status, "body": body });
+        fs::write(dir.join(format!("{:05}.json", idx + 1)), interaction.to_string()).unwrap();
+    }
+}
+
+/// 写一个内容固定的片段文件
+pub fn segment_file(dir: &Path, size: usize, duration: Option<f64>) -> AudioSegment {
+    let path = dir.join("part_0000.mp3");
+    fs::write(&path, vec![0x5a; size]).unwrap();
+    AudioSegment::new(&path, 0.0, duration)
+}
+
+/// 错误种类的名称，便于在断言中比较
+pub fn kind(error: &AsrError) -> &'static str {
+    match error {
+        AsrError::Unavailable(_) => "Unavailable",
+        AsrError::Request(_) => "Request",
+        AsrError::QuotaExceeded(_) => "QuotaExceeded",
+        AsrError::Unauthorized(_) => "Unauthorized",
+        AsrError::Transient(_) => "Transient",
+        AsrError::InvalidResponse(_) => "InvalidResponse",
+        AsrError::TaskFailed(_) => "TaskFailed",
+        AsrError::Timeout(_) => "Timeout",
+        AsrError::Io(_) => "Io",
+    }
+}
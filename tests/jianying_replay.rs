@@ -0,0 +1,53 @@
+//! 用录制的夹具回放剪映的签名、上传、提交和查询流程
+
+mod common;
+
+use std::time::Duration;
+use serde_json::json;
+
+use audio_processor::asr::base::AsrProvider;
+use audio_processor::asr::jianying::{JianyingProvider, DEFAULT_BASE_URL};
+use common::{fixture_dir, kind, replay_client, segment_file, write_fixtures};
+
+fn provider(dir: &std::path::Path) -> JianyingProvider {
+    JianyingProvider::new()
+        .with_client(replay_client(dir))
+        .with_polling(Duration::ZERO, 5)
+}
+
+#[tokio::test]
+async fn recognizes_a_segment_from_recorded_fixtures() {
+    let dir = tempfile::tempdir().unwrap();
+    let segment = segment_file(dir.path(), 16, Some(3.2));
+
+    let transcript = provider(&fixture_dir("jianying")).recognize(&segment).await.unwrap();
+
+    assert_eq!(transcript.provider, "jianying");
+    let utterances: Vec<_> = transcript.utterances.iter()
+        .map(|u| (u.text.as_str(), u.start, u.end))
+        .collect();
+    assert_eq!(utterances, [("欢迎收听", 0.0, 1.5), ("这是第二句", 1.6, 3.2)]);
+}
+
+#[tokio::test]
+async fn rejects_unsuccessful_uploads() {
+    let fixtures = tempfile::tempdir().unwrap();
+    let store = "https://tos.example.com/bucket/audio?partNumber=1&uploadID=up";
+    write_fixtures(fixtures.path(), &[
+        ("POST", &format!("{}/lv/v1/upload_sign", DEFAULT_BASE_URL), 200, json!({
+            "ret": "0",
+            "data": { "access_key_id": "ak", "secret_access_key": "sk", "session_token": "token" },
+        })),
+        ("GET", "https://vod.bytedanceapi.com/?Action=ApplyUploadInner&FileSize=16&FileType=object&IsInner=1&SpaceName=lv-mac-recognition&Version=2020-11-19&s=5y0udbjapi", 200, json!({
+            "Result": { "UploadAddress": {
+                "StoreInfos": [{ "StoreUri": "bucket/audio", "Auth": "auth", "UploadID": "up" }],
+                "UploadHosts": ["https://tos.example.com"],
+            } },
+        })),
+        ("PUT", store, 200, json!({ "success": 1, "error": { "code": 4000 } })),
+    ]);
+    let segment = segment_file(fixtures.path(), 16, Some(3.2));
+
+    let error = provider(fixtures.path()).recognize(&segment).await.unwrap_err();
+    assert_eq!(kind(&error), "Request", "{}", error);
+}
@@ -0,0 +1,63 @@
+//! 用模拟服务驱动ASR管理器的熔断，回退顺序由 manager 的单元测试覆盖
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use audio_processor::asr::base::{AsrError, AsrProvider, AudioSegment};
+use audio_processor::asr::manager::AsrManager;
+use audio_processor::asr::mock::{MockOptions, MockProvider};
+use audio_processor::asr::selector::AsrSelector;
+
+fn mock(name: &str, options: MockOptions) -> Arc<dyn AsrProvider> {
+    Arc::new(MockProvider::new(options).with_name(name))
+}
+
+fn failing() -> MockOptions {
+    MockOptions { failure_rate: 1.0, ..Default::default() }
+}
+
+/// 按给定顺序注册服务的管理器
+fn chain(providers: Vec<Arc<dyn AsrProvider>>, failure_threshold: u32) -> AsrManager {
+    let selector = AsrSelector::new(failure_threshold, Duration::from_secs(60));
+    for (idx, provider) in providers.iter().enumerate() {
+        selector.register(provider.name(), idx as u32);
+    }
+    AsrManager::with_providers(providers, selector)
+}
+
+fn segment(idx: usize) -> AudioSegment {
+    AudioSegment::new(Path::new(&format!("/tmp/fallback/part_{:04}.wav", idx)), idx as f64 * 10.0, Some(10.0))
+}
+
+fn stat(manager: &AsrManager, name: &str, key: &str) -> u64 {
+    manager.get_service_stats()[name][key].as_u64().unwrap()
+}
+
+#[tokio::test]
+async fn opens_the_circuit_after_consecutive_failures() {
+    let manager = chain(vec![mock("primary", failing()), mock("secondary", MockOptions::default())], 2);
+
+    for idx in 0..2 {
+        assert_eq!(manager.recognize(&segment(idx)).await.unwrap().provider, "secondary");
+    }
+    let stats = manager.get_service_stats();
+    assert_eq!(stats["primary"]["available"], false);
+    assert!(stats["primary"]["skip_reason"].as_str().unwrap().contains("连续失败 2 次"));
+
+    // 熔断期间不再调用失败的服务
+    for idx in 2..5 {
+        assert_eq!(manager.recognize(&segment(idx)).await.unwrap().provider, "secondary");
+    }
+    assert_eq!(stat(&manager, "primary", "count"), 2);
+    assert_eq!(stat(&manager, "secondary", "success"), 5);
+}
+
+#[tokio::test]
+async fn reports_unavailable_when_every_circuit_is_open() {
+    let manager = chain(vec![mock("primary", failing())], 1);
+
+    assert!(matches!(manager.recognize(&segment(0)).await, Err(AsrError::Transient(_))));
+    assert!(matches!(manager.recognize(&segment(1)).await, Err(AsrError::Unavailable(_))));
+    assert_eq!(stat(&manager, "primary", "count"), 1);
+}